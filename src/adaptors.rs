@@ -44,17 +44,71 @@ pub mod erased;
 #[cfg(feature = "throttle")]
 pub mod throttle;
 
+/// [`Retry`] bot adaptor which allows automatically retry requests that failed
+/// with transient errors.
+///
+/// [`Retry`]: retry::Retry
+#[cfg(feature = "retry")]
+pub mod retry;
+
+/// [`Record`] bot adaptor which records requests and responses, and
+/// [`Replay`] which replays them.
+///
+/// [`Record`]: record::Record
+/// [`Replay`]: record::Replay
+#[cfg(feature = "record")]
+pub mod record;
+
+/// [`Metrics`] bot adaptor which collects metrics of requests.
+///
+/// [`Metrics`]: metrics::Metrics
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+/// [`Validate`] bot adaptor which checks requests against limits of the Bot
+/// API before sending them.
+///
+/// [`Validate`]: validate::Validate
+#[cfg(feature = "validate")]
+pub mod validate;
+
+/// [`CacheFileIds`] bot adaptor which reuses `file_id`s of already uploaded
+/// files.
+///
+/// [`CacheFileIds`]: cache_file_ids::CacheFileIds
+#[cfg(feature = "cache_file_ids")]
+pub mod cache_file_ids;
+
+/// [`AutoMigrate`] bot adaptor which handles migrations of groups to
+/// supergroups.
+///
+/// [`AutoMigrate`]: auto_migrate::AutoMigrate
+#[cfg(feature = "auto_migrate")]
+pub mod auto_migrate;
+
 mod parse_mode;
 
+#[cfg(feature = "auto_migrate")]
+pub use auto_migrate::AutoMigrate;
 #[cfg(feature = "auto_send")]
 pub use auto_send::AutoSend;
+#[cfg(feature = "cache_file_ids")]
+pub use cache_file_ids::CacheFileIds;
 #[cfg(feature = "cache_me")]
 pub use cache_me::CacheMe;
 #[cfg(feature = "erased")]
 pub use erased::ErasedRequester;
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+#[cfg(feature = "record")]
+pub use record::Record;
+#[cfg(feature = "retry")]
+pub use retry::Retry;
 #[cfg(feature = "throttle")]
 pub use throttle::Throttle;
 #[cfg(feature = "trace_adaptor")]
 pub use trace::Trace;
+#[cfg(feature = "validate")]
+pub use validate::Validate;
 
 pub use parse_mode::DefaultParseMode;
@@ -0,0 +1,523 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    future::BoxFuture,
+    task::{Context, Poll},
+};
+use url::Url;
+
+use crate::{
+    errors::AsResponseParameters,
+    requests::{HasPayload, Output, Request, Requester},
+    types::*,
+};
+
+/// Automatic handling of group to supergroup migrations.
+///
+/// When a group is upgraded to a supergroup, requests to the group fail with
+/// [`RequestError::MigrateToChatId`]. This bot wrapper catches such errors,
+/// replaces `chat_id` of the request with the identifier of the supergroup and
+/// sends the request once again.
+///
+/// Migrations are remembered in a [`MigrationStore`] (by default in memory,
+/// see [`MemoryStore`]), so that future requests to the old group are sent
+/// directly to the supergroup. To update your own database when a migration
+/// is detected, use [`AutoMigrate::on_migrate`] or implement
+/// [`MigrationStore`] on top of the database.
+///
+/// Only requests with a `chat_id` field are affected, note that `from_chat_id`
+/// of [`ForwardMessage`] and [`CopyMessage`] is not rewritten.
+///
+/// [`RequestError::MigrateToChatId`]: crate::RequestError::MigrateToChatId
+/// [`ForwardMessage`]: crate::payloads::ForwardMessage
+/// [`CopyMessage`]: crate::payloads::CopyMessage
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{requests::RequesterExt, Bot};
+///
+/// let bot = Bot::new("TOKEN").auto_migrate().on_migrate(|from, to| {
+///     log::info!("Group {} was migrated to supergroup {}", from, to);
+/// });
+/// # let _ = bot;
+/// ```
+#[derive(Clone)]
+pub struct AutoMigrate<B, S = MemoryStore> {
+    bot: B,
+    store: Arc<S>,
+    on_migrate: Option<Arc<MigrateHook>>,
+}
+
+type MigrateHook = dyn Fn(ChatId, ChatId) + Send + Sync;
+
+impl<B> AutoMigrate<B> {
+    /// Creates new [`AutoMigrate`] which remembers migrations in memory.
+    ///
+    /// Note: it's recommended to use [`RequesterExt::auto_migrate`] instead.
+    ///
+    /// [`RequesterExt::auto_migrate`]: crate::requests::RequesterExt::auto_migrate
+    pub fn new(bot: B) -> Self {
+        Self::with_store(bot, MemoryStore::default())
+    }
+}
+
+impl<B, S> AutoMigrate<B, S> {
+    /// Creates new [`AutoMigrate`] which remembers migrations in `store`.
+    pub fn with_store(bot: B, store: S) -> Self {
+        Self {
+            bot,
+            store: Arc::new(store),
+            on_migrate: None,
+        }
+    }
+
+    /// Sets a callback that is called with the old and the new chat
+    /// identifiers every time a migration is detected.
+    pub fn on_migrate<F>(mut self, f: F) -> Self
+    where
+        F: Fn(ChatId, ChatId) + Send + Sync + 'static,
+    {
+        self.on_migrate = Some(Arc::new(f));
+        self
+    }
+
+    /// Allows to access inner bot
+    pub fn inner(&self) -> &B {
+        &self.bot
+    }
+
+    /// Unwraps inner bot
+    pub fn into_inner(self) -> B {
+        self.bot
+    }
+
+    /// Returns the store of migrations.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<B: fmt::Debug, S: fmt::Debug> fmt::Debug for AutoMigrate<B, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoMigrate")
+            .field("bot", &self.bot)
+            .field("store", &self.store)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A storage of group to supergroup migrations used by [`AutoMigrate`].
+pub trait MigrationStore {
+    /// Returns the identifier of the supergroup to which the group `chat_id`
+    /// was migrated.
+    fn get(&self, chat_id: ChatId) -> Option<ChatId>;
+
+    /// Remembers that the group `from` was migrated to the supergroup `to`.
+    fn insert(&self, from: ChatId, to: ChatId);
+}
+
+/// [`MigrationStore`] which keeps migrations in memory.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    migrations: Mutex<HashMap<ChatId, ChatId>>,
+}
+
+impl MigrationStore for MemoryStore {
+    fn get(&self, chat_id: ChatId) -> Option<ChatId> {
+        self.migrations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(&chat_id)
+            .copied()
+    }
+
+    fn insert(&self, from: ChatId, to: ChatId) {
+        self.migrations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(from, to);
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        AutoMigrateRequest {
+            request: Arc::new($this.inner().$m($($arg),*)),
+            chat_id: |p| &p.chat_id,
+            chat_id_mut: |p| &mut p.chat_id,
+            store: Arc::clone(&$this.store),
+            on_migrate: $this.on_migrate.clone(),
+        }
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        AutoMigrateRequest<B::$T, MS>
+    };
+}
+
+macro_rules! fid {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        $this.inner().$m($($arg),*)
+    };
+}
+
+macro_rules! ftyid {
+    ($T:ident) => {
+        B::$T
+    };
+}
+
+// Note: `MS` is used instead of `S` to not clash with generic parameters of
+//       `Requester` methods
+impl<B, MS> Requester for AutoMigrate<B, MS>
+where
+    B: Requester,
+    B::Err: AsResponseParameters,
+    MS: MigrationStore + Send + Sync + 'static,
+
+    B::SendMessage: Clone + Send + Sync + 'static,
+    B::ForwardMessage: Clone + Send + Sync + 'static,
+    B::CopyMessage: Clone + Send + Sync + 'static,
+    B::SendPhoto: Clone + Send + Sync + 'static,
+    B::SendAudio: Clone + Send + Sync + 'static,
+    B::SendDocument: Clone + Send + Sync + 'static,
+    B::SendVideo: Clone + Send + Sync + 'static,
+    B::SendAnimation: Clone + Send + Sync + 'static,
+    B::SendVoice: Clone + Send + Sync + 'static,
+    B::SendVideoNote: Clone + Send + Sync + 'static,
+    B::SendMediaGroup: Clone + Send + Sync + 'static,
+    B::SendLocation: Clone + Send + Sync + 'static,
+    B::EditMessageLiveLocation: Clone + Send + Sync + 'static,
+    B::StopMessageLiveLocation: Clone + Send + Sync + 'static,
+    B::SendVenue: Clone + Send + Sync + 'static,
+    B::SendContact: Clone + Send + Sync + 'static,
+    B::SendPoll: Clone + Send + Sync + 'static,
+    B::SendDice: Clone + Send + Sync + 'static,
+    B::SendChatAction: Clone + Send + Sync + 'static,
+    B::BanChatMember: Clone + Send + Sync + 'static,
+    B::KickChatMember: Clone + Send + Sync + 'static,
+    B::UnbanChatMember: Clone + Send + Sync + 'static,
+    B::RestrictChatMember: Clone + Send + Sync + 'static,
+    B::PromoteChatMember: Clone + Send + Sync + 'static,
+    B::SetChatAdministratorCustomTitle: Clone + Send + Sync + 'static,
+    B::BanChatSenderChat: Clone + Send + Sync + 'static,
+    B::UnbanChatSenderChat: Clone + Send + Sync + 'static,
+    B::SetChatPermissions: Clone + Send + Sync + 'static,
+    B::ExportChatInviteLink: Clone + Send + Sync + 'static,
+    B::CreateChatInviteLink: Clone + Send + Sync + 'static,
+    B::EditChatInviteLink: Clone + Send + Sync + 'static,
+    B::RevokeChatInviteLink: Clone + Send + Sync + 'static,
+    B::ApproveChatJoinRequest: Clone + Send + Sync + 'static,
+    B::DeclineChatJoinRequest: Clone + Send + Sync + 'static,
+    B::SetChatPhoto: Clone + Send + Sync + 'static,
+    B::DeleteChatPhoto: Clone + Send + Sync + 'static,
+    B::SetChatTitle: Clone + Send + Sync + 'static,
+    B::SetChatDescription: Clone + Send + Sync + 'static,
+    B::PinChatMessage: Clone + Send + Sync + 'static,
+    B::UnpinChatMessage: Clone + Send + Sync + 'static,
+    B::UnpinAllChatMessages: Clone + Send + Sync + 'static,
+    B::LeaveChat: Clone + Send + Sync + 'static,
+    B::GetChat: Clone + Send + Sync + 'static,
+    B::GetChatAdministrators: Clone + Send + Sync + 'static,
+    B::GetChatMemberCount: Clone + Send + Sync + 'static,
+    B::GetChatMembersCount: Clone + Send + Sync + 'static,
+    B::GetChatMember: Clone + Send + Sync + 'static,
+    B::SetChatStickerSet: Clone + Send + Sync + 'static,
+    B::DeleteChatStickerSet: Clone + Send + Sync + 'static,
+    B::EditMessageText: Clone + Send + Sync + 'static,
+    B::EditMessageCaption: Clone + Send + Sync + 'static,
+    B::EditMessageMedia: Clone + Send + Sync + 'static,
+    B::EditMessageReplyMarkup: Clone + Send + Sync + 'static,
+    B::StopPoll: Clone + Send + Sync + 'static,
+    B::DeleteMessage: Clone + Send + Sync + 'static,
+    B::SendSticker: Clone + Send + Sync + 'static,
+    B::SendInvoice: Clone + Send + Sync + 'static,
+{
+    type Err = B::Err;
+
+    requester_forward! {
+        send_message,
+        forward_message,
+        copy_message,
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_media_group,
+        send_location,
+        edit_message_live_location,
+        stop_message_live_location,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        ban_chat_member,
+        kick_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        approve_chat_join_request,
+        decline_chat_join_request,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_member_count,
+        get_chat_members_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        edit_message_text,
+        edit_message_caption,
+        edit_message_media,
+        edit_message_reply_markup,
+        stop_poll,
+        delete_message,
+        send_sticker,
+        send_invoice
+        => f, fty
+    }
+
+    requester_forward! {
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        get_me,
+        log_out,
+        close,
+        edit_message_live_location_inline,
+        stop_message_live_location_inline,
+        get_user_profile_photos,
+        get_file,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text_inline,
+        edit_message_caption_inline,
+        edit_message_media_inline,
+        edit_message_reply_markup_inline,
+        get_sticker_set,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores
+        => fid, ftyid
+    }
+}
+
+download_forward! {
+    'w
+    B
+    [S]
+    AutoMigrate<B, S>
+    { this => this.inner() }
+}
+
+/// Request returned by [`AutoMigrate`] methods.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct AutoMigrateRequest<R: HasPayload, S> {
+    request: Arc<R>,
+    chat_id: fn(&R::Payload) -> &Recipient,
+    chat_id_mut: fn(&mut R::Payload) -> &mut Recipient,
+    store: Arc<S>,
+    on_migrate: Option<Arc<MigrateHook>>,
+}
+
+/// Future returned by [`AutoMigrateRequest`]s.
+#[pin_project::pin_project]
+pub struct AutoMigrateSend<R: Request>(#[pin] BoxFuture<'static, Result<Output<R>, R::Err>>);
+
+impl<R: HasPayload + Clone, S> HasPayload for AutoMigrateRequest<R, S> {
+    type Payload = R::Payload;
+
+    /// Note that if this request was already executed via `send_ref` and it
+    /// didn't yet completed, this method will clone the underlying request.
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        Arc::make_mut(&mut self.request).payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.request.payload_ref()
+    }
+}
+
+impl<R, S> Request for AutoMigrateRequest<R, S>
+where
+    R: Request + Clone + Send + Sync + 'static,
+    R::Err: AsResponseParameters + Send,
+    Output<R>: Send,
+    S: MigrationStore + Send + Sync + 'static,
+{
+    type Err = R::Err;
+    type Send = AutoMigrateSend<R>;
+    type SendRef = AutoMigrateSend<R>;
+
+    fn send(self) -> Self::Send {
+        AutoMigrateSend(Box::pin(send(
+            self.request,
+            self.chat_id,
+            self.chat_id_mut,
+            self.store,
+            self.on_migrate,
+        )))
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        AutoMigrateSend(Box::pin(send(
+            Arc::clone(&self.request),
+            self.chat_id,
+            self.chat_id_mut,
+            Arc::clone(&self.store),
+            self.on_migrate.clone(),
+        )))
+    }
+}
+
+impl<R: Request> Future for AutoMigrateSend<R> {
+    type Output = Result<Output<R>, R::Err>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().0.poll(cx)
+    }
+}
+
+/// Actual implementation of the `AutoMigrateSend` future
+async fn send<R, S>(
+    mut request: Arc<R>,
+    chat_id: fn(&R::Payload) -> &Recipient,
+    chat_id_mut: fn(&mut R::Payload) -> &mut Recipient,
+    store: Arc<S>,
+    on_migrate: Option<Arc<MigrateHook>>,
+) -> Result<Output<R>, R::Err>
+where
+    R: Request + Clone,
+    R::Err: AsResponseParameters,
+    S: MigrationStore,
+{
+    let old = match chat_id(request.payload_ref()) {
+        Recipient::Id(id) => Some(*id),
+        Recipient::ChannelUsername(_) => None,
+    };
+
+    // Send requests to already migrated groups directly to supergroups
+    if let Some(new) = old.and_then(|old| store.get(old)) {
+        *chat_id_mut(Arc::make_mut(&mut request).payload_mut()) = Recipient::Id(new);
+    }
+
+    let err = match request.send_ref().await {
+        Err(err) => err,
+        res => return res,
+    };
+
+    let new = match err.migrate_to_chat_id() {
+        Some(id) => ChatId(id),
+        None => return Err(err),
+    };
+
+    if let Some(old) = old {
+        log::info!("Group {} was migrated to supergroup {}", old, new);
+
+        store.insert(old, new);
+        if let Some(on_migrate) = &on_migrate {
+            on_migrate(old, new);
+        }
+    }
+
+    *chat_id_mut(Arc::make_mut(&mut request).payload_mut()) = Recipient::Id(new);
+    request.send_ref().await
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn requests_are_resent_to_supergroups() {
+        use std::sync::{Arc, Mutex};
+
+        use serde_json::json;
+
+        use crate::{
+            adaptors::auto_migrate::MigrationStore,
+            requests::{Request, Requester, RequesterExt},
+            testing::fake_server::{FakeError, FakeServer},
+            types::ChatId,
+        };
+
+        const SUPERGROUP: ChatId = ChatId(-1001234567890);
+
+        let server = FakeServer::start().unwrap();
+        server.add_group(ChatId(-1), "Group");
+        server.add_group(SUPERGROUP, "Supergroup");
+        server.fail_next("sendMessage", FakeError::MigrateToChatId(SUPERGROUP.0));
+
+        let migrations = Arc::new(Mutex::new(Vec::new()));
+        let bot = server.bot().auto_migrate().on_migrate({
+            let migrations = Arc::clone(&migrations);
+            move |from, to| migrations.lock().unwrap().push((from, to))
+        });
+
+        let message = bot.send_message(ChatId(-1), "hi").send().await.unwrap();
+        assert_eq!(message.chat.id, SUPERGROUP);
+        assert_eq!(*migrations.lock().unwrap(), [(ChatId(-1), SUPERGROUP)]);
+        assert_eq!(bot.store().get(ChatId(-1)), Some(SUPERGROUP));
+
+        // The migration is remembered, so the request is sent to the supergroup at once
+        let message = bot.send_message(ChatId(-1), "hi").send().await.unwrap();
+        assert_eq!(message.chat.id, SUPERGROUP);
+
+        let chat_ids: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.params["chat_id"].clone())
+            .collect();
+        assert_eq!(
+            chat_ids,
+            [json!(-1), json!(SUPERGROUP.0), json!(SUPERGROUP.0)]
+        );
+        assert_eq!(migrations.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,716 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{self, Poll},
+};
+
+use futures::future::BoxFuture;
+use url::Url;
+
+use crate::{
+    requests::{HasPayload, Output, Request, Requester},
+    types::*,
+};
+
+use self::private::{Media, ReusableFiles};
+
+/// Reuses `file_id`s of already uploaded files.
+///
+/// When the same file (a file on disk or in-memory bytes) is sent many times,
+/// it's uploaded to Telegram each time. This adaptor remembers `file_id`s
+/// returned by Telegram after a file was sent successfully and replaces the
+/// same files in later requests with their `file_id`s, so they are not
+/// uploaded again.
+///
+/// Files are cached in the following methods:
+/// - [`send_photo`], [`send_audio`], [`send_document`], [`send_video`],
+///   [`send_animation`], [`send_voice`], [`send_video_note`] and
+///   [`send_sticker`],
+/// - [`send_media_group`] (only the media itself, thumbnails can't be reused).
+///
+/// Files on disk are identified by their path, size and modification time,
+/// in-memory files are identified by a SHA-256 digest of their contents.
+/// Files created with [`InputFile::read`] are never cached. `file_id`s are
+/// cached separately for different kinds of media (e.g. the same file sent as
+/// a photo and as a document is uploaded twice).
+///
+/// Note that a file is cached only when a request which uploaded it completes,
+/// so concurrent requests with the same file all upload it.
+///
+/// At most 1000 `file_id`s are cached by default (see
+/// [`CacheFileIds::with_capacity`]), when the cache is full the least recently
+/// used `file_id` is evicted.
+///
+/// [`send_photo`]: crate::requests::Requester::send_photo
+/// [`send_audio`]: crate::requests::Requester::send_audio
+/// [`send_document`]: crate::requests::Requester::send_document
+/// [`send_video`]: crate::requests::Requester::send_video
+/// [`send_animation`]: crate::requests::Requester::send_animation
+/// [`send_voice`]: crate::requests::Requester::send_voice
+/// [`send_video_note`]: crate::requests::Requester::send_video_note
+/// [`send_sticker`]: crate::requests::Requester::send_sticker
+/// [`send_media_group`]: crate::requests::Requester::send_media_group
+///
+/// ## Examples
+///
+/// ```no_run
+/// use teloxide_core::{
+///     prelude::*,
+///     types::{ChatId, InputFile},
+/// };
+///
+/// # async {
+/// let bot = Bot::new("TOKEN").cache_file_ids();
+///
+/// for chat_id in [ChatId(1), ChatId(2), ChatId(3)] {
+///     // The file is uploaded only once, later it's sent by `file_id`
+///     bot.send_photo(chat_id, InputFile::file("photo.jpg"))
+///         .send()
+///         .await?;
+/// }
+/// # Ok::<_, teloxide_core::RequestError>(()) };
+/// ```
+#[derive(Clone, Debug)]
+pub struct CacheFileIds<B> {
+    inner: B,
+    cache: Arc<Mutex<Cache>>,
+}
+
+/// The default maximum number of cached `file_id`s.
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// Key of a reusable file, see [`InputFile::source_key`].
+type Key = (Media, SourceKey);
+
+/// Keys of files of a payload, in the same order as `ReusableFiles::files`.
+/// `None` for files which can't be cached (or are already replaced).
+type Keys = Vec<Option<Key>>;
+
+/// Cached `file_id`s with the time of their last use.
+#[derive(Debug)]
+struct Cache {
+    file_ids: HashMap<Key, (String, u64)>,
+    capacity: usize,
+    clock: u64,
+}
+
+impl<B> CacheFileIds<B> {
+    /// Creates new adaptor with an empty cache.
+    ///
+    /// Note: it's recommended to use [`RequesterExt::cache_file_ids`] instead.
+    ///
+    /// [`RequesterExt::cache_file_ids`]: crate::requests::RequesterExt::cache_file_ids
+    pub fn new(inner: B) -> Self {
+        Self::with_capacity(inner, DEFAULT_CAPACITY)
+    }
+
+    /// Creates new adaptor with an empty cache which holds at most `capacity`
+    /// `file_id`s.
+    pub fn with_capacity(inner: B, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(Cache {
+                file_ids: HashMap::new(),
+                capacity,
+                clock: 0,
+            })),
+        }
+    }
+
+    /// Allows to access the inner bot.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Unwraps the inner bot.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Returns the number of cached `file_id`s.
+    pub fn len(&self) -> usize {
+        lock(&self.cache).file_ids.len()
+    }
+
+    /// Returns `true` if no `file_id`s are cached.
+    pub fn is_empty(&self) -> bool {
+        lock(&self.cache).file_ids.is_empty()
+    }
+
+    /// Clears the cache.
+    ///
+    /// Note that the cache is shared with clones of this adaptor.
+    pub fn clear(&self) {
+        lock(&self.cache).file_ids.clear()
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        $this.inner().$m($($arg),*)
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        B::$T
+    };
+}
+
+macro_rules! fc {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        CacheFileIdsRequest::new($this.inner().$m($($arg),*), Arc::clone(&$this.cache))
+    };
+}
+
+macro_rules! ftyc {
+    ($T:ident) => {
+        CacheFileIdsRequest<B::$T>
+    };
+}
+
+impl<B> Requester for CacheFileIds<B>
+where
+    B: Requester,
+    B::Err: Send,
+
+    B::SendPhoto: Clone + Send + Sync + 'static,
+    B::SendAudio: Clone + Send + Sync + 'static,
+    B::SendDocument: Clone + Send + Sync + 'static,
+    B::SendVideo: Clone + Send + Sync + 'static,
+    B::SendAnimation: Clone + Send + Sync + 'static,
+    B::SendVoice: Clone + Send + Sync + 'static,
+    B::SendVideoNote: Clone + Send + Sync + 'static,
+    B::SendSticker: Clone + Send + Sync + 'static,
+    B::SendMediaGroup: Clone + Send + Sync + 'static,
+{
+    type Err = B::Err;
+
+    requester_forward! {
+        get_me,
+        log_out,
+        close,
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        forward_message,
+        copy_message,
+        send_message,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        kick_chat_member,
+        ban_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_members_count,
+        get_chat_member_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_media,
+        edit_message_media_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        get_sticker_set,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        send_invoice,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores,
+        approve_chat_join_request,
+        decline_chat_join_request
+        => f, fty
+    }
+
+    // Requests with reusable files
+    requester_forward! {
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_sticker,
+        send_media_group
+        => fc, ftyc
+    }
+}
+
+download_forward! {
+    'w
+    B
+    CacheFileIds<B>
+    { this => this.inner() }
+}
+
+/// Request returned by [`CacheFileIds`] methods.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct CacheFileIdsRequest<R> {
+    request: Arc<R>,
+    cache: Arc<Mutex<Cache>>,
+    // Computed on the first send and reset when the payload is changed
+    keys: Arc<Mutex<Option<Keys>>>,
+}
+
+/// Future returned by [`CacheFileIdsRequest`].
+#[pin_project::pin_project]
+pub struct CacheFileIdsSend<R: Request>(#[pin] BoxFuture<'static, Result<Output<R>, R::Err>>);
+
+impl<R> CacheFileIdsRequest<R> {
+    fn new(request: R, cache: Arc<Mutex<Cache>>) -> Self {
+        Self {
+            request: Arc::new(request),
+            cache,
+            keys: Arc::default(),
+        }
+    }
+}
+
+impl<R> HasPayload for CacheFileIdsRequest<R>
+where
+    R: HasPayload + Clone,
+{
+    type Payload = R::Payload;
+
+    /// Note that if this request was already executed via `send_ref` and it
+    /// didn't yet completed, this method will clone the underlying request.
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        // Files may be changed
+        self.keys = Arc::default();
+        Arc::make_mut(&mut self.request).payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.request.payload_ref()
+    }
+}
+
+impl<R> Request for CacheFileIdsRequest<R>
+where
+    R: Request + Clone + Send + Sync + 'static,
+    R::Payload: ReusableFiles,
+    R::Err: Send,
+    Output<R>: Send,
+{
+    type Err = R::Err;
+
+    type Send = CacheFileIdsSend<R>;
+
+    type SendRef = CacheFileIdsSend<R>;
+
+    fn send(self) -> Self::Send {
+        CacheFileIdsSend(Box::pin(send(self.request, self.cache, self.keys)))
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        CacheFileIdsSend(Box::pin(send(
+            Arc::clone(&self.request),
+            Arc::clone(&self.cache),
+            Arc::clone(&self.keys),
+        )))
+    }
+}
+
+impl<R: Request> Future for CacheFileIdsSend<R> {
+    type Output = Result<Output<R>, R::Err>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.project().0.poll(cx)
+    }
+}
+
+/// Actual implementation of the `CacheFileIdsSend` future
+async fn send<R>(
+    mut request: Arc<R>,
+    cache: Arc<Mutex<Cache>>,
+    cached_keys: Arc<Mutex<Option<Keys>>>,
+) -> Result<Output<R>, R::Err>
+where
+    R: Request + Clone,
+    R::Payload: ReusableFiles,
+{
+    let cached = lock(&cached_keys).clone();
+    let mut keys = match cached {
+        Some(keys) => keys,
+        None => {
+            let files = request
+                .payload_ref()
+                .files()
+                .into_iter()
+                .map(|(media, file)| (media, file.clone()))
+                .collect();
+            let keys = compute_keys(files).await;
+            *lock(&cached_keys) = Some(keys.clone());
+            keys
+        }
+    };
+
+    let file_ids = lock(&cache).take_file_ids(&mut keys);
+    if !file_ids.is_empty() {
+        let mut files = Arc::make_mut(&mut request).payload_mut().files_mut();
+        for (i, file_id) in file_ids {
+            *files[i].1 = InputFile::file_id(file_id);
+        }
+    }
+
+    let res = request.send_ref().await;
+
+    if let Ok(output) = &res {
+        let messages = R::Payload::messages(output);
+        let mut cache = lock(&cache);
+        for (key, message) in keys.into_iter().zip(messages) {
+            if let Some(key) = key {
+                if let Some(file_id) = key.0.file_id(message) {
+                    cache.insert(key, file_id.to_owned());
+                }
+            }
+        }
+    }
+
+    res
+}
+
+/// Returns keys of `files`.
+async fn compute_keys(files: Vec<(Media, InputFile)>) -> Keys {
+    let mut keys = Vec::with_capacity(files.len());
+    for (media, file) in files {
+        keys.push(file.source_key().await.map(|key| (media, key)));
+    }
+
+    keys
+}
+
+impl Cache {
+    /// Returns cached `file_id`s of files with `keys` (with indexes of the
+    /// files), removing their keys since they won't be uploaded.
+    fn take_file_ids(&mut self, keys: &mut Keys) -> Vec<(usize, String)> {
+        let mut file_ids = Vec::new();
+        for (i, key) in keys.iter_mut().enumerate() {
+            let entry = match key {
+                Some(key) => self.file_ids.get_mut(key),
+                None => None,
+            };
+
+            if let Some((file_id, last_used)) = entry {
+                self.clock += 1;
+                *last_used = self.clock;
+                file_ids.push((i, file_id.clone()));
+                *key = None;
+            }
+        }
+
+        file_ids
+    }
+
+    /// Caches `file_id`, evicting the least recently used `file_id` if the
+    /// cache is full.
+    fn insert(&mut self, key: Key, file_id: String) {
+        if self.file_ids.len() >= self.capacity && !self.file_ids.contains_key(&key) {
+            let lru = self
+                .file_ids
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+
+            match lru {
+                Some(lru) => {
+                    self.file_ids.remove(&lru);
+                }
+                // Zero capacity
+                None => return,
+            }
+        }
+
+        self.clock += 1;
+        self.file_ids.insert(key, (file_id, self.clock));
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+mod private {
+    use crate::{
+        payloads::*,
+        requests::Payload,
+        types::{InputFile, InputMedia, Message},
+    };
+
+    /// Kind of media, `file_id`s are cached separately for each kind.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum Media {
+        Photo,
+        Audio,
+        Document,
+        Video,
+        Animation,
+        Voice,
+        VideoNote,
+        Sticker,
+    }
+
+    impl Media {
+        /// Returns the `file_id` of this kind of media from a sent message.
+        pub fn file_id(self, message: &Message) -> Option<&str> {
+            let file_id = match self {
+                // Resending the largest size resends all of the sizes
+                Media::Photo => &message.photo()?.last()?.file_id,
+                Media::Audio => &message.audio()?.file_id,
+                Media::Document => &message.document()?.file_id,
+                Media::Video => &message.video()?.file_id,
+                Media::Animation => &message.animation()?.file_id,
+                Media::Voice => &message.voice()?.file_id,
+                Media::VideoNote => &message.video_note()?.file_id,
+                Media::Sticker => &message.sticker()?.file_id,
+            };
+
+            Some(file_id)
+        }
+    }
+
+    /// Payloads with files which can be replaced with `file_id`s.
+    pub trait ReusableFiles: Payload {
+        /// Returns reusable files, in the same order as messages returned by
+        /// [`messages`](ReusableFiles::messages).
+        fn files(&self) -> Vec<(Media, &InputFile)>;
+
+        /// Same as [`files`](ReusableFiles::files), but returns mutable
+        /// references.
+        fn files_mut(&mut self) -> Vec<(Media, &mut InputFile)>;
+
+        /// Returns messages which contain sent files.
+        fn messages(output: &Self::Output) -> &[Message];
+    }
+
+    macro_rules! impl_reusable_files {
+        ($($P:ident . $field:ident => $media:ident),* $(,)?) => {
+            $(
+                impl ReusableFiles for $P {
+                    fn files(&self) -> Vec<(Media, &InputFile)> {
+                        vec![(Media::$media, &self.$field)]
+                    }
+
+                    fn files_mut(&mut self) -> Vec<(Media, &mut InputFile)> {
+                        vec![(Media::$media, &mut self.$field)]
+                    }
+
+                    fn messages(output: &Message) -> &[Message] {
+                        std::slice::from_ref(output)
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_reusable_files! {
+        SendPhoto.photo => Photo,
+        SendAudio.audio => Audio,
+        SendDocument.document => Document,
+        SendVideo.video => Video,
+        SendAnimation.animation => Animation,
+        SendVoice.voice => Voice,
+        SendVideoNote.video_note => VideoNote,
+        SendSticker.sticker => Sticker,
+    }
+
+    impl ReusableFiles for SendMediaGroup {
+        fn files(&self) -> Vec<(Media, &InputFile)> {
+            self.media.iter().map(media).collect()
+        }
+
+        fn files_mut(&mut self) -> Vec<(Media, &mut InputFile)> {
+            self.media.iter_mut().map(media_mut).collect()
+        }
+
+        fn messages(output: &Vec<Message>) -> &[Message] {
+            output
+        }
+    }
+
+    fn media(media: &InputMedia) -> (Media, &InputFile) {
+        match media {
+            InputMedia::Photo(photo) => (Media::Photo, &photo.media),
+            InputMedia::Video(video) => (Media::Video, &video.media),
+            InputMedia::Animation(animation) => (Media::Animation, &animation.media),
+            InputMedia::Audio(audio) => (Media::Audio, &audio.media),
+            InputMedia::Document(document) => (Media::Document, &document.media),
+        }
+    }
+
+    fn media_mut(media: &mut InputMedia) -> (Media, &mut InputFile) {
+        match media {
+            InputMedia::Photo(photo) => (Media::Photo, &mut photo.media),
+            InputMedia::Video(video) => (Media::Video, &mut video.media),
+            InputMedia::Animation(animation) => (Media::Animation, &mut animation.media),
+            InputMedia::Audio(audio) => (Media::Audio, &mut audio.media),
+            InputMedia::Document(document) => (Media::Document, &mut document.media),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn file_ids_are_reused() {
+        use crate::{
+            adaptors::CacheFileIds,
+            requests::{Request, Requester},
+            testing::fake_server::FakeServer,
+            types::{ChatId, InputFile, InputMedia, InputMediaPhoto},
+        };
+
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        server.add_private_chat(ChatId(2), "Bob");
+        let bot = CacheFileIds::new(server.bot());
+
+        let file = InputFile::memory(&b"photo"[..]);
+        let first = bot
+            .send_photo(ChatId(1), file.clone())
+            .send()
+            .await
+            .unwrap();
+        let second = bot
+            .send_photo(ChatId(2), file.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(bot.len(), 1);
+        assert_eq!(
+            first.photo().unwrap()[0].file_id,
+            second.photo().unwrap()[0].file_id
+        );
+
+        let requests = server.requests();
+        assert_eq!(requests[0].files.len(), 1);
+        assert!(requests[1].files.is_empty());
+        assert_eq!(
+            requests[1].params["photo"],
+            first.photo().unwrap()[0].file_id.as_str()
+        );
+
+        // File ids are cached separately for different kinds of media
+        bot.send_document(ChatId(1), file.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(server.requests()[2].files.len(), 1);
+        assert_eq!(bot.len(), 2);
+
+        // Media groups reuse files too
+        let _ = bot
+            .send_media_group(
+                ChatId(1),
+                [
+                    InputMedia::Photo(InputMediaPhoto::new(file.clone())),
+                    InputMedia::Photo(InputMediaPhoto::new(InputFile::memory(&b"other"[..]))),
+                ],
+            )
+            .send()
+            .await;
+        let request = &server.requests()[3];
+        assert_eq!(request.method, "SendMediaGroup");
+        assert_eq!(request.files.len(), 1);
+
+        // Different contents of the same size are uploaded
+        bot.send_photo(ChatId(2), InputFile::memory(&b"phot0"[..]))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(server.requests()[4].files.len(), 1);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn least_recently_used_file_ids_are_evicted() {
+        use crate::{
+            adaptors::CacheFileIds,
+            requests::{Request, Requester},
+            testing::fake_server::FakeServer,
+            types::{ChatId, InputFile},
+        };
+
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        let bot = CacheFileIds::with_capacity(server.bot(), 2);
+
+        let a = InputFile::memory(&b"a"[..]);
+        let b = InputFile::memory(&b"b"[..]);
+        let c = InputFile::memory(&b"c"[..]);
+        for file in [&a, &b, &a, &c, &a, &b] {
+            bot.send_photo(ChatId(1), file.clone())
+                .send()
+                .await
+                .unwrap();
+        }
+        assert_eq!(bot.len(), 2);
+
+        let uploaded: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|request| !request.files.is_empty())
+            .collect();
+        // `b` is evicted by `c`, since `a` was used more recently
+        assert_eq!(uploaded, [true, true, false, true, false, true]);
+    }
+}
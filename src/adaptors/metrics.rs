@@ -0,0 +1,771 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{self, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::ready;
+use url::Url;
+
+use crate::{
+    requests::{HasPayload, MultipartPayload, Output, Payload, Request, Requester, ResponseResult},
+    types::*,
+    RequestError,
+};
+
+/// Collects metrics of requests: number of requests per method, latency,
+/// errors and sizes of uploaded files.
+///
+/// Every completed request is reported to a [`MetricsSink`]. This crate
+/// provides [`InMemoryMetrics`] which aggregates metrics per method and can
+/// export them in the [Prometheus text format].
+///
+/// [Prometheus text format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+///
+/// ## Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use teloxide_core::{
+///     adaptors::metrics::{InMemoryMetrics, Metrics},
+///     prelude::*,
+/// };
+///
+/// # async {
+/// let metrics = Arc::new(InMemoryMetrics::new());
+/// let bot = Metrics::new(Bot::from_env(), Arc::clone(&metrics));
+///
+/// bot.get_me().send().await?;
+///
+/// // Serve this on e.g. `/metrics`
+/// let text = metrics.to_prometheus();
+/// # let _ = text;
+/// # Ok::<_, teloxide_core::RequestError>(()) };
+/// ```
+#[derive(Debug)]
+pub struct Metrics<B, S = InMemoryMetrics> {
+    inner: B,
+    sink: Arc<S>,
+}
+
+/// A receiver of [`RequestEvent`]s produced by the [`Metrics`] adaptor.
+pub trait MetricsSink: Send + Sync + 'static {
+    /// Called after each request is completed.
+    fn observe(&self, event: &RequestEvent<'_>);
+}
+
+/// Information about a completed request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct RequestEvent<'a> {
+    /// Name of the method, i.e. [`Payload::NAME`].
+    pub method: &'static str,
+
+    /// Time elapsed since the request was first polled until its completion.
+    pub duration: Duration,
+
+    /// Total size of files uploaded with the request.
+    ///
+    /// Only sizes of in-memory files and files on disk are known, files
+    /// created with [`InputFile::read`] are not counted.
+    pub bytes_uploaded: u64,
+
+    /// The error, if the request failed.
+    pub error: Option<&'a RequestError>,
+}
+
+/// A [`MetricsSink`] which aggregates metrics per method in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    methods: Mutex<BTreeMap<&'static str, MethodMetrics>>,
+}
+
+/// Metrics of a single method, collected by [`InMemoryMetrics`].
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct MethodMetrics {
+    /// Number of completed requests.
+    pub requests: u64,
+
+    /// Number of failed requests by the kind of error, see
+    /// [`RequestEvent::error_kind`].
+    pub errors: BTreeMap<&'static str, u64>,
+
+    /// Total size of uploaded files.
+    pub bytes_uploaded: u64,
+
+    /// Distribution of request latency.
+    pub latency: Histogram,
+}
+
+/// A histogram of durations with fixed buckets.
+#[derive(Clone)]
+pub struct Histogram {
+    // Non-cumulative counts, the last one is for durations that didn't fit in
+    // any bucket
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: Duration,
+}
+
+/// Upper bounds of [`Histogram`] buckets, in seconds.
+pub const LATENCY_BUCKETS: [f64; 12] = [
+    0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0,
+];
+
+impl<B, S> Metrics<B, S> {
+    /// Creates new adaptor which reports metrics to `sink`.
+    pub fn new(inner: B, sink: S) -> Self {
+        Self {
+            inner,
+            sink: Arc::new(sink),
+        }
+    }
+
+    /// Allows to access inner bot
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Unwraps inner bot
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Returns the sink metrics are reported to.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+}
+
+impl<B: Clone, S> Clone for Metrics<B, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            sink: Arc::clone(&self.sink),
+        }
+    }
+}
+
+impl<S> MetricsSink for Arc<S>
+where
+    S: MetricsSink + ?Sized,
+{
+    fn observe(&self, event: &RequestEvent<'_>) {
+        (**self).observe(event)
+    }
+}
+
+impl RequestEvent<'_> {
+    /// Returns the kind of the error, if the request failed.
+    ///
+    /// This is the name of the [`ApiError`] variant for API errors (e.g.
+    /// `"BotBlocked"` or `"Unknown"`) and the name of the [`RequestError`]
+    /// variant otherwise (e.g. `"RetryAfter"` or `"Network"`).
+    ///
+    /// [`ApiError`]: crate::ApiError
+    pub fn error_kind(&self) -> Option<&'static str> {
+        self.error.map(error_kind)
+    }
+}
+
+fn error_kind(error: &RequestError) -> &'static str {
+    match error {
+        RequestError::Api(err) => err.kind.name(),
+        RequestError::MigrateToChatId(_) => "MigrateToChatId",
+        RequestError::RetryAfter(_) => "RetryAfter",
+        RequestError::Network(_) => "Network",
+        RequestError::InvalidJson { .. } => "InvalidJson",
+        RequestError::Io(_) => "Io",
+        RequestError::Validation(_) => "Validation",
+    }
+}
+
+/// Label value escaped for the Prometheus text format.
+struct Label<'a>(&'a str);
+
+impl fmt::Display for Label<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl InMemoryMetrics {
+    /// Creates new empty metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns metrics of the `method` (e.g. `"SendMessage"`), if any request
+    /// to it was completed.
+    pub fn method(&self, method: &str) -> Option<MethodMetrics> {
+        self.methods().get(method).cloned()
+    }
+
+    /// Returns metrics of all methods, sorted by method name.
+    pub fn snapshot(&self) -> BTreeMap<&'static str, MethodMetrics> {
+        self.methods().clone()
+    }
+
+    /// Renders the metrics in the [Prometheus text format].
+    ///
+    /// The following metrics are exported (all labeled with `method`):
+    /// - `teloxide_requests_total` counter,
+    /// - `teloxide_request_errors_total` counter, additionally labeled with
+    ///   `error` (see [`RequestEvent::error_kind`]),
+    /// - `teloxide_uploaded_bytes_total` counter,
+    /// - `teloxide_request_duration_seconds` histogram.
+    ///
+    /// [Prometheus text format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+    pub fn to_prometheus(&self) -> String {
+        let methods = self.snapshot();
+        let mut out = String::new();
+
+        // Writing to a `String` never fails
+        let header = |out: &mut String, name, kind, help| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        };
+
+        header(
+            &mut out,
+            "teloxide_requests_total",
+            "counter",
+            "Total number of completed requests.",
+        );
+        for (method, m) in &methods {
+            let _ = writeln!(
+                out,
+                "teloxide_requests_total{{method=\"{}\"}} {}",
+                Label(method),
+                m.requests
+            );
+        }
+
+        header(
+            &mut out,
+            "teloxide_request_errors_total",
+            "counter",
+            "Total number of failed requests.",
+        );
+        for (method, m) in &methods {
+            for (error, count) in &m.errors {
+                let _ = writeln!(
+                    out,
+                    "teloxide_request_errors_total{{method=\"{}\",error=\"{}\"}} {}",
+                    Label(method),
+                    Label(error),
+                    count
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "teloxide_uploaded_bytes_total",
+            "counter",
+            "Total size of uploaded files in bytes.",
+        );
+        for (method, m) in &methods {
+            let _ = writeln!(
+                out,
+                "teloxide_uploaded_bytes_total{{method=\"{}\"}} {}",
+                Label(method),
+                m.bytes_uploaded
+            );
+        }
+
+        header(
+            &mut out,
+            "teloxide_request_duration_seconds",
+            "histogram",
+            "Request latency in seconds.",
+        );
+        for (method, m) in &methods {
+            for (le, count) in m.latency.buckets() {
+                let _ = writeln!(
+                    out,
+                    "teloxide_request_duration_seconds_bucket{{method=\"{}\",le=\"{}\"}} {}",
+                    Label(method),
+                    le,
+                    count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "teloxide_request_duration_seconds_bucket{{method=\"{}\",le=\"+Inf\"}} {}",
+                Label(method),
+                m.latency.count()
+            );
+            let _ = writeln!(
+                out,
+                "teloxide_request_duration_seconds_sum{{method=\"{}\"}} {}",
+                Label(method),
+                m.latency.sum().as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "teloxide_request_duration_seconds_count{{method=\"{}\"}} {}",
+                Label(method),
+                m.latency.count()
+            );
+        }
+
+        out
+    }
+
+    fn methods(&self) -> std::sync::MutexGuard<'_, BTreeMap<&'static str, MethodMetrics>> {
+        // We never panic while holding the lock
+        self.methods.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl MetricsSink for InMemoryMetrics {
+    fn observe(&self, event: &RequestEvent<'_>) {
+        let mut methods = self.methods();
+        let m = methods.entry(event.method).or_default();
+
+        m.requests += 1;
+        m.bytes_uploaded += event.bytes_uploaded;
+        m.latency.observe(event.duration);
+        if let Some(kind) = event.error_kind() {
+            *m.errors.entry(kind).or_default() += 1;
+        }
+    }
+}
+
+impl MethodMetrics {
+    /// Returns the number of failed requests.
+    pub fn failed(&self) -> u64 {
+        self.errors.values().sum()
+    }
+
+    /// Returns the number of requests failed with [`RequestError::RetryAfter`].
+    pub fn retry_after(&self) -> u64 {
+        self.errors.get("RetryAfter").copied().unwrap_or(0)
+    }
+}
+
+impl Histogram {
+    /// Records a duration.
+    pub fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&le| secs <= le)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.counts[bucket] += 1;
+        self.sum += duration;
+    }
+
+    /// Returns the number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the sum of recorded durations.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Returns upper bounds of the buckets (in seconds, see
+    /// [`LATENCY_BUCKETS`]) alongside with the number of recorded durations
+    /// less than or equal to them.
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        LATENCY_BUCKETS
+            .iter()
+            .zip(&self.counts)
+            .scan(0, |total, (&le, &count)| {
+                *total += count;
+                Some((le, *total))
+            })
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; LATENCY_BUCKETS.len() + 1],
+            sum: Duration::ZERO,
+        }
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        MetricsRequest {
+            inner: $this.inner().$m($($arg),*),
+            sink: Arc::clone(&$this.sink),
+            upload_size: |_| 0,
+        }
+    };
+}
+
+macro_rules! fm {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        MetricsRequest {
+            inner: $this.inner().$m($($arg),*),
+            sink: Arc::clone(&$this.sink),
+            upload_size,
+        }
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        MetricsRequest<B::$T, MS>
+    };
+}
+
+// Note: `MS` is used instead of `S` to not clash with generic parameters of
+//       `Requester` methods
+impl<B, MS> Requester for Metrics<B, MS>
+where
+    B: Requester<Err = RequestError>,
+    MS: MetricsSink,
+{
+    type Err = RequestError;
+
+    requester_forward! {
+        get_updates,
+        delete_webhook,
+        get_webhook_info,
+        get_me,
+        log_out,
+        close,
+        send_message,
+        forward_message,
+        copy_message,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        ban_chat_member,
+        kick_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        approve_chat_join_request,
+        decline_chat_join_request,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_member_count,
+        get_chat_members_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        get_sticker_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        send_invoice,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores
+        => f, fty
+    }
+
+    // Requests that may upload files
+    requester_forward! {
+        set_webhook,
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_media_group,
+        set_chat_photo,
+        edit_message_media,
+        edit_message_media_inline,
+        send_sticker,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_set_thumb
+        => fm, fty
+    }
+}
+
+download_forward! {
+    'w
+    B
+    [S]
+    Metrics<B, S>
+    { this => this.inner() }
+}
+
+/// Request returned by [`Metrics`] methods.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct MetricsRequest<R: HasPayload, S> {
+    inner: R,
+    sink: Arc<S>,
+    upload_size: fn(&R::Payload) -> u64,
+}
+
+/// Future returned by [`MetricsRequest`]s.
+#[pin_project::pin_project]
+pub struct MetricsSend<F: Future, S> {
+    #[pin]
+    inner: F,
+    sink: Arc<S>,
+    method: &'static str,
+    bytes_uploaded: u64,
+    started: Option<Instant>,
+    error: fn(&F::Output) -> Option<&RequestError>,
+}
+
+impl<R, S> HasPayload for MetricsRequest<R, S>
+where
+    R: HasPayload,
+{
+    type Payload = R::Payload;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        self.inner.payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.inner.payload_ref()
+    }
+}
+
+impl<R, S> Request for MetricsRequest<R, S>
+where
+    R: Request<Err = RequestError>,
+    S: MetricsSink,
+{
+    type Err = RequestError;
+    type Send = MetricsSend<R::Send, S>;
+    type SendRef = MetricsSend<R::SendRef, S>;
+
+    fn send(self) -> Self::Send {
+        let bytes_uploaded = (self.upload_size)(self.inner.payload_ref());
+
+        MetricsSend {
+            inner: self.inner.send(),
+            sink: self.sink,
+            method: R::Payload::NAME,
+            bytes_uploaded,
+            started: None,
+            error: result_error::<Output<R>>,
+        }
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        MetricsSend {
+            inner: self.inner.send_ref(),
+            sink: Arc::clone(&self.sink),
+            method: R::Payload::NAME,
+            bytes_uploaded: (self.upload_size)(self.inner.payload_ref()),
+            started: None,
+            error: result_error::<Output<R>>,
+        }
+    }
+}
+
+impl<F, S> Future for MetricsSend<F, S>
+where
+    F: Future,
+    S: MetricsSink,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let started = *this.started.get_or_insert_with(Instant::now);
+        let ret = ready!(this.inner.poll(cx));
+
+        this.sink.observe(&RequestEvent {
+            method: this.method,
+            duration: started.elapsed(),
+            bytes_uploaded: *this.bytes_uploaded,
+            error: (this.error)(&ret),
+        });
+
+        Poll::Ready(ret)
+    }
+}
+
+fn result_error<T>(result: &ResponseResult<T>) -> Option<&RequestError> {
+    result.as_ref().err()
+}
+
+/// Returns the total size of files attached to `payload`.
+fn upload_size<P: MultipartPayload>(payload: &P) -> u64 {
+    let mut size = 0;
+    payload.copy_files(&mut |file| size += file.size_hint().unwrap_or(0));
+    size
+}
+
+impl fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Histogram")
+            .field("count", &self.count())
+            .field("sum", &self.sum)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        adaptors::metrics::{error_kind, Histogram, Label},
+        ApiError, RequestError,
+    };
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::default();
+        histogram.observe(Duration::from_millis(10));
+        histogram.observe(Duration::from_millis(70));
+        histogram.observe(Duration::from_millis(80));
+        histogram.observe(Duration::from_secs(1000));
+
+        let buckets: Vec<_> = histogram.buckets().take(3).collect();
+        assert_eq!(buckets, [(0.025, 1), (0.05, 1), (0.1, 3)]);
+        assert_eq!(histogram.buckets().last(), Some((120.0, 3)));
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.sum(), Duration::from_millis(1_000_160));
+    }
+
+    #[test]
+    fn error_kinds_are_variant_names() {
+        let err = RequestError::Api(
+            ApiError::CantParseEntities {
+                reason: "Can't find end of the entity".to_owned(),
+                byte_offset: Some(5),
+            }
+            .into(),
+        );
+        assert_eq!(error_kind(&err), "CantParseEntities");
+
+        let err = RequestError::Api(ApiError::Unknown("Bad Request: \"?\"".to_owned()).into());
+        assert_eq!(error_kind(&err), "Unknown");
+
+        let err = RequestError::RetryAfter(Duration::from_secs(1));
+        assert_eq!(error_kind(&err), "RetryAfter");
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        assert_eq!(Label("GetMe").to_string(), "GetMe");
+        assert_eq!(Label("a\\b\"c\nd").to_string(), r#"a\\b\"c\nd"#);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn collects_metrics() {
+        use std::sync::Arc;
+
+        use crate::{
+            adaptors::metrics::{InMemoryMetrics, Metrics},
+            payloads::{GetMe, SendDocument, SendMessage},
+            requests::{Request, Requester},
+            testing::MockBot,
+            types::{ChatId, InputFile},
+            ApiError, RequestError,
+        };
+
+        let mock = MockBot::new();
+        mock.respond::<SendMessage>(Err(RequestError::Api(ApiError::BotBlocked.into())));
+        mock.respond::<SendMessage>(Err(RequestError::RetryAfter(Duration::from_secs(1))));
+        mock.respond::<GetMe>(Err(RequestError::Api(
+            ApiError::Unknown("?".to_owned()).into(),
+        )));
+        mock.respond::<SendDocument>(Err(RequestError::Api(ApiError::BotBlocked.into())));
+
+        let metrics = Arc::new(InMemoryMetrics::new());
+        let bot = Metrics::new(mock, Arc::clone(&metrics));
+
+        bot.send_message(ChatId(1), "a").send().await.unwrap_err();
+        bot.send_message(ChatId(1), "a").send().await.unwrap_err();
+        bot.get_me().send().await.unwrap_err();
+        bot.send_document(ChatId(1), InputFile::memory(&b"12345"[..]))
+            .send()
+            .await
+            .unwrap_err();
+
+        let send_message = metrics.method("SendMessage").unwrap();
+        assert_eq!(send_message.requests, 2);
+        assert_eq!(send_message.failed(), 2);
+        assert_eq!(send_message.retry_after(), 1);
+        assert_eq!(send_message.errors["BotBlocked"], 1);
+        assert_eq!(send_message.latency.count(), 2);
+
+        assert_eq!(metrics.method("GetMe").unwrap().errors["Unknown"], 1);
+        assert_eq!(metrics.method("SendDocument").unwrap().bytes_uploaded, 5);
+        assert!(metrics.method("GetChat").is_none());
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("teloxide_requests_total{method=\"SendMessage\"} 2\n"));
+        assert!(text.contains(
+            "teloxide_request_errors_total{method=\"SendMessage\",error=\"RetryAfter\"} 1\n"
+        ));
+        assert!(text.contains("teloxide_uploaded_bytes_total{method=\"SendDocument\"} 5\n"));
+        assert!(text.contains(
+            "teloxide_request_duration_seconds_bucket{method=\"GetMe\",le=\"+Inf\"} 1\n"
+        ));
+    }
+}
@@ -0,0 +1,520 @@
+//! Recording requests and replaying recorded responses.
+//!
+//! See [`Record`] and [`Replay`] for more.
+
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    io::Write,
+    pin::Pin,
+    task::{self, Poll},
+    thread,
+};
+
+use futures::ready;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+use url::Url;
+
+use crate::{
+    net,
+    requests::{HasPayload, Payload, Request, Requester},
+    types::*,
+    RequestError,
+};
+
+mod replay;
+
+pub use replay::{Replay, ReplayRequest, ReplaySend};
+
+/// Records requests and responses into a cassette.
+///
+/// A cassette is a [JSON Lines] file where each line is an [`Interaction`]:
+/// the name of the method, the request payload serialized to JSON (the same
+/// way it's sent to Telegram) and the response text. Recorded cassettes can be
+/// replayed with [`Replay`], e.g. to write regression tests using real
+/// Telegram responses.
+///
+/// Note that:
+/// - responses are recorded exactly as they were received by the underlying
+///   [`Bot`] (or [`Replay`]), requests which didn't receive a response (e.g.
+///   because of a network or I/O error) are not recorded,
+/// - contents of uploaded files are not recorded,
+/// - file downloads are not recorded.
+///
+/// [JSON Lines]: https://jsonlines.org/
+/// [`Bot`]: crate::Bot
+///
+/// ## Examples
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use teloxide_core::{adaptors::Record, prelude::*};
+///
+/// # async {
+/// let cassette = File::create("cassette.jsonl")?;
+/// let bot = Record::new(Bot::from_env(), cassette);
+///
+/// bot.get_me().send().await?;
+/// bot.flush().await;
+/// # Ok::<_, Box<dyn std::error::Error>>(()) };
+/// ```
+pub struct Record<B> {
+    inner: B,
+    cassette: Cassette,
+}
+
+/// A single request and response recorded by [`Record`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Name of the method, i.e. [`Payload::NAME`].
+    pub method: String,
+
+    /// Payload of the request serialized to JSON.
+    pub request: Value,
+
+    /// Raw text of the response, as it would be sent by Telegram.
+    pub response: String,
+}
+
+/// Sender of commands to the thread which writes to the cassette.
+type Cassette = mpsc::UnboundedSender<Command>;
+
+enum Command {
+    Write(Interaction),
+    Flush(oneshot::Sender<()>),
+}
+
+impl<B> Record<B> {
+    /// Creates new adaptor which writes recorded interactions to `cassette`.
+    ///
+    /// Interactions are written (and flushed) by a separate thread, so that
+    /// sending requests is not blocked by I/O. Use [`Record::flush`] to wait
+    /// until they are written.
+    pub fn new<W>(inner: B, cassette: W) -> Self
+    where
+        W: Write + Send + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded_channel();
+        thread::spawn(move || write_cassette(cassette, rx));
+
+        Self {
+            inner,
+            cassette: tx,
+        }
+    }
+
+    /// Waits until all interactions recorded so far are written to the
+    /// cassette.
+    pub async fn flush(&self) {
+        let (tx, rx) = oneshot::channel();
+
+        // The writer thread only stops when all senders are dropped
+        if self.cassette.send(Command::Flush(tx)).is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    /// Allows to access inner bot
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Unwraps inner bot
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Clone> Clone for Record<B> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            cassette: self.cassette.clone(),
+        }
+    }
+}
+
+impl<B: Debug> Debug for Record<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Record")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        RecordRequest {
+            inner: $this.inner().$m($($arg),*),
+            cassette: $this.cassette.clone(),
+        }
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        RecordRequest<B::$T>
+    };
+}
+
+impl<B> Requester for Record<B>
+where
+    B: Requester<Err = RequestError>,
+{
+    type Err = RequestError;
+
+    requester_forward! {
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        get_me,
+        log_out,
+        close,
+        send_message,
+        forward_message,
+        copy_message,
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_media_group,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        ban_chat_member,
+        kick_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        approve_chat_join_request,
+        decline_chat_join_request,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_member_count,
+        get_chat_members_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_media,
+        edit_message_media_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        send_sticker,
+        get_sticker_set,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        send_invoice,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores
+        => f, fty
+    }
+}
+
+download_forward! {
+    'w
+    B
+    Record<B>
+    { this => this.inner() }
+}
+
+/// Request returned by [`Record`] methods.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct RecordRequest<R> {
+    inner: R,
+    cassette: Cassette,
+}
+
+/// Future returned by [`RecordRequest`]s.
+#[pin_project::pin_project]
+pub struct RecordSend<F>
+where
+    F: Future,
+{
+    #[pin]
+    inner: F,
+    request: Option<(&'static str, Value)>,
+    response: Option<String>,
+    cassette: Cassette,
+}
+
+impl<R> RecordRequest<R>
+where
+    R: HasPayload,
+    R::Payload: Serialize,
+{
+    fn serialize_request(&self) -> Option<(&'static str, Value)> {
+        let name = <R::Payload as Payload>::NAME;
+
+        match serde_json::to_value(self.inner.payload_ref()) {
+            Ok(request) => Some((name, request)),
+            Err(err) => {
+                log::error!("Couldn't serialize `{}` request: {}", name, err);
+                None
+            }
+        }
+    }
+}
+
+impl<R> HasPayload for RecordRequest<R>
+where
+    R: HasPayload,
+{
+    type Payload = R::Payload;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        self.inner.payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.inner.payload_ref()
+    }
+}
+
+impl<R> Request for RecordRequest<R>
+where
+    R: Request<Err = RequestError>,
+    R::Payload: Serialize,
+{
+    type Err = RequestError;
+    type Send = RecordSend<R::Send>;
+    type SendRef = RecordSend<R::SendRef>;
+
+    fn send(self) -> Self::Send {
+        RecordSend {
+            request: self.serialize_request(),
+            response: None,
+            inner: self.inner.send(),
+            cassette: self.cassette,
+        }
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        RecordSend {
+            request: self.serialize_request(),
+            response: None,
+            inner: self.inner.send_ref(),
+            cassette: self.cassette.clone(),
+        }
+    }
+}
+
+impl<F> Future for RecordSend<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = this.inner;
+        let (ret, response) = net::capture_response(|| inner.poll(cx));
+
+        // The response may be received in an earlier poll, e.g. by `Retry`
+        if response.is_some() {
+            *this.response = response;
+        }
+
+        let ret = ready!(ret);
+
+        if let Some((method, request)) = this.request.take() {
+            match this.response.take() {
+                Some(response) => {
+                    let interaction = Interaction {
+                        method: method.to_owned(),
+                        request,
+                        response,
+                    };
+                    // The writer thread only stops when all senders are dropped
+                    let _ = this.cassette.send(Command::Write(interaction));
+                }
+                None => log::warn!("Response to `{}` request was not recorded", method),
+            }
+        }
+
+        Poll::Ready(ret)
+    }
+}
+
+fn write_cassette<W: Write>(mut cassette: W, mut commands: mpsc::UnboundedReceiver<Command>) {
+    while let Some(command) = commands.blocking_recv() {
+        match command {
+            Command::Write(interaction) => {
+                let res = serde_json::to_writer(&mut cassette, &interaction)
+                    .map_err(Into::into)
+                    .and_then(|()| cassette.write_all(b"\n"))
+                    .and_then(|()| cassette.flush());
+
+                if let Err(err) = res {
+                    log::error!("Couldn't write to the cassette: {}", err);
+                }
+            }
+            Command::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use serde_json::json;
+
+    use crate::{
+        adaptors::record::{Interaction, Record, Replay},
+        requests::{Request, Requester},
+        types::ChatId,
+        ApiError, RequestError,
+    };
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn interactions() -> Vec<Interaction> {
+        let message = json!({
+            "message_id": 1,
+            "date": 1,
+            "chat": { "id": 1, "type": "private", "first_name": "A" },
+            "text": "hi",
+            // Unknown fields must be recorded as well
+            "unknown_field": true,
+        });
+
+        vec![
+            Interaction {
+                method: "SendMessage".to_owned(),
+                request: json!({ "chat_id": 1, "text": "hi" }),
+                response: json!({ "ok": true, "result": message }).to_string(),
+            },
+            Interaction {
+                method: "SendMessage".to_owned(),
+                request: json!({ "chat_id": 2, "text": "hi" }),
+                response: json!({
+                    "ok": false,
+                    "error_code": 403,
+                    "description": "Forbidden: bot was blocked by the user",
+                })
+                .to_string(),
+            },
+            Interaction {
+                method: "DeleteMessage".to_owned(),
+                request: json!({ "chat_id": 1, "message_id": 1 }),
+                response: json!({
+                    "ok": false,
+                    "error_code": 429,
+                    "description": "Too Many Requests: retry after 3",
+                    "parameters": { "retry_after": 3 },
+                })
+                .to_string(),
+            },
+        ]
+    }
+
+    async fn run<R: Requester<Err = RequestError>>(bot: R) {
+        let message = bot.send_message(ChatId(1), "hi").send().await.unwrap();
+        assert_eq!(message.text(), Some("hi"));
+
+        let res = bot.send_message(ChatId(2), "hi").send().await;
+        assert_eq!(res.unwrap_err().api_error(), Some(&ApiError::BotBlocked));
+
+        let res = bot.delete_message(ChatId(1), message.id).send().await;
+        assert!(matches!(res, Err(RequestError::RetryAfter(d)) if d.as_secs() == 3));
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let replay = Replay::new(interactions());
+        let buffer = Buffer::default();
+
+        let record = Record::new(replay.clone(), buffer.clone());
+        run(record.clone()).await;
+        assert!(replay.unused().is_empty());
+
+        record.flush().await;
+
+        let cassette = buffer.0.lock().unwrap().clone();
+        let recorded = Replay::from_reader(&cassette[..]).unwrap();
+        assert_eq!(recorded.unused(), interactions());
+
+        run(recorded.clone()).await;
+        assert!(recorded.unused().is_empty());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "no recorded interaction matches `SendMessage` request")]
+    async fn unmatched_request() {
+        let replay = Replay::new(interactions());
+        let _ = replay.send_message(ChatId(1), "bye").send().await;
+    }
+}
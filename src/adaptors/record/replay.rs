@@ -0,0 +1,390 @@
+use std::{
+    fmt::{self, Debug},
+    fs::File,
+    future::Future,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    adaptors::record::Interaction,
+    net, payloads,
+    requests::{HasPayload, Payload, Request, Requester, ResponseResult},
+    types::*,
+    Bot,
+};
+
+/// A [`Requester`] which doesn't send requests to Telegram, but replays
+/// responses from a cassette recorded by [`Record`].
+///
+/// Each request is matched against interactions from the cassette which were
+/// not replayed yet: the first interaction with the same method and the same
+/// request payload is used. Attached files are only compared by their
+/// presence, since their ids are random.
+///
+/// Sending a request which doesn't match any interaction causes a panic.
+///
+/// `Replay` is cheap to clone, all clones share the cassette.
+///
+/// [`Requester`]: crate::requests::Requester
+/// [`Record`]: crate::adaptors::Record
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{adaptors::record::Replay, prelude::*};
+///
+/// # async {
+/// let cassette = r#"{"method":"GetMe","request":{},"response":"{\"ok\":true,\"result\":{\"id\":1,\"is_bot\":true,\"first_name\":\"Bot\",\"username\":\"bot\",\"can_join_groups\":true,\"can_read_all_group_messages\":false,\"supports_inline_queries\":false}}"}"#;
+/// let bot = Replay::from_reader(cassette.as_bytes()).unwrap();
+///
+/// let me = bot.get_me().send().await.unwrap();
+/// assert_eq!(me.username(), "bot");
+/// assert!(bot.unused().is_empty());
+/// # };
+/// ```
+#[derive(Clone)]
+pub struct Replay {
+    // `None` marks already replayed interactions
+    interactions: Arc<Mutex<Vec<Option<Interaction>>>>,
+}
+
+/// Request returned by [`Replay`] methods.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+#[derive(Clone)]
+pub struct ReplayRequest<P> {
+    replay: Replay,
+    payload: P,
+}
+
+/// Future returned by [`ReplayRequest`]s.
+#[must_use = "Futures are lazy and do nothing unless polled or awaited"]
+pub struct ReplaySend<P> {
+    inner: Option<(Replay, P)>,
+}
+
+impl Replay {
+    /// Creates new replaying requester from recorded interactions.
+    pub fn new<I>(interactions: I) -> Self
+    where
+        I: IntoIterator<Item = Interaction>,
+    {
+        Self {
+            interactions: Arc::new(Mutex::new(interactions.into_iter().map(Some).collect())),
+        }
+    }
+
+    /// Reads a cassette in the [JSON Lines] format, empty lines are ignored.
+    ///
+    /// [JSON Lines]: https://jsonlines.org/
+    pub fn from_reader<R>(reader: R) -> io::Result<Self>
+    where
+        R: BufRead,
+    {
+        let mut interactions = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let interaction = serde_json::from_str(&line)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            interactions.push(interaction);
+        }
+
+        Ok(Self::new(interactions))
+    }
+
+    /// Reads a cassette from the file at `path`.
+    ///
+    /// See also [`from_reader`](Self::from_reader).
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Returns interactions which were not replayed yet.
+    pub fn unused(&self) -> Vec<Interaction> {
+        self.interactions().iter().flatten().cloned().collect()
+    }
+
+    fn interactions(&self) -> MutexGuard<'_, Vec<Option<Interaction>>> {
+        // We never panic while holding the lock
+        self.interactions
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn execute<P>(&self, payload: &P) -> ResponseResult<P::Output>
+    where
+        P: Payload + Serialize,
+        P::Output: DeserializeOwned,
+    {
+        let request = serde_json::to_value(payload).expect("payloads are always serializable");
+
+        let response = self
+            .interactions()
+            .iter_mut()
+            .find(|i| {
+                matches!(
+                    i,
+                    Some(i) if i.method == P::NAME && request_matches(&i.request, &request)
+                )
+            })
+            .and_then(Option::take)
+            .map(|i| i.response);
+
+        match response {
+            Some(response) => net::deserialize_response(response, P::NAME),
+            None => panic!(
+                "no recorded interaction matches `{}` request: {}",
+                P::NAME,
+                request
+            ),
+        }
+    }
+}
+
+impl Debug for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Replay")
+            .field("unused", &self.unused().len())
+            .finish()
+    }
+}
+
+/// Compares recorded request with the actual one, ignoring ids of attached
+/// files.
+fn request_matches(recorded: &Value, actual: &Value) -> bool {
+    const ATTACH: &str = "attach://";
+
+    match (recorded, actual) {
+        (Value::String(r), Value::String(a)) if r.starts_with(ATTACH) => a.starts_with(ATTACH),
+        (Value::Array(r), Value::Array(a)) => {
+            r.len() == a.len() && r.iter().zip(a).all(|(r, a)| request_matches(r, a))
+        }
+        (Value::Object(r), Value::Object(a)) => {
+            r.len() == a.len()
+                && r.iter()
+                    .all(|(k, r)| matches!(a.get(k), Some(a) if request_matches(r, a)))
+        }
+        (r, a) => r == a,
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        ReplayRequest::new(
+            $this.clone(),
+            Bot::payload_builder().$m($($arg),*).into_payload(),
+        )
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        ReplayRequest<payloads::$T>
+    };
+}
+
+impl Requester for Replay {
+    type Err = crate::RequestError;
+
+    requester_forward! {
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        get_me,
+        log_out,
+        close,
+        send_message,
+        forward_message,
+        copy_message,
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_media_group,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        ban_chat_member,
+        kick_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        approve_chat_join_request,
+        decline_chat_join_request,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_member_count,
+        get_chat_members_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_media,
+        edit_message_media_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        send_sticker,
+        get_sticker_set,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        send_invoice,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores
+        => f, fty
+    }
+}
+
+impl<P> ReplayRequest<P> {
+    pub(crate) fn new(replay: Replay, payload: P) -> Self {
+        Self { replay, payload }
+    }
+}
+
+impl<P> HasPayload for ReplayRequest<P>
+where
+    P: Payload,
+{
+    type Payload = P;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        &mut self.payload
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        &self.payload
+    }
+}
+
+impl<P> Request for ReplayRequest<P>
+where
+    P: Payload + Serialize + Clone + Send,
+    P::Output: DeserializeOwned + Send,
+{
+    type Err = crate::RequestError;
+    type Send = ReplaySend<P>;
+    type SendRef = ReplaySend<P>;
+
+    fn send(self) -> Self::Send {
+        ReplaySend {
+            inner: Some((self.replay, self.payload)),
+        }
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        ReplaySend {
+            inner: Some((self.replay.clone(), self.payload.clone())),
+        }
+    }
+}
+
+impl<P> Future for ReplaySend<P>
+where
+    P: Payload + Serialize,
+    P::Output: DeserializeOwned,
+{
+    type Output = ResponseResult<P::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        let (replay, payload) = self
+            .inner
+            .take()
+            .expect("`ReplaySend` polled after completion");
+
+        Poll::Ready(replay.execute(&payload))
+    }
+}
+
+// `ReplaySend` doesn't pin its fields
+impl<P> Unpin for ReplaySend<P> {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::adaptors::record::replay::request_matches;
+
+    #[test]
+    fn attachments_are_matched_by_presence() {
+        let recorded = json!({ "chat_id": 1, "document": "attach://abc" });
+
+        assert!(request_matches(
+            &recorded,
+            &json!({ "chat_id": 1, "document": "attach://xyz" })
+        ));
+        assert!(!request_matches(
+            &recorded,
+            &json!({ "chat_id": 1, "document": "file_id" })
+        ));
+        assert!(!request_matches(
+            &recorded,
+            &json!({ "chat_id": 1, "document": "attach://abc", "caption": "c" })
+        ));
+    }
+}
@@ -0,0 +1,647 @@
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::{
+    future::BoxFuture,
+    task::{Context, Poll},
+};
+use url::Url;
+
+use crate::{
+    errors::AsResponseParameters,
+    requests::{HasPayload, Output, Request, Requester},
+    types::*,
+    RequestError,
+};
+
+/// Automatic retrying of failed requests.
+///
+/// This bot wrapper re-sends requests (using [`Request::send_ref`]) that
+/// failed with a transient error, e.g. a network error, a server error or
+/// [`RequestError::RetryAfter`]. When to retry and how long to wait before
+/// the next attempt is decided by a [`RetryPolicy`], by default
+/// [`ExponentialBackoff`].
+///
+/// Note that if an error has [`retry_after`] information, the request is
+/// retried after the duration specified by Telegram, not after the duration
+/// computed by the backoff.
+///
+/// It's recommended to use this wrapper after [`Throttle`] (i.e.
+/// `Retry<Throttle<Bot>>`) so that retried requests are still subject to the
+/// limits.
+///
+/// [`retry_after`]: crate::errors::AsResponseParameters::retry_after
+/// [`Throttle`]: crate::adaptors::Throttle
+///
+/// ## Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use teloxide_core::{adaptors::retry::ExponentialBackoff, requests::RequesterExt, Bot};
+///
+/// let bot = Bot::new("TOKEN").retry(
+///     ExponentialBackoff::default()
+///         .max_attempts(5)
+///         .initial_delay(Duration::from_millis(500)),
+/// );
+/// # let _ = bot;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Retry<B, P = ExponentialBackoff> {
+    bot: B,
+    policy: Arc<P>,
+}
+
+impl<B, P> Retry<B, P> {
+    /// Creates new [`Retry`].
+    ///
+    /// Note: it's recommended to use [`RequesterExt::retry`] instead.
+    ///
+    /// [`RequesterExt::retry`]: crate::requests::RequesterExt::retry
+    pub fn new(bot: B, policy: P) -> Self {
+        Self {
+            bot,
+            policy: Arc::new(policy),
+        }
+    }
+
+    /// Allows to access inner bot
+    pub fn inner(&self) -> &B {
+        &self.bot
+    }
+
+    /// Unwraps inner bot
+    pub fn into_inner(self) -> B {
+        self.bot
+    }
+
+    /// Returns currently used retry policy.
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+}
+
+/// A policy which decides whether a failed request should be retried.
+pub trait RetryPolicy<E> {
+    /// Returns the delay before the next attempt to send a request, or `None`
+    /// if the request should not be retried.
+    ///
+    /// `attempt` is the number of the attempt that failed with `error`,
+    /// starting from `1`.
+    fn retry_delay(&self, attempt: u32, error: &E) -> Option<Duration>;
+}
+
+impl<E, F> RetryPolicy<E> for F
+where
+    F: Fn(u32, &E) -> Option<Duration>,
+{
+    fn retry_delay(&self, attempt: u32, error: &E) -> Option<Duration> {
+        self(attempt, error)
+    }
+}
+
+/// Exponential backoff [`RetryPolicy`].
+///
+/// The delay before `n`-th retry is `initial_delay * multiplier^(n - 1)`, but
+/// no more than `max_delay`. If jitter is on, the delay is then randomly
+/// chosen from `[delay / 2, delay]`.
+///
+/// Only errors for which the classification function returns `true` are
+/// retried, by default it's [`is_transient`].
+///
+/// ## Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use teloxide_core::{adaptors::retry::ExponentialBackoff, RequestError};
+///
+/// let policy = ExponentialBackoff::default()
+///     .max_attempts(10)
+///     .max_delay(Duration::from_secs(60))
+///     .classify(|err| matches!(err, RequestError::Network(_)));
+/// # let _ = policy;
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExponentialBackoff<E = RequestError> {
+    initial_delay: Duration,
+    max_delay: Duration,
+    multiplier: u32,
+    max_attempts: u32,
+    jitter: bool,
+    classify: fn(&E) -> bool,
+}
+
+impl<E> ExponentialBackoff<E> {
+    /// Creates new exponential backoff policy which retries errors for which
+    /// `classify` returns `true`.
+    pub fn with_classify(classify: fn(&E) -> bool) -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2,
+            max_attempts: 3,
+            jitter: true,
+            classify,
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn initial_delay(mut self, val: Duration) -> Self {
+        self.initial_delay = val;
+        self
+    }
+
+    /// Sets the maximum delay between attempts.
+    pub fn max_delay(mut self, val: Duration) -> Self {
+        self.max_delay = val;
+        self
+    }
+
+    /// Sets the factor by which the delay is multiplied after each attempt.
+    pub fn multiplier(mut self, val: u32) -> Self {
+        self.multiplier = val;
+        self
+    }
+
+    /// Sets the maximum number of attempts to send a request (including the
+    /// first one).
+    pub fn max_attempts(mut self, val: u32) -> Self {
+        self.max_attempts = val;
+        self
+    }
+
+    /// Turns off jitter.
+    pub fn no_jitter(mut self) -> Self {
+        self.jitter = false;
+        self
+    }
+
+    /// Sets the function that decides which errors should be retried.
+    pub fn classify(mut self, val: fn(&E) -> bool) -> Self {
+        self.classify = val;
+        self
+    }
+
+    /// Returns delay before `attempt + 1`-th attempt, without jitter.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(attempt.saturating_sub(1));
+
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self::with_classify(is_transient)
+    }
+}
+
+impl<E> RetryPolicy<E> for ExponentialBackoff<E>
+where
+    E: AsResponseParameters,
+{
+    fn retry_delay(&self, attempt: u32, error: &E) -> Option<Duration> {
+        if attempt >= self.max_attempts || !(self.classify)(error) {
+            return None;
+        }
+
+        if let Some(retry_after) = error.retry_after() {
+            return Some(retry_after);
+        }
+
+        let delay = self.backoff(attempt);
+        match self.jitter {
+            true => Some(jitter(delay)),
+            false => Some(delay),
+        }
+    }
+}
+
+/// Returns `true` if `error` is likely to go away if the request is retried.
+///
+/// This is the same as [`RequestError::is_retryable`].
+pub fn is_transient(error: &RequestError) -> bool {
+    error.is_retryable()
+}
+
+/// Randomly chooses a duration from `[delay / 2, delay]`.
+fn jitter(delay: Duration) -> Duration {
+    // `RandomState` is randomly seeded, which is enough for jitter and allows
+    // us to not depend on `rand`
+    let random = RandomState::new().build_hasher().finish();
+    let half = delay / 2;
+    let nanos = half.as_nanos() as u64;
+
+    half + Duration::from_nanos(random.checked_rem(nanos + 1).unwrap_or(0))
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        RetryRequest {
+            request: Arc::new($this.inner().$m($($arg),*)),
+            policy: Arc::clone(&$this.policy),
+        }
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        RetryRequest<B::$T, RP>
+    };
+}
+
+// Note: `RP` is used instead of `P` to not clash with generic parameters of
+//       `Requester` methods
+impl<B, RP> Requester for Retry<B, RP>
+where
+    B: Requester,
+    B::Err: AsResponseParameters,
+    RP: RetryPolicy<B::Err> + Send + Sync + 'static,
+
+    B::GetUpdates: Clone + Send + Sync + 'static,
+    B::SetWebhook: Clone + Send + Sync + 'static,
+    B::DeleteWebhook: Clone + Send + Sync + 'static,
+    B::GetWebhookInfo: Clone + Send + Sync + 'static,
+    B::GetMe: Clone + Send + Sync + 'static,
+    B::LogOut: Clone + Send + Sync + 'static,
+    B::Close: Clone + Send + Sync + 'static,
+    B::SendMessage: Clone + Send + Sync + 'static,
+    B::ForwardMessage: Clone + Send + Sync + 'static,
+    B::CopyMessage: Clone + Send + Sync + 'static,
+    B::SendPhoto: Clone + Send + Sync + 'static,
+    B::SendAudio: Clone + Send + Sync + 'static,
+    B::SendDocument: Clone + Send + Sync + 'static,
+    B::SendVideo: Clone + Send + Sync + 'static,
+    B::SendAnimation: Clone + Send + Sync + 'static,
+    B::SendVoice: Clone + Send + Sync + 'static,
+    B::SendVideoNote: Clone + Send + Sync + 'static,
+    B::SendMediaGroup: Clone + Send + Sync + 'static,
+    B::SendLocation: Clone + Send + Sync + 'static,
+    B::EditMessageLiveLocation: Clone + Send + Sync + 'static,
+    B::EditMessageLiveLocationInline: Clone + Send + Sync + 'static,
+    B::StopMessageLiveLocation: Clone + Send + Sync + 'static,
+    B::StopMessageLiveLocationInline: Clone + Send + Sync + 'static,
+    B::SendVenue: Clone + Send + Sync + 'static,
+    B::SendContact: Clone + Send + Sync + 'static,
+    B::SendPoll: Clone + Send + Sync + 'static,
+    B::SendDice: Clone + Send + Sync + 'static,
+    B::SendChatAction: Clone + Send + Sync + 'static,
+    B::GetUserProfilePhotos: Clone + Send + Sync + 'static,
+    B::GetFile: Clone + Send + Sync + 'static,
+    B::BanChatMember: Clone + Send + Sync + 'static,
+    B::KickChatMember: Clone + Send + Sync + 'static,
+    B::UnbanChatMember: Clone + Send + Sync + 'static,
+    B::RestrictChatMember: Clone + Send + Sync + 'static,
+    B::PromoteChatMember: Clone + Send + Sync + 'static,
+    B::SetChatAdministratorCustomTitle: Clone + Send + Sync + 'static,
+    B::BanChatSenderChat: Clone + Send + Sync + 'static,
+    B::UnbanChatSenderChat: Clone + Send + Sync + 'static,
+    B::SetChatPermissions: Clone + Send + Sync + 'static,
+    B::ExportChatInviteLink: Clone + Send + Sync + 'static,
+    B::CreateChatInviteLink: Clone + Send + Sync + 'static,
+    B::EditChatInviteLink: Clone + Send + Sync + 'static,
+    B::RevokeChatInviteLink: Clone + Send + Sync + 'static,
+    B::ApproveChatJoinRequest: Clone + Send + Sync + 'static,
+    B::DeclineChatJoinRequest: Clone + Send + Sync + 'static,
+    B::SetChatPhoto: Clone + Send + Sync + 'static,
+    B::DeleteChatPhoto: Clone + Send + Sync + 'static,
+    B::SetChatTitle: Clone + Send + Sync + 'static,
+    B::SetChatDescription: Clone + Send + Sync + 'static,
+    B::PinChatMessage: Clone + Send + Sync + 'static,
+    B::UnpinChatMessage: Clone + Send + Sync + 'static,
+    B::UnpinAllChatMessages: Clone + Send + Sync + 'static,
+    B::LeaveChat: Clone + Send + Sync + 'static,
+    B::GetChat: Clone + Send + Sync + 'static,
+    B::GetChatAdministrators: Clone + Send + Sync + 'static,
+    B::GetChatMemberCount: Clone + Send + Sync + 'static,
+    B::GetChatMembersCount: Clone + Send + Sync + 'static,
+    B::GetChatMember: Clone + Send + Sync + 'static,
+    B::SetChatStickerSet: Clone + Send + Sync + 'static,
+    B::DeleteChatStickerSet: Clone + Send + Sync + 'static,
+    B::AnswerCallbackQuery: Clone + Send + Sync + 'static,
+    B::SetMyCommands: Clone + Send + Sync + 'static,
+    B::GetMyCommands: Clone + Send + Sync + 'static,
+    B::SetChatMenuButton: Clone + Send + Sync + 'static,
+    B::GetChatMenuButton: Clone + Send + Sync + 'static,
+    B::SetMyDefaultAdministratorRights: Clone + Send + Sync + 'static,
+    B::GetMyDefaultAdministratorRights: Clone + Send + Sync + 'static,
+    B::DeleteMyCommands: Clone + Send + Sync + 'static,
+    B::AnswerInlineQuery: Clone + Send + Sync + 'static,
+    B::AnswerWebAppQuery: Clone + Send + Sync + 'static,
+    B::EditMessageText: Clone + Send + Sync + 'static,
+    B::EditMessageTextInline: Clone + Send + Sync + 'static,
+    B::EditMessageCaption: Clone + Send + Sync + 'static,
+    B::EditMessageCaptionInline: Clone + Send + Sync + 'static,
+    B::EditMessageMedia: Clone + Send + Sync + 'static,
+    B::EditMessageMediaInline: Clone + Send + Sync + 'static,
+    B::EditMessageReplyMarkup: Clone + Send + Sync + 'static,
+    B::EditMessageReplyMarkupInline: Clone + Send + Sync + 'static,
+    B::StopPoll: Clone + Send + Sync + 'static,
+    B::DeleteMessage: Clone + Send + Sync + 'static,
+    B::SendSticker: Clone + Send + Sync + 'static,
+    B::GetStickerSet: Clone + Send + Sync + 'static,
+    B::UploadStickerFile: Clone + Send + Sync + 'static,
+    B::CreateNewStickerSet: Clone + Send + Sync + 'static,
+    B::AddStickerToSet: Clone + Send + Sync + 'static,
+    B::SetStickerPositionInSet: Clone + Send + Sync + 'static,
+    B::DeleteStickerFromSet: Clone + Send + Sync + 'static,
+    B::SetStickerSetThumb: Clone + Send + Sync + 'static,
+    B::SendInvoice: Clone + Send + Sync + 'static,
+    B::AnswerShippingQuery: Clone + Send + Sync + 'static,
+    B::AnswerPreCheckoutQuery: Clone + Send + Sync + 'static,
+    B::SetPassportDataErrors: Clone + Send + Sync + 'static,
+    B::SendGame: Clone + Send + Sync + 'static,
+    B::SetGameScore: Clone + Send + Sync + 'static,
+    B::SetGameScoreInline: Clone + Send + Sync + 'static,
+    B::GetGameHighScores: Clone + Send + Sync + 'static,
+{
+    type Err = B::Err;
+
+    requester_forward! {
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        get_me,
+        log_out,
+        close,
+        send_message,
+        forward_message,
+        copy_message,
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_media_group,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        ban_chat_member,
+        kick_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        approve_chat_join_request,
+        decline_chat_join_request,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_member_count,
+        get_chat_members_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_media,
+        edit_message_media_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        send_sticker,
+        get_sticker_set,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        send_invoice,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores
+        => f, fty
+    }
+}
+
+download_forward! {
+    'w
+    B
+    [P]
+    Retry<B, P>
+    { this => this.inner() }
+}
+
+/// Request returned by [`Retry`] methods.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct RetryRequest<R, P> {
+    request: Arc<R>,
+    policy: Arc<P>,
+}
+
+/// Future returned by [`RetryRequest`]s.
+#[pin_project::pin_project]
+pub struct RetrySend<R: Request>(#[pin] BoxFuture<'static, Result<Output<R>, R::Err>>);
+
+impl<R: HasPayload + Clone, P> HasPayload for RetryRequest<R, P> {
+    type Payload = R::Payload;
+
+    /// Note that if this request was already executed via `send_ref` and it
+    /// didn't yet completed, this method will clone the underlying request.
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        Arc::make_mut(&mut self.request).payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.request.payload_ref()
+    }
+}
+
+impl<R, P> Request for RetryRequest<R, P>
+where
+    R: Request + Clone + Send + Sync + 'static,
+    R::Err: AsResponseParameters + Send,
+    Output<R>: Send,
+    P: RetryPolicy<R::Err> + Send + Sync + 'static,
+{
+    type Err = R::Err;
+    type Send = RetrySend<R>;
+    type SendRef = RetrySend<R>;
+
+    fn send(self) -> Self::Send {
+        RetrySend(Box::pin(send(self.request, self.policy)))
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        RetrySend(Box::pin(send(
+            Arc::clone(&self.request),
+            Arc::clone(&self.policy),
+        )))
+    }
+}
+
+impl<R: Request> Future for RetrySend<R> {
+    type Output = Result<Output<R>, R::Err>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().0.poll(cx)
+    }
+}
+
+/// Actual implementation of the `RetrySend` future
+async fn send<R, P>(request: Arc<R>, policy: Arc<P>) -> Result<Output<R>, R::Err>
+where
+    R: Request,
+    P: RetryPolicy<R::Err>,
+{
+    let mut attempt = 1;
+
+    loop {
+        let err = match request.send_ref().await {
+            Err(err) => err,
+            res => return res,
+        };
+
+        match policy.retry_delay(attempt, &err) {
+            Some(delay) => {
+                log::warn!(
+                    "Request failed (attempt {}), retrying in {:?}: {}",
+                    attempt,
+                    delay,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            None => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        adaptors::retry::{is_transient, ExponentialBackoff, RetryPolicy},
+        errors::ApiErrorResponse,
+        ApiError, RequestError,
+    };
+
+    #[test]
+    fn backoff_grows_exponentially() {
+        let policy = ExponentialBackoff::default()
+            .no_jitter()
+            .max_attempts(10)
+            .initial_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5));
+        let err = RequestError::Api(ApiErrorResponse::new(502, "Bad Gateway"));
+
+        let delays: Vec<_> = (1..=5)
+            .map(|attempt| policy.retry_delay(attempt, &err))
+            .collect();
+        let secs = |s| Some(Duration::from_secs(s));
+        assert_eq!(delays, [secs(1), secs(2), secs(4), secs(5), secs(5)]);
+    }
+
+    #[test]
+    fn max_attempts() {
+        let policy = ExponentialBackoff::default().max_attempts(3);
+        let err = RequestError::Api(ApiErrorResponse::new(500, "Internal Server Error"));
+
+        assert!(policy.retry_delay(2, &err).is_some());
+        assert!(policy.retry_delay(3, &err).is_none());
+    }
+
+    #[test]
+    fn retry_after_is_honored() {
+        let policy = ExponentialBackoff::default();
+        let err = RequestError::RetryAfter(Duration::from_secs(42));
+
+        assert_eq!(policy.retry_delay(1, &err), Some(Duration::from_secs(42)));
+    }
+
+    #[test]
+    fn too_many_requests_is_retried() {
+        let policy = ExponentialBackoff::default();
+        let err = RequestError::Api(
+            ApiError::TooManyRequests {
+                retry_after: Duration::from_secs(7),
+            }
+            .into(),
+        );
+
+        assert!(is_transient(&err));
+        assert_eq!(policy.retry_delay(1, &err), Some(Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn jitter_stays_in_bounds() {
+        let policy = ExponentialBackoff::default().initial_delay(Duration::from_secs(8));
+        let err = RequestError::Api(ApiErrorResponse::new(502, "Bad Gateway"));
+
+        for _ in 0..100 {
+            let delay = policy.retry_delay(1, &err).unwrap();
+            assert!(delay >= Duration::from_secs(4) && delay <= Duration::from_secs(8));
+        }
+    }
+
+    #[test]
+    fn classification() {
+        assert!(is_transient(&RequestError::RetryAfter(
+            Duration::from_secs(1)
+        )));
+        assert!(!is_transient(&RequestError::Api(
+            ApiError::BotBlocked.into()
+        )));
+        assert!(!is_transient(&RequestError::MigrateToChatId(1)));
+
+        let policy = ExponentialBackoff::default();
+        let err = RequestError::Api(ApiError::ChatNotFound.into());
+        assert!(policy.retry_delay(1, &err).is_none());
+    }
+}
@@ -30,6 +30,16 @@ enum ShareableRequest<R> {
     Owned(Option<R>),
 }
 
+impl<R: HasPayload> Clone for ThrottlingRequest<R> {
+    fn clone(&self) -> Self {
+        Self {
+            request: Arc::clone(&self.request),
+            chat_id: self.chat_id,
+            worker: self.worker.clone(),
+        }
+    }
+}
+
 impl<R: HasPayload + Clone> HasPayload for ThrottlingRequest<R> {
     type Payload = R::Payload;
 
@@ -25,6 +25,15 @@ use crate::{
 /// TRACE teloxide_core::adaptors::trace > Sending `SendDice` request: SendDice { chat_id: Id(0), emoji: Some(Dice), disable_notification: None, reply_to_message_id: None, allow_sending_without_reply: None, reply_markup: None }
 /// TRACE teloxide_core::adaptors::trace > Got response from `SendDice` request: Ok(Message { id: 13812, date: 1625926524, chat: Chat { .. }, via_bot: None, kind: Dice(MessageDice { dice: Dice { emoji: Dice, value: 3 } }) })
 /// ```
+///
+/// If the `tracing` feature is enabled, this adaptor also opens a
+/// `teloxide_request` span (with `DEBUG` level) for each request. The span has
+/// the following fields:
+/// - `method`, name of the method (e.g. `SendMessage`),
+/// - `outcome`, `"ok"` or `"error"`, and `error`, recorded on completion.
+///
+/// Network requests made inside the span are instrumented too (with the size of
+/// JSON payloads and the response status), see [`net`](crate::net).
 #[derive(Clone, Debug)]
 pub struct Trace<B> {
     inner: B,
@@ -225,6 +234,16 @@ impl<R> TraceRequest<R>
 where
     R: Request,
 {
+    #[cfg(feature = "tracing")]
+    fn span(&self) -> tracing::Span {
+        tracing::debug_span!(
+            "teloxide_request",
+            method = <R::Payload as Payload>::NAME,
+            outcome = tracing::field::Empty,
+            error = tracing::field::Empty,
+        )
+    }
+
     fn trace_request(&self)
     where
         R::Payload: Debug,
@@ -294,6 +313,10 @@ where
 
         Send {
             trace_fn: self.trace_response_fn(),
+            #[cfg(feature = "tracing")]
+            span: self.span(),
+            #[cfg(feature = "tracing")]
+            record_outcome: record_outcome::<Output<R>, R::Err>,
             inner: self.inner.send(),
         }
     }
@@ -303,6 +326,10 @@ where
 
         Send {
             trace_fn: self.trace_response_fn(),
+            #[cfg(feature = "tracing")]
+            span: self.span(),
+            #[cfg(feature = "tracing")]
+            record_outcome: record_outcome::<Output<R>, R::Err>,
             inner: self.inner.send_ref(),
         }
     }
@@ -314,6 +341,10 @@ where
     F: Future,
 {
     trace_fn: fn(&F::Output),
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    record_outcome: fn(&tracing::Span, &F::Output),
     #[pin]
     inner: F,
 }
@@ -327,8 +358,28 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
 
+        #[cfg(feature = "tracing")]
+        let _enter = this.span.enter();
+
         let ret = ready!(this.inner.poll(cx));
         (this.trace_fn)(&ret);
+
+        #[cfg(feature = "tracing")]
+        (this.record_outcome)(this.span, &ret);
+
         Poll::Ready(ret)
     }
 }
+
+#[cfg(feature = "tracing")]
+fn record_outcome<T, E: Debug>(span: &tracing::Span, result: &Result<T, E>) {
+    match result {
+        Ok(_) => {
+            span.record("outcome", "ok");
+        }
+        Err(err) => {
+            span.record("outcome", "error");
+            span.record("error", tracing::field::debug(err));
+        }
+    }
+}
@@ -0,0 +1,840 @@
+use std::{
+    future::Future,
+    ops::RangeInclusive,
+    pin::Pin,
+    task::{self, Poll},
+};
+
+use futures::{
+    future::{self, BoxFuture, Either, Ready},
+    ready,
+};
+use url::Url;
+
+use crate::{
+    errors::ValidationError,
+    payloads,
+    requests::{HasPayload, MultipartPayload, Output, Payload, Request, Requester},
+    types::*,
+    utils::{html, markdown, ParseError},
+};
+
+/// Validates requests before sending them.
+///
+/// Many errors (e.g. [`ApiError::MessageIsTooLong`] or
+/// [`ApiError::ButtonDataInvalid`]) can be detected without sending a request
+/// to Telegram. This adaptor checks payloads of requests against limits of the
+/// Bot API (see [`validate`]) and returns [`RequestError::Validation`] without
+/// sending a request if a limit is violated.
+///
+/// Additionally, sizes of uploaded files are checked to be at most 50 MB (or
+/// 2000 MB in the [local mode]), if they are known without reading the files.
+///
+/// [`ApiError::MessageIsTooLong`]: crate::ApiError::MessageIsTooLong
+/// [`ApiError::ButtonDataInvalid`]: crate::ApiError::ButtonDataInvalid
+/// [`RequestError::Validation`]: crate::RequestError::Validation
+/// [local mode]: Validate::local_mode
+///
+/// ## Examples
+///
+/// ```no_run
+/// use teloxide_core::{
+///     errors::ValidationError,
+///     prelude::*,
+///     types::{ChatId, ParseMode},
+///     RequestError,
+/// };
+///
+/// # async {
+/// let bot = Bot::new("TOKEN").validate().parse_mode(ParseMode::Html);
+///
+/// let res = bot.send_message(ChatId(1), "<b>unclosed").send().await;
+/// assert!(matches!(
+///     res,
+///     Err(RequestError::Validation(
+///         ValidationError::CantParseEntities(_)
+///     ))
+/// ));
+/// # };
+/// ```
+#[derive(Clone, Debug)]
+pub struct Validate<B> {
+    inner: B,
+    max_file_size: u64,
+}
+
+/// The maximum size of an uploaded file, 50 MB.
+const MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// The maximum size of a file uploaded to a local Bot API server, 2000 MB.
+const MAX_LOCAL_FILE_SIZE: u64 = 2000 * 1024 * 1024;
+
+impl<B> Validate<B> {
+    /// Creates new validating adaptor.
+    ///
+    /// Note: it's recommended to use [`RequesterExt::validate`] instead.
+    ///
+    /// [`RequesterExt::validate`]: crate::requests::RequesterExt::validate
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            max_file_size: MAX_FILE_SIZE,
+        }
+    }
+
+    /// Relaxes limits for a local Bot API server, i.e. allows to upload files
+    /// up to 2000 MB.
+    ///
+    /// See also [`Bot::set_local_mode`].
+    ///
+    /// [`Bot::set_local_mode`]: crate::Bot::set_local_mode
+    pub fn local_mode(mut self, local: bool) -> Self {
+        self.max_file_size = match local {
+            true => MAX_LOCAL_FILE_SIZE,
+            false => MAX_FILE_SIZE,
+        };
+        self
+    }
+
+    /// Allows to access the inner bot.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Unwraps the inner bot.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        ValidateRequest<B::$T>
+    };
+}
+
+macro_rules! fwd_inner {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        ValidateRequest {
+            inner: $this.inner().$m($($arg),*),
+            max_file_size: $this.max_file_size,
+            files: |_| Vec::new(),
+        }
+    };
+}
+
+macro_rules! fwd_multipart {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        ValidateRequest {
+            inner: $this.inner().$m($($arg),*),
+            max_file_size: $this.max_file_size,
+            files: attached_files,
+        }
+    };
+}
+
+impl<B> Requester for Validate<B>
+where
+    B: Requester,
+    B::Err: From<ValidationError>,
+{
+    type Err = B::Err;
+
+    requester_forward! {
+        get_me,
+        log_out,
+        close,
+        get_updates,
+        delete_webhook,
+        get_webhook_info,
+        forward_message,
+        copy_message,
+        send_message,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        kick_chat_member,
+        ban_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_members_count,
+        get_chat_member_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        get_sticker_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        send_invoice,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores,
+        approve_chat_join_request,
+        decline_chat_join_request
+        => fwd_inner, fty
+    }
+
+    // Requests that may upload files
+    requester_forward! {
+        set_webhook,
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_media_group,
+        set_chat_photo,
+        edit_message_media,
+        edit_message_media_inline,
+        send_sticker,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_set_thumb
+        => fwd_multipart, fty
+    }
+}
+
+download_forward! {
+    'w
+    B
+    Validate<B>
+    { this => this.inner() }
+}
+
+/// Request returned by [`Validate`] methods.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+pub struct ValidateRequest<R: HasPayload> {
+    inner: R,
+    max_file_size: u64,
+    files: fn(&R::Payload) -> Vec<InputFile>,
+}
+
+/// Future returned by [`ValidateRequest`]s which passed validation.
+///
+/// Sizes of attached files are checked before sending the request.
+#[pin_project::pin_project]
+pub struct ValidateSend<F> {
+    check_files: Option<BoxFuture<'static, Result<(), ValidationError>>>,
+    #[pin]
+    inner: F,
+}
+
+impl<R> HasPayload for ValidateRequest<R>
+where
+    R: HasPayload,
+{
+    type Payload = R::Payload;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        self.inner.payload_mut()
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        self.inner.payload_ref()
+    }
+}
+
+impl<R> Request for ValidateRequest<R>
+where
+    R: Request,
+    R::Payload: ValidatePayload,
+    R::Err: From<ValidationError>,
+    Output<R>: Send,
+{
+    type Err = R::Err;
+
+    type Send = Either<Ready<Result<Output<R>, R::Err>>, ValidateSend<R::Send>>;
+
+    type SendRef = Either<Ready<Result<Output<R>, R::Err>>, ValidateSend<R::SendRef>>;
+
+    fn send(self) -> Self::Send {
+        match self.validate() {
+            Ok(()) => Either::Right(ValidateSend {
+                check_files: self.check_files(),
+                inner: self.inner.send(),
+            }),
+            Err(err) => Either::Left(future::err(err.into())),
+        }
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        match self.validate() {
+            Ok(()) => Either::Right(ValidateSend {
+                check_files: self.check_files(),
+                inner: self.inner.send_ref(),
+            }),
+            Err(err) => Either::Left(future::err(err.into())),
+        }
+    }
+}
+
+impl<F, T, E> Future for ValidateSend<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: From<ValidationError>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(check_files) = this.check_files {
+            let res = ready!(check_files.as_mut().poll(cx));
+            *this.check_files = None;
+
+            if let Err(err) = res {
+                return Poll::Ready(Err(err.into()));
+            }
+        }
+
+        this.inner.poll(cx)
+    }
+}
+
+impl<R> ValidateRequest<R>
+where
+    R: HasPayload,
+    R::Payload: ValidatePayload,
+{
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.inner.payload_ref().validate()
+    }
+
+    fn check_files(&self) -> Option<BoxFuture<'static, Result<(), ValidationError>>> {
+        let files = (self.files)(self.inner.payload_ref());
+
+        match files.is_empty() {
+            true => None,
+            false => Some(Box::pin(check_file_sizes(files, self.max_file_size))),
+        }
+    }
+}
+
+/// Returns files attached to `payload`.
+fn attached_files<P: MultipartPayload>(payload: &P) -> Vec<InputFile> {
+    let mut files = Vec::new();
+    payload.copy_files(&mut |file| files.push(file));
+    files
+}
+
+/// Checks that `files` are at most `limit` bytes.
+async fn check_file_sizes(files: Vec<InputFile>, limit: u64) -> Result<(), ValidationError> {
+    for file in files {
+        match file.size().await {
+            Some(size) if size > limit => return Err(ValidationError::FileSize { size, limit }),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Payloads which can be checked against limits of the Bot API.
+///
+/// This trait is implemented for all payloads, see [`validate`] for the list of
+/// checked limits.
+pub trait ValidatePayload: Payload {
+    /// Checks the payload against limits of the Bot API.
+    fn validate(&self) -> Result<(), ValidationError>;
+}
+
+/// Checks a payload against limits of the Bot API.
+///
+/// The following limits are checked:
+/// - text of [`SendMessage`] and [`EditMessageText`] must be 1-4096 characters
+///   long,
+/// - captions must be at most 1024 characters long,
+/// - callback data of inline keyboard buttons must be 1-64 bytes long,
+/// - [`SendPoll`] must have a 1-300 characters long question and 2-10 options
+///   which are 1-100 characters long,
+/// - [`SendMediaGroup`] must have 2-10 items,
+/// - [`AnswerInlineQuery`] must have at most 50 results.
+///
+/// Lengths of texts are measured after parsing entities (if [`ParseMode::Html`]
+/// or [`ParseMode::MarkdownV2`] is used, see [`utils`]), texts which can't be
+/// parsed are reported too. Lengths of texts with the deprecated
+/// [`ParseMode::Markdown`] are not checked.
+///
+/// [`SendMessage`]: crate::payloads::SendMessage
+/// [`EditMessageText`]: crate::payloads::EditMessageText
+/// [`SendPoll`]: crate::payloads::SendPoll
+/// [`SendMediaGroup`]: crate::payloads::SendMediaGroup
+/// [`AnswerInlineQuery`]: crate::payloads::AnswerInlineQuery
+/// [`utils`]: crate::utils
+pub fn validate<P>(payload: &P) -> Result<(), ValidationError>
+where
+    P: ValidatePayload,
+{
+    payload.validate()
+}
+
+macro_rules! impl_validate {
+    ($( $P:ident { $($check:ident),* } ),* $(,)?) => {
+        $(
+            impl ValidatePayload for payloads::$P {
+                fn validate(&self) -> Result<(), ValidationError> {
+                    $( impl_validate!(@$check self); )*
+                    Ok(())
+                }
+            }
+        )*
+    };
+    (@text $this:ident) => {
+        check_text(&$this.text, $this.parse_mode, 1..=4096, ValidationError::TextLength)?
+    };
+    (@caption $this:ident) => {
+        check_caption($this.caption.as_deref(), $this.parse_mode)?
+    };
+    (@reply_markup $this:ident) => {
+        if let Some(markup) = &$this.reply_markup {
+            check_inline_keyboard(markup.inline_keyboard())?
+        }
+    };
+    (@media $this:ident) => {
+        check_media(&$this.media)?
+    };
+    (@media_group $this:ident) => {{
+        if !(2..=10).contains(&$this.media.len()) {
+            return Err(ValidationError::MediaGroupSize($this.media.len()));
+        }
+        $this.media.iter().try_for_each(check_media)?
+    }};
+    (@poll $this:ident) => {{
+        check_text(&$this.question, None, 1..=300, ValidationError::PollQuestionLength)?;
+
+        if !(2..=10).contains(&$this.options.len()) {
+            return Err(ValidationError::PollOptionsCount($this.options.len()));
+        }
+        for option in &$this.options {
+            check_len(utf16_len(option), 1..=100, ValidationError::PollOptionLength)?;
+        }
+    }};
+    (@inline_query_results $this:ident) => {
+        if $this.results.len() > 50 {
+            return Err(ValidationError::InlineQueryResultsCount($this.results.len()));
+        }
+    };
+}
+
+impl_validate! {
+    AddStickerToSet {},
+    AnswerCallbackQuery {},
+    AnswerInlineQuery { inline_query_results },
+    AnswerPreCheckoutQuery {},
+    AnswerShippingQuery {},
+    AnswerWebAppQuery {},
+    ApproveChatJoinRequest {},
+    BanChatMember {},
+    BanChatSenderChat {},
+    Close {},
+    CopyMessage { caption, reply_markup },
+    CreateChatInviteLink {},
+    CreateNewStickerSet {},
+    DeclineChatJoinRequest {},
+    DeleteChatPhoto {},
+    DeleteChatStickerSet {},
+    DeleteMessage {},
+    DeleteMyCommands {},
+    DeleteStickerFromSet {},
+    DeleteWebhook {},
+    EditChatInviteLink {},
+    EditMessageCaption { caption, reply_markup },
+    EditMessageCaptionInline { caption, reply_markup },
+    EditMessageLiveLocation { reply_markup },
+    EditMessageLiveLocationInline { reply_markup },
+    EditMessageMedia { media, reply_markup },
+    EditMessageMediaInline { media, reply_markup },
+    EditMessageReplyMarkup { reply_markup },
+    EditMessageReplyMarkupInline { reply_markup },
+    EditMessageText { text, reply_markup },
+    EditMessageTextInline { text, reply_markup },
+    ExportChatInviteLink {},
+    ForwardMessage {},
+    GetChat {},
+    GetChatAdministrators {},
+    GetChatMember {},
+    GetChatMemberCount {},
+    GetChatMembersCount {},
+    GetChatMenuButton {},
+    GetFile {},
+    GetGameHighScores {},
+    GetMe {},
+    GetMyCommands {},
+    GetMyDefaultAdministratorRights {},
+    GetStickerSet {},
+    GetUpdates {},
+    GetUserProfilePhotos {},
+    GetWebhookInfo {},
+    KickChatMember {},
+    LeaveChat {},
+    LogOut {},
+    PinChatMessage {},
+    PromoteChatMember {},
+    RestrictChatMember {},
+    RevokeChatInviteLink {},
+    SendAnimation { caption, reply_markup },
+    SendAudio { caption, reply_markup },
+    SendChatAction {},
+    SendContact { reply_markup },
+    SendDice { reply_markup },
+    SendDocument { caption, reply_markup },
+    SendGame { reply_markup },
+    SendInvoice { reply_markup },
+    SendLocation { reply_markup },
+    SendMediaGroup { media_group },
+    SendMessage { text, reply_markup },
+    SendPhoto { caption, reply_markup },
+    SendPoll { poll, reply_markup },
+    SendSticker { reply_markup },
+    SendVenue { reply_markup },
+    SendVideo { caption, reply_markup },
+    SendVideoNote { reply_markup },
+    SendVoice { caption, reply_markup },
+    SetChatAdministratorCustomTitle {},
+    SetChatDescription {},
+    SetChatMenuButton {},
+    SetChatPermissions {},
+    SetChatPhoto {},
+    SetChatStickerSet {},
+    SetChatTitle {},
+    SetGameScore {},
+    SetGameScoreInline {},
+    SetMyCommands {},
+    SetMyDefaultAdministratorRights {},
+    SetPassportDataErrors {},
+    SetStickerPositionInSet {},
+    SetStickerSetThumb {},
+    SetWebhook {},
+    StopMessageLiveLocation { reply_markup },
+    StopMessageLiveLocationInline { reply_markup },
+    StopPoll { reply_markup },
+    UnbanChatMember {},
+    UnbanChatSenderChat {},
+    UnpinAllChatMessages {},
+    UnpinChatMessage {},
+    UploadStickerFile {},
+}
+
+/// Markups which may contain an inline keyboard.
+trait InlineKeyboard {
+    fn inline_keyboard(&self) -> Option<&InlineKeyboardMarkup>;
+}
+
+impl InlineKeyboard for InlineKeyboardMarkup {
+    fn inline_keyboard(&self) -> Option<&InlineKeyboardMarkup> {
+        Some(self)
+    }
+}
+
+impl InlineKeyboard for ReplyMarkup {
+    fn inline_keyboard(&self) -> Option<&InlineKeyboardMarkup> {
+        match self {
+            ReplyMarkup::InlineKeyboard(markup) => Some(markup),
+            _ => None,
+        }
+    }
+}
+
+fn check_inline_keyboard(markup: Option<&InlineKeyboardMarkup>) -> Result<(), ValidationError> {
+    let buttons = markup.iter().flat_map(|m| &m.inline_keyboard).flatten();
+    for button in buttons {
+        if let InlineKeyboardButtonKind::CallbackData(data) = &button.kind {
+            check_len(data.len(), 1..=64, ValidationError::CallbackDataLength)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_media(media: &InputMedia) -> Result<(), ValidationError> {
+    let (caption, parse_mode) = match media {
+        InputMedia::Photo(m) => (&m.caption, m.parse_mode),
+        InputMedia::Video(m) => (&m.caption, m.parse_mode),
+        InputMedia::Animation(m) => (&m.caption, m.parse_mode),
+        InputMedia::Audio(m) => (&m.caption, m.parse_mode),
+        InputMedia::Document(m) => (&m.caption, m.parse_mode),
+    };
+
+    check_caption(caption.as_deref(), parse_mode)
+}
+
+fn check_caption(
+    caption: Option<&str>,
+    parse_mode: Option<ParseMode>,
+) -> Result<(), ValidationError> {
+    match caption {
+        Some(caption) => check_text(
+            caption,
+            parse_mode,
+            0..=1024,
+            ValidationError::CaptionLength,
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Checks length of a `text`, taking its `parse_mode` into account.
+fn check_text(
+    text: &str,
+    parse_mode: Option<ParseMode>,
+    range: RangeInclusive<usize>,
+    error: fn(usize) -> ValidationError,
+) -> Result<(), ValidationError> {
+    #[allow(deprecated)]
+    let len = match parse_mode {
+        None => utf16_len(text),
+        Some(ParseMode::Html) => parse_len(html::parse(text))?,
+        Some(ParseMode::MarkdownV2) => parse_len(markdown::parse(text))?,
+        Some(ParseMode::Markdown) => return Ok(()),
+    };
+
+    check_len(len, range, error)
+}
+
+fn parse_len(
+    parsed: Result<(String, Vec<MessageEntity>), ParseError>,
+) -> Result<usize, ValidationError> {
+    parsed
+        .map(|(text, _)| utf16_len(&text))
+        .map_err(ValidationError::CantParseEntities)
+}
+
+fn check_len(
+    len: usize,
+    range: RangeInclusive<usize>,
+    error: fn(usize) -> ValidationError,
+) -> Result<(), ValidationError> {
+    match range.contains(&len) {
+        true => Ok(()),
+        false => Err(error(len)),
+    }
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        adaptors::validate::validate,
+        errors::ValidationError,
+        payloads::*,
+        types::{
+            ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, InputMedia,
+            InputMediaPhoto, ParseMode,
+        },
+    };
+
+    #[test]
+    fn text_length() {
+        let long = "a".repeat(4097);
+
+        assert_eq!(
+            validate(&SendMessage::new(ChatId(1), &long)),
+            Err(ValidationError::TextLength(4097))
+        );
+        assert_eq!(
+            validate(&SendMessage::new(ChatId(1), "")),
+            Err(ValidationError::TextLength(0))
+        );
+        // Limits apply to the text after parsing entities
+        assert_eq!(
+            validate(
+                &SendMessage::new(ChatId(1), format!("*{}*", &long[1..]))
+                    .parse_mode(ParseMode::MarkdownV2)
+            ),
+            Ok(())
+        );
+        assert!(matches!(
+            validate(&SendMessage::new(ChatId(1), "<b>a").parse_mode(ParseMode::Html)),
+            Err(ValidationError::CantParseEntities(_))
+        ));
+
+        let photo = || SendPhoto::new(ChatId(1), InputFile::file_id("id"));
+        assert_eq!(validate(&photo().caption("a".repeat(1024))), Ok(()));
+        assert_eq!(
+            validate(&photo().caption("a".repeat(1025))),
+            Err(ValidationError::CaptionLength(1025))
+        );
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!(
+            validate(&SendPoll::new(ChatId(1), "?", ["a".to_owned()])),
+            Err(ValidationError::PollOptionsCount(1))
+        );
+        assert_eq!(
+            validate(&SendPoll::new(
+                ChatId(1),
+                "?",
+                ["a".to_owned(), "b".repeat(101)]
+            )),
+            Err(ValidationError::PollOptionLength(101))
+        );
+
+        let photo = || InputMedia::Photo(InputMediaPhoto::new(InputFile::file_id("id")));
+        assert_eq!(
+            validate(&SendMediaGroup::new(ChatId(1), [photo()])),
+            Err(ValidationError::MediaGroupSize(1))
+        );
+        assert_eq!(
+            validate(&SendMediaGroup::new(ChatId(1), [photo(), photo()])),
+            Ok(())
+        );
+
+        let long_caption = InputMedia::Photo(
+            InputMediaPhoto::new(InputFile::file_id("id")).caption("a".repeat(1025)),
+        );
+        assert_eq!(
+            validate(&SendMediaGroup::new(
+                ChatId(1),
+                [photo(), long_caption.clone()]
+            )),
+            Err(ValidationError::CaptionLength(1025))
+        );
+        assert_eq!(
+            validate(&EditMessageMedia::new(ChatId(1), 1, long_caption)),
+            Err(ValidationError::CaptionLength(1025))
+        );
+
+        let markup = |data: &str| {
+            InlineKeyboardMarkup::new([[
+                InlineKeyboardButton::url("a", "https://example.com".parse().unwrap()),
+                InlineKeyboardButton::callback("b", data),
+            ]])
+        };
+        assert_eq!(
+            validate(&SendMessage::new(ChatId(1), "a").reply_markup(markup(&"d".repeat(64)))),
+            Ok(())
+        );
+        assert_eq!(
+            validate(&SendMessage::new(ChatId(1), "a").reply_markup(markup(&"d".repeat(65)))),
+            Err(ValidationError::CallbackDataLength(65))
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn invalid_requests_are_not_sent() {
+        use crate::{
+            adaptors::Validate,
+            requests::{Request, Requester},
+            testing::MockBot,
+            ApiError, RequestError,
+        };
+
+        let mock = MockBot::new();
+        mock.respond::<SendMessage>(Err(RequestError::Api(ApiError::BotBlocked.into())));
+        let bot = Validate::new(mock.clone());
+
+        let err = bot.send_message(ChatId(1), "").send().await.unwrap_err();
+        assert!(matches!(
+            err,
+            RequestError::Validation(ValidationError::TextLength(0))
+        ));
+        mock.assert_no_requests();
+
+        let err = bot.send_message(ChatId(1), "a").send().await.unwrap_err();
+        assert_eq!(err.api_error(), Some(&ApiError::BotBlocked));
+        let _ = mock.assert_message_sent(ChatId(1), "a");
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn file_sizes() {
+        use crate::{
+            adaptors::Validate,
+            requests::{Request, Requester},
+            testing::MockBot,
+            ApiError, RequestError,
+        };
+
+        let path = std::env::temp_dir().join(format!("{}.bin", uuid::Uuid::new_v4()));
+        // Sparse file, which doesn't actually take space
+        std::fs::File::create(&path)
+            .unwrap()
+            .set_len(60 * 1024 * 1024)
+            .unwrap();
+
+        let mock = MockBot::new();
+        mock.respond::<SendDocument>(Err(RequestError::Api(ApiError::BotBlocked.into())));
+        let bot = Validate::new(mock.clone());
+        let err = bot
+            .send_document(ChatId(1), InputFile::file(&path))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RequestError::Validation(ValidationError::FileSize {
+                size: 62914560,
+                limit: 52428800
+            })
+        ));
+        mock.assert_no_requests();
+
+        let bot = bot.local_mode(true);
+        let err = bot
+            .send_document(ChatId(1), InputFile::file(&path))
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.api_error(), Some(&ApiError::BotBlocked));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
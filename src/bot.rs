@@ -1,5 +1,7 @@
-use std::{future::Future, sync::Arc};
+use std::{future::Future, sync::Arc, time::Duration};
 
+#[cfg(any(feature = "testing", feature = "record"))]
+use once_cell::sync::Lazy;
 use reqwest::Client;
 use serde::{de::DeserializeOwned, Serialize};
 
@@ -14,6 +16,13 @@ mod download;
 
 const TELOXIDE_TOKEN: &str = "TELOXIDE_TOKEN";
 
+/// The timeout of clients created with [`net::default_reqwest_settings`].
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(17);
+
+/// The slowest upload speed (in bytes per second) which is still expected to
+/// finish uploading files in time.
+const MIN_UPLOAD_SPEED: u64 = 64 * 1024;
+
 /// A requests sender.
 ///
 /// This is the main type of the library, it allows to send requests to the
@@ -56,6 +65,11 @@ pub struct Bot {
     token: Arc<str>,
     api_url: Arc<reqwest::Url>,
     client: Client,
+    local_mode: bool,
+    timeout: Option<Duration>,
+    /// The timeout of the client, if it's known (i.e. the client was created
+    /// by the bot).
+    client_timeout: Option<Duration>,
 }
 
 /// Constructors
@@ -74,7 +88,10 @@ impl Bot {
             .build()
             .expect("Client creation failed");
 
-        Self::with_client(token, client)
+        Self {
+            client_timeout: Some(DEFAULT_TIMEOUT),
+            ..Self::with_client(token, client)
+        }
     }
 
     /// Creates a new `Bot` with the specified token and your
@@ -101,6 +118,9 @@ impl Bot {
             token,
             api_url,
             client,
+            local_mode: false,
+            timeout: None,
+            client_timeout: None,
         }
     }
 
@@ -119,7 +139,10 @@ impl Bot {
     /// [`reqwest::Client`]: https://docs.rs/reqwest/0.10.1/reqwest/struct.Client.html
     /// [`reqwest::Proxy::all`]: https://docs.rs/reqwest/latest/reqwest/struct.Proxy.html#method.all
     pub fn from_env() -> Self {
-        Self::from_env_with_client(crate::net::client_from_env())
+        Self {
+            client_timeout: Some(DEFAULT_TIMEOUT),
+            ..Self::from_env_with_client(crate::net::client_from_env())
+        }
     }
 
     /// Creates a new `Bot` with the `TELOXIDE_TOKEN` environmental variable (a
@@ -181,6 +204,79 @@ impl Bot {
         self.api_url = Arc::new(url);
         self
     }
+
+    /// Enables or disables the local mode.
+    ///
+    /// Use this with your own [Telegram bot API server][tbas] started with the
+    /// `--local` flag (see [`set_api_url`]). In the local mode:
+    /// - files with absolute paths (which are returned by [`GetFile`] from a
+    ///   local server) are [downloaded] directly from the filesystem,
+    /// - files created with [`InputFile::file`] are sent as `file://` urls
+    ///   instead of being uploaded, so the server must have access to them.
+    ///
+    /// Note that [`Download::download_file_stream`] always downloads files
+    /// over HTTP.
+    ///
+    /// Local servers allow to upload files up to 2000 MB, see
+    /// [`Validate::local_mode`] to relax the validation accordingly.
+    ///
+    /// [tbas]: https://github.com/tdlib/telegram-bot-api
+    /// [`set_api_url`]: Bot::set_api_url
+    /// [`GetFile`]: crate::payloads::GetFile
+    /// [downloaded]: crate::net::Download
+    /// [`InputFile::file`]: crate::types::InputFile::file
+    /// [`Download::download_file_stream`]: crate::net::Download::download_file_stream
+    /// [`Validate::local_mode`]: crate::adaptors::Validate::local_mode
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use teloxide_core::Bot;
+    ///
+    /// let url = reqwest::Url::parse("http://localhost:8081").unwrap();
+    /// let bot = Bot::new("TOKEN").set_api_url(url).set_local_mode(true);
+    ///
+    /// assert!(bot.local_mode());
+    /// ```
+    pub fn set_local_mode(mut self, local: bool) -> Self {
+        self.local_mode = local;
+        self
+    }
+
+    /// Sets the base timeout of requests.
+    ///
+    /// The timeout is extended for requests which may take long time:
+    /// - [`Payload::timeout_hint`] (e.g. the `timeout` of [`GetUpdates`]) is
+    ///   added to it,
+    /// - requests with files get additional time to upload them, proportional
+    ///   to the size of the files (if it's known, i.e. for in-memory files and
+    ///   files on disk).
+    ///
+    /// By default the base timeout is not set and requests use the timeout
+    /// configured in the [`reqwest::Client`]. If the client was created by the
+    /// bot (i.e. by [`Bot::new`] or [`Bot::from_env`]), its timeout is known
+    /// (17 seconds, see [`net::default_reqwest_settings`]), so it's extended
+    /// for requests which need more time. Timeouts of clients passed to
+    /// [`Bot::with_client`] are never overridden unless the base timeout is
+    /// set.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use teloxide_core::Bot;
+    ///
+    /// let bot = Bot::new("TOKEN").set_timeout(Duration::from_secs(30));
+    /// assert_eq!(bot.timeout(), Some(Duration::from_secs(30)));
+    /// ```
+    ///
+    /// [`GetUpdates`]: crate::payloads::GetUpdates
+    /// [`net::default_reqwest_settings`]: crate::net::default_reqwest_settings
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 }
 
 /// Getters
@@ -199,6 +295,29 @@ impl Bot {
     pub fn api_url(&self) -> reqwest::Url {
         reqwest::Url::clone(&*self.api_url)
     }
+
+    /// Returns `true` if the [local mode] is enabled.
+    ///
+    /// [local mode]: Bot::set_local_mode
+    pub fn local_mode(&self) -> bool {
+        self.local_mode
+    }
+
+    /// Returns the base timeout of requests, see [`Bot::set_timeout`].
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+#[cfg(any(feature = "testing", feature = "record"))]
+impl Bot {
+    /// Returns a bot which is only used to build payloads by requesters which
+    /// don't send requests to Telegram (e.g. `MockBot` and `Replay`).
+    pub(crate) fn payload_builder() -> &'static Self {
+        static BOT: Lazy<Bot> = Lazy::new(|| Bot::with_client("", Client::new()));
+
+        &BOT
+    }
 }
 
 impl Bot {
@@ -214,7 +333,11 @@ impl Bot {
         let token = Arc::clone(&self.token);
         let api_url = Arc::clone(&self.api_url);
 
-        let timeout_hint = payload.timeout_hint();
+        let timeout = request_timeout(
+            self.timeout,
+            self.client_timeout,
+            payload.timeout_hint().unwrap_or_default(),
+        );
         let params = serde_json::to_vec(payload)
             // this `expect` should be ok since we don't write request those may trigger error here
             .expect("serialization of request to be infallible");
@@ -227,7 +350,7 @@ impl Bot {
                 reqwest::Url::clone(&*api_url),
                 P::NAME,
                 params,
-                timeout_hint,
+                timeout,
             )
             .await
         }
@@ -244,20 +367,23 @@ impl Bot {
         let client = self.client.clone();
         let token = Arc::clone(&self.token);
         let api_url = Arc::clone(&self.api_url);
+        let (bot_timeout, client_timeout) = (self.timeout, self.client_timeout);
 
-        let timeout_hint = payload.timeout_hint();
-        let params = serde_multipart::to_form(payload);
+        let timeout_hint = payload.timeout_hint().unwrap_or_default();
+        let params = serde_multipart::to_form(payload, self.local_mode);
 
         // async move to capture client&token&api_url&params
         async move {
-            let params = params?.await;
+            let (params, size) = params?.await;
+            let extra = timeout_hint + upload_time(size);
+            let timeout = request_timeout(bot_timeout, client_timeout, extra);
             net::request_multipart(
                 &client,
                 token.as_ref(),
                 reqwest::Url::clone(&*api_url),
                 P::NAME,
                 params,
-                timeout_hint,
+                timeout,
             )
             .await
         }
@@ -274,26 +400,115 @@ impl Bot {
         let client = self.client.clone();
         let token = Arc::clone(&self.token);
         let api_url = self.api_url.clone();
+        let (bot_timeout, client_timeout) = (self.timeout, self.client_timeout);
 
-        let timeout_hint = payload.timeout_hint();
-        let params = serde_multipart::to_form_ref(payload);
+        let timeout_hint = payload.timeout_hint().unwrap_or_default();
+        let params = serde_multipart::to_form_ref(payload, self.local_mode);
 
         // async move to capture client&token&api_url&params
         async move {
-            let params = params?.await;
+            let (params, size) = params?.await;
+            let extra = timeout_hint + upload_time(size);
+            let timeout = request_timeout(bot_timeout, client_timeout, extra);
             net::request_multipart(
                 &client,
                 token.as_ref(),
                 reqwest::Url::clone(&*api_url),
                 P::NAME,
                 params,
-                timeout_hint,
+                timeout,
             )
             .await
         }
     }
 }
 
+/// Returns the timeout for a request which needs `extra` time (see
+/// [`Bot::set_timeout`]), `None` means that the timeout of the client is used.
+fn request_timeout(
+    timeout: Option<Duration>,
+    client_timeout: Option<Duration>,
+    extra: Duration,
+) -> Option<Duration> {
+    match (timeout, client_timeout) {
+        (Some(timeout), _) => Some(timeout + extra),
+        // The timeout of an unknown client can't be extended, so it's not
+        // overridden
+        (None, Some(client_timeout)) if extra > Duration::ZERO => Some(client_timeout + extra),
+        (None, _) => None,
+    }
+}
+
+/// Returns time needed to upload `size` bytes at [`MIN_UPLOAD_SPEED`].
+fn upload_time(size: u64) -> Duration {
+    Duration::from_millis(size.saturating_mul(1000) / MIN_UPLOAD_SPEED)
+}
+
 fn get_env(env: &'static str) -> String {
     std::env::var(env).unwrap_or_else(|_| panic!("Cannot get the {} env variable", env))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn request_timeout() {
+        use super::{request_timeout, upload_time};
+
+        let secs = Duration::from_secs;
+        let extra = upload_time(640 * 1024);
+        assert_eq!(extra, secs(10));
+
+        assert_eq!(
+            request_timeout(Some(secs(10)), None, Duration::ZERO),
+            Some(secs(10))
+        );
+        assert_eq!(request_timeout(Some(secs(10)), None, extra), Some(secs(20)));
+        assert_eq!(
+            request_timeout(Some(secs(10)), Some(secs(17)), extra),
+            Some(secs(20))
+        );
+
+        // The timeout of the client is used, unless more time is needed
+        assert_eq!(request_timeout(None, Some(secs(17)), Duration::ZERO), None);
+        assert_eq!(request_timeout(None, Some(secs(17)), extra), Some(secs(27)));
+
+        // Timeouts of custom clients are not overridden
+        assert_eq!(request_timeout(None, None, Duration::ZERO), None);
+        assert_eq!(request_timeout(None, None, extra), None);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn local_mode_sends_file_urls() {
+        use crate::{
+            requests::{Request, Requester},
+            testing::fake_server::FakeServer,
+            types::{ChatId, InputFile, InputMedia, InputMediaPhoto},
+        };
+
+        let server = FakeServer::start().unwrap();
+        let bot = server.bot().set_local_mode(true);
+        let url = InputFile::file("./media/logo.png").local_url().unwrap();
+
+        let request = bot.send_document(ChatId(1), InputFile::file("./media/logo.png"));
+        let _ = request.send_ref().await;
+        let _ = request.send().await;
+
+        let media = InputMedia::Photo(InputMediaPhoto::new(InputFile::file("./media/logo.png")));
+        let _ = bot.send_media_group(ChatId(1), [media]).send().await;
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        for request in &requests[..2] {
+            assert_eq!(request.params["document"], url.as_str());
+            assert!(request.files.is_empty());
+        }
+
+        let media: serde_json::Value =
+            serde_json::from_str(requests[2].params["media"].as_str().unwrap()).unwrap();
+        assert_eq!(media[0]["media"], url.as_str());
+        assert!(requests[2].files.is_empty());
+    }
+}
@@ -1,10 +1,12 @@
+use std::path::{Path, PathBuf};
+
 use bytes::Bytes;
 use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
 use tokio::io::AsyncWrite;
 
 use crate::{
     bot::Bot,
-    net::{self, Download},
+    net::{self, Download, DownloadOptions, DownloadWith},
     DownloadError,
 };
 
@@ -20,6 +22,10 @@ impl<'w> Download<'w> for Bot {
         path: &str,
         destination: &'w mut (dyn AsyncWrite + Unpin + Send),
     ) -> Self::Fut {
+        if let Some(path) = self.local_path(path) {
+            return net::read_local_file(path, destination, DownloadOptions::new()).boxed();
+        }
+
         net::download_file(
             &self.client,
             reqwest::Url::clone(&*self.api_url),
@@ -45,3 +51,91 @@ impl<'w> Download<'w> for Bot {
         .boxed()
     }
 }
+
+impl<'w> DownloadWith<'w> for Bot {
+    fn download_file_with(
+        &self,
+        path: &str,
+        destination: &'w mut (dyn AsyncWrite + Unpin + Send),
+        options: DownloadOptions,
+    ) -> Self::Fut {
+        if let Some(path) = self.local_path(path) {
+            return net::read_local_file(path, destination, options).boxed();
+        }
+
+        net::download_file_with(
+            &self.client,
+            reqwest::Url::clone(&*self.api_url),
+            &self.token,
+            path,
+            destination,
+            options,
+        )
+        .boxed()
+    }
+}
+
+impl Bot {
+    /// Returns the path of a file on the filesystem, if the local mode is
+    /// enabled and `path` is absolute.
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        let path = Path::new(path);
+        (self.local_mode && path.is_absolute()).then(|| path.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        net::{Download, DownloadOptions, DownloadWith},
+        Bot,
+    };
+
+    #[tokio::test]
+    async fn local_mode() {
+        let path = std::env::temp_dir().join(format!("{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"hello world").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let bot = Bot::new("TOKEN").set_local_mode(true);
+
+        let mut buf = Vec::new();
+        bot.download_file(path_str, &mut buf).await.unwrap();
+        assert_eq!(buf, b"hello world");
+
+        let mut buf = b"hello".to_vec();
+        let options = DownloadOptions::new().resume_from(5).expected_size(11);
+        bot.download_file_with(path_str, &mut buf, options)
+            .await
+            .unwrap();
+        assert_eq!(buf, b"hello world");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn local_mode_upload() {
+        use crate::{
+            requests::{Request, Requester},
+            testing::fake_server::FakeServer,
+            types::{ChatId, InputFile},
+        };
+
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        let bot = server.bot().set_local_mode(true);
+
+        let path = std::env::temp_dir().join("video.mp4");
+        let request = bot.send_document(ChatId(1), InputFile::file(&path));
+        let _ = request.send_ref().await;
+        let _ = request.send().await;
+
+        let expected = url::Url::from_file_path(&path).unwrap();
+        for request in server.requests() {
+            assert_eq!(request.params["document"], expected.as_str());
+            assert!(request.files.is_empty());
+        }
+        assert_eq!(server.requests().len(), 2);
+    }
+}
@@ -2,17 +2,19 @@
 
 use std::{io, time::Duration};
 
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use thiserror::Error;
 
-use crate::types::ResponseParameters;
+use crate::{types::ResponseParameters, utils::ParseError};
+
+mod parse;
 
 /// An error caused by sending a request to Telegram.
 #[derive(Debug, Error)]
 pub enum RequestError {
     /// A Telegram API error.
     #[error("A Telegram's error: {0}")]
-    Api(#[source] ApiError),
+    Api(#[source] ApiErrorResponse),
 
     /// The group has been migrated to a supergroup with the specified
     /// identifier.
@@ -46,6 +48,214 @@ pub enum RequestError {
     /// Occurs when trying to send a file to Telegram.
     #[error("An I/O error: {0}")]
     Io(#[source] io::Error),
+
+    /// The request violates limits of the Bot API, so it wasn't sent.
+    ///
+    /// This error is only returned by the [`Validate`] adaptor.
+    ///
+    /// [`Validate`]: crate::adaptors::Validate
+    #[error("An invalid request: {0}")]
+    Validation(#[source] ValidationError),
+}
+
+impl RequestError {
+    /// Returns the kind of the Telegram API error, if this is an API error.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::Api(err) => Some(&err.kind),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the request may succeed if it's sent again (possibly
+    /// after a delay).
+    ///
+    /// These are network errors, flood control errors
+    /// ([`RequestError::RetryAfter`] and [`ApiError::TooManyRequests`]) and
+    /// internal errors of the Telegram server (API errors with `error_code`
+    /// 500 or greater).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network(_) | Self::RetryAfter(_) => true,
+            Self::Api(err) => {
+                err.error_code >= 500 || matches!(err.kind, ApiError::TooManyRequests { .. })
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the bot can't send messages to the chat anymore, see
+    /// [`ApiError::is_chat_unreachable`].
+    ///
+    /// All errors with `error_code` 403 (`Forbidden`) are considered to be such
+    /// errors, even if they are unknown to `teloxide`.
+    ///
+    /// Note that [`RequestError::MigrateToChatId`] is not such an error, the
+    /// chat can be reached by its new identifier.
+    pub fn is_chat_unreachable(&self) -> bool {
+        match self {
+            Self::Api(err) => err.error_code == 403 || err.kind.is_chat_unreachable(),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the bot doesn't have enough rights in the chat, see
+    /// [`ApiError::is_permission_error`].
+    pub fn is_permission_error(&self) -> bool {
+        matches!(self, Self::Api(err) if err.kind.is_permission_error())
+    }
+
+    /// Returns `true` if the request itself is invalid, so sending it again
+    /// won't help, see [`ApiError::is_user_input_error`].
+    ///
+    /// Requests rejected by the [`Validate`] adaptor are such errors too.
+    ///
+    /// [`Validate`]: crate::adaptors::Validate
+    pub fn is_user_input_error(&self) -> bool {
+        match self {
+            Self::Api(err) => err.kind.is_user_input_error(),
+            Self::Validation(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// An error returned by Telegram in response to a request.
+///
+/// Besides the parsed [`ApiError`], this contains everything Telegram has sent:
+/// the `error_code` (which mostly follows HTTP status codes, e.g. `403` means
+/// that the bot can't send messages to the chat anymore) and the raw
+/// `description`, which is useful for errors unknown to `teloxide`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{description}")]
+#[non_exhaustive]
+pub struct ApiErrorResponse {
+    /// The kind of the error, parsed from the `description`.
+    pub kind: ApiError,
+
+    /// The error code, e.g. `400`, `403` or `429`.
+    pub error_code: u16,
+
+    /// The description of the error, as sent by Telegram.
+    pub description: String,
+
+    /// Name of the method which has failed (e.g. `SendMessage`), if known.
+    pub method: Option<String>,
+}
+
+impl ApiErrorResponse {
+    /// Creates an error from the `error_code` and `description` sent by
+    /// Telegram.
+    pub fn new(error_code: u16, description: impl Into<String>) -> Self {
+        let description = description.into();
+        let kind = parse::parse(&description);
+
+        Self {
+            kind,
+            error_code,
+            description,
+            method: None,
+        }
+    }
+
+    /// Sets the name of the method which has failed.
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+}
+
+impl From<ApiError> for ApiErrorResponse {
+    /// Creates an error with the description Telegram uses for `kind` and the
+    /// error code guessed from the description.
+    fn from(kind: ApiError) -> Self {
+        let description = match &kind {
+            ApiError::Unknown(description) => description.clone(),
+            kind => kind.to_string(),
+        };
+
+        Self {
+            error_code: default_error_code(&description),
+            kind,
+            description,
+            method: None,
+        }
+    }
+}
+
+/// Guesses the error code from the description of an error.
+///
+/// Descriptions sent by Telegram start with the name of the HTTP status that
+/// matches the error code, e.g. `Forbidden: bot was blocked by the user`.
+pub(crate) fn default_error_code(description: &str) -> u16 {
+    match description.split(':').next().unwrap_or_default().trim() {
+        "Unauthorized" => 401,
+        "Forbidden" => 403,
+        "Not Found" => 404,
+        "Conflict" => 409,
+        "Too Many Requests" => 429,
+        "Internal Server Error" => 500,
+        "Bad Gateway" => 502,
+        "Service Unavailable" => 503,
+        "Gateway Timeout" => 504,
+        _ => 400,
+    }
+}
+
+/// A violation of the Bot API limits, detected before sending a request.
+///
+/// Lengths of texts are measured in UTF-16 code units (like Telegram does) and
+/// after parsing entities, lengths of callback data are measured in bytes.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// Text of a message is empty or longer than 4096 characters.
+    #[error("Text of a message must be 1-4096 characters long, but it's {0}")]
+    TextLength(usize),
+
+    /// Caption is longer than 1024 characters.
+    #[error("Caption must be at most 1024 characters long, but it's {0}")]
+    CaptionLength(usize),
+
+    /// Callback data of an inline keyboard button is empty or longer than 64
+    /// bytes.
+    #[error("Callback data must be 1-64 bytes long, but it's {0}")]
+    CallbackDataLength(usize),
+
+    /// Poll question is empty or longer than 300 characters.
+    #[error("Poll question must be 1-300 characters long, but it's {0}")]
+    PollQuestionLength(usize),
+
+    /// Poll has less than 2 or more than 10 options.
+    #[error("Poll must have 2-10 options, but it has {0}")]
+    PollOptionsCount(usize),
+
+    /// Poll option is empty or longer than 100 characters.
+    #[error("Poll option must be 1-100 characters long, but it's {0}")]
+    PollOptionLength(usize),
+
+    /// Media group has less than 2 or more than 10 items.
+    #[error("Media group must have 2-10 items, but it has {0}")]
+    MediaGroupSize(usize),
+
+    /// More than 50 results were passed to an inline query answer.
+    #[error("At most 50 inline query results are allowed, but there are {0}")]
+    InlineQueryResultsCount(usize),
+
+    /// Text or caption can't be parsed according to its parse mode.
+    #[error("Can't parse entities: {0}")]
+    CantParseEntities(#[source] ParseError),
+
+    /// An uploaded file is larger than 50 MB (or 2000 MB for a local Bot API
+    /// server).
+    #[error("File must be at most {limit} bytes, but it's {size} bytes")]
+    FileSize {
+        /// Size of the file.
+        size: u64,
+
+        /// The maximum size of a file.
+        limit: u64,
+    },
 }
 
 /// An error caused by downloading a file.
@@ -59,6 +269,52 @@ pub enum DownloadError {
     /// An I/O error while writing a file to destination.
     #[error("An I/O error: {0}")]
     Io(#[from] std::io::Error),
+
+    /// The file is larger than the limit set by
+    /// [`DownloadOptions::max_size`].
+    ///
+    /// [`DownloadOptions::max_size`]: crate::net::DownloadOptions::max_size
+    #[error("The file is larger than the limit of {limit} bytes")]
+    TooLarge {
+        /// The limit which was exceeded.
+        limit: u64,
+    },
+
+    /// The size of the downloaded file is not the one set by
+    /// [`DownloadOptions::expected_size`].
+    ///
+    /// [`DownloadOptions::expected_size`]: crate::net::DownloadOptions::expected_size
+    #[error("Expected the file to be {expected} bytes, but downloaded {actual} bytes")]
+    SizeMismatch {
+        /// The expected size of the file.
+        expected: u64,
+
+        /// The actual size of the downloaded file.
+        actual: u64,
+    },
+}
+
+/// An error caused by downloading a file by its `file_id` via
+/// [`FileDownloader`].
+///
+/// `R` is the error of requests and `D` is the error of downloads.
+///
+/// [`FileDownloader`]: crate::net::FileDownloader
+#[derive(Debug, Error)]
+pub enum FileDownloadError<R, D> {
+    /// An error while resolving the `file_id` via [`GetFile`].
+    ///
+    /// [`GetFile`]: crate::payloads::GetFile
+    #[error("Can't get the file: {0}")]
+    GetFile(#[source] R),
+
+    /// An error while downloading the file.
+    #[error("Can't download the file: {0}")]
+    Download(#[source] D),
+
+    /// An I/O error while reading or writing the cache.
+    #[error("An I/O error of the cache: {0}")]
+    Cache(#[from] io::Error),
 }
 
 pub trait AsResponseParameters {
@@ -87,21 +343,29 @@ impl AsResponseParameters for crate::RequestError {
             _ => None,
         }
     }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match *self {
+            Self::RetryAfter(n) => Some(n),
+            Self::Api(ApiErrorResponse {
+                kind: ApiError::TooManyRequests { retry_after },
+                ..
+            }) => Some(retry_after),
+            _ => None,
+        }
+    }
 }
 
 /// A kind of an API error.
-#[derive(Debug, Error, Deserialize, PartialEq, Hash, Eq, Clone)]
-#[serde(field_identifier)]
+#[derive(Debug, Error, PartialEq, Hash, Eq, Clone)]
 #[non_exhaustive]
 pub enum ApiError {
     /// Occurs when the bot tries to send message to user who blocked the bot.
-    #[serde(rename = "Forbidden: bot was blocked by the user")]
     #[error("Forbidden: bot was blocked by the user")]
     BotBlocked,
 
     /// Occurs when the bot token is incorrect.
-    #[serde(rename = "Forbidden: bot was blocked by the user")]
-    #[error("Forbidden: bot was blocked by the user")]
+    #[error("Not Found")]
     NotFound,
 
     /// Occurs when bot tries to modify a message without modification content.
@@ -110,10 +374,6 @@ pub enum ApiError {
     /// 1. [`EditMessageText`]
     ///
     /// [`EditMessageText`]: crate::payloads::EditMessageText
-    #[serde(
-        rename = "Bad Request: message is not modified: specified new message content and reply \
-                  markup are exactly the same as a current content and reply markup of the message"
-    )]
     #[error(
         "Bad Request: message is not modified: specified new message content and reply markup are \
          exactly the same as a current content and reply markup of the message"
@@ -128,7 +388,6 @@ pub enum ApiError {
     ///
     /// [`ForwardMessage`]: crate::payloads::ForwardMessage
     /// [`DeleteMessage`]: crate::payloads::DeleteMessage
-    #[serde(rename = "Bad Request: MESSAGE_ID_INVALID")]
     #[error("Bad Request: MESSAGE_ID_INVALID")]
     MessageIdInvalid,
 
@@ -138,7 +397,6 @@ pub enum ApiError {
     /// 1. [`ForwardMessage`]
     ///
     /// [`ForwardMessage`]: crate::payloads::ForwardMessage
-    #[serde(rename = "Bad Request: message to forward not found")]
     #[error("Bad Request: message to forward not found")]
     MessageToForwardNotFound,
 
@@ -148,7 +406,6 @@ pub enum ApiError {
     /// 1. [`DeleteMessage`]
     ///
     /// [`DeleteMessage`]: crate::payloads::DeleteMessage
-    #[serde(rename = "Bad Request: message to delete not found")]
     #[error("Bad Request: message to delete not found")]
     MessageToDeleteNotFound,
 
@@ -158,7 +415,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Bad Request: message text is empty")]
     #[error("Bad Request: message text is empty")]
     MessageTextIsEmpty,
 
@@ -168,7 +424,6 @@ pub enum ApiError {
     /// 1. [`EditMessageText`]
     ///
     /// [`EditMessageText`]: crate::payloads::EditMessageText
-    #[serde(rename = "Bad Request: message can't be edited")]
     #[error("Bad Request: message can't be edited")]
     MessageCantBeEdited,
 
@@ -179,7 +434,6 @@ pub enum ApiError {
     /// 1. [`DeleteMessage`]
     ///
     /// [`DeleteMessage`]: crate::payloads::DeleteMessage
-    #[serde(rename = "Bad Request: message can't be deleted")]
     #[error("Bad Request: message can't be deleted")]
     MessageCantBeDeleted,
 
@@ -189,7 +443,6 @@ pub enum ApiError {
     /// 1. [`EditMessageText`]
     ///
     /// [`EditMessageText`]: crate::payloads::EditMessageText
-    #[serde(rename = "Bad Request: message to edit not found")]
     #[error("Bad Request: message to edit not found")]
     MessageToEditNotFound,
 
@@ -199,12 +452,10 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Bad Request: reply message not found")]
     #[error("Bad Request: reply message not found")]
     MessageToReplyNotFound,
 
     /// Occurs when bot tries to
-    #[serde(rename = "Bad Request: message identifier is not specified")]
     #[error("Bad Request: message identifier is not specified")]
     MessageIdentifierNotSpecified,
 
@@ -215,7 +466,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Bad Request: message is too long")]
     #[error("Bad Request: message is too long")]
     MessageIsTooLong,
 
@@ -232,7 +482,6 @@ pub enum ApiError {
     /// [`EditMessageTextInline`]: crate::payloads::EditMessageTextInline
     /// [`EditMessageCaption`]: crate::payloads::EditMessageCaption
     /// [`EditMessageCaptionInline`]: crate::payloads::EditMessageCaptionInline
-    #[serde(rename = "Bad Request: MESSAGE_TOO_LONG")]
     #[error("Bad Request: MESSAGE_TOO_LONG")]
     EditedMessageIsTooLong,
 
@@ -242,7 +491,6 @@ pub enum ApiError {
     /// 1. [`SendMediaGroup`]
     ///
     /// [`SendMediaGroup`]: crate::payloads::SendMediaGroup
-    #[serde(rename = "Bad Request: Too much messages to send as an album")]
     #[error("Bad Request: Too much messages to send as an album")]
     ToMuchMessages,
 
@@ -255,7 +503,6 @@ pub enum ApiError {
     /// 1. [`AnswerInlineQuery`]
     ///
     /// [`AnswerInlineQuery`]: crate::payloads::AnswerInlineQuery
-    #[serde(rename = "Bad Request: RESULTS_TOO_MUCH")]
     #[error("Bad Request: RESULTS_TOO_MUCH")]
     TooMuchInlineQueryResults,
 
@@ -265,7 +512,6 @@ pub enum ApiError {
     /// 1. [`SendPoll`]
     ///
     /// [`SendPoll`]: crate::payloads::SendPoll
-    #[serde(rename = "Bad Request: poll has already been closed")]
     #[error("Bad Request: poll has already been closed")]
     PollHasAlreadyClosed,
 
@@ -275,7 +521,6 @@ pub enum ApiError {
     /// 1. [`SendPoll`]
     ///
     /// [`SendPoll`]: crate::payloads::SendPoll
-    #[serde(rename = "Bad Request: poll must have at least 2 option")]
     #[error("Bad Request: poll must have at least 2 option")]
     PollMustHaveMoreOptions,
 
@@ -285,7 +530,6 @@ pub enum ApiError {
     /// 1. [`SendPoll`]
     ///
     /// [`SendPoll`]: crate::payloads::SendPoll
-    #[serde(rename = "Bad Request: poll can't have more than 10 options")]
     #[error("Bad Request: poll can't have more than 10 options")]
     PollCantHaveMoreOptions,
 
@@ -295,7 +539,6 @@ pub enum ApiError {
     /// 1. [`SendPoll`]
     ///
     /// [`SendPoll`]: crate::payloads::SendPoll
-    #[serde(rename = "Bad Request: poll options must be non-empty")]
     #[error("Bad Request: poll options must be non-empty")]
     PollOptionsMustBeNonEmpty,
 
@@ -305,31 +548,34 @@ pub enum ApiError {
     /// 1. [`SendPoll`]
     ///
     /// [`SendPoll`]: crate::payloads::SendPoll
-    #[serde(rename = "Bad Request: poll question must be non-empty")]
     #[error("Bad Request: poll question must be non-empty")]
     PollQuestionMustBeNonEmpty,
 
     /// Occurs when bot tries to send poll with total size of options more than
-    /// 100 symbols.
+    /// `limit` symbols.
     ///
     /// May happen in methods:
     /// 1. [`SendPoll`]
     ///
     /// [`SendPoll`]: crate::payloads::SendPoll
-    #[serde(rename = "Bad Request: poll options length must not exceed 100")]
-    #[error("Bad Request: poll options length must not exceed 100")]
-    PollOptionsLengthTooLong,
+    #[error("Bad Request: poll options length must not exceed {limit}")]
+    PollOptionsLengthTooLong {
+        /// The maximum total length of the options.
+        limit: u32,
+    },
 
-    /// Occurs when bot tries to send poll with question size more than 255
-    /// symbols.
+    /// Occurs when bot tries to send poll with question size more than
+    /// `limit` symbols.
     ///
     /// May happen in methods:
     /// 1. [`SendPoll`]
     ///
     /// [`SendPoll`]: crate::payloads::SendPoll
-    #[serde(rename = "Bad Request: poll question length must not exceed 255")]
-    #[error("Bad Request: poll question length must not exceed 255")]
-    PollQuestionLengthTooLong,
+    #[error("Bad Request: poll question length must not exceed {limit}")]
+    PollQuestionLengthTooLong {
+        /// The maximum length of the question.
+        limit: u32,
+    },
 
     /// Occurs when bot tries to stop poll with message without poll.
     ///
@@ -337,7 +583,6 @@ pub enum ApiError {
     /// 1. [`StopPoll`]
     ///
     /// [`StopPoll`]: crate::payloads::StopPoll
-    #[serde(rename = "Bad Request: message with poll to stop not found")]
     #[error("Bad Request: message with poll to stop not found")]
     MessageWithPollNotFound,
 
@@ -347,7 +592,6 @@ pub enum ApiError {
     /// 1. [`StopPoll`]
     ///
     /// [`StopPoll`]: crate::payloads::StopPoll
-    #[serde(rename = "Bad Request: message is not a poll")]
     #[error("Bad Request: message is not a poll")]
     MessageIsNotAPoll,
 
@@ -358,7 +602,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Bad Request: chat not found")]
     #[error("Bad Request: chat not found")]
     ChatNotFound,
 
@@ -369,7 +612,6 @@ pub enum ApiError {
     ///
     /// [`getUserProfilePhotos`]:
     /// crate::payloads::GetUserProfilePhotos
-    #[serde(rename = "Bad Request: user not found")]
     #[error("Bad Request: user not found")]
     UserNotFound,
 
@@ -380,7 +622,6 @@ pub enum ApiError {
     /// 1. [`SetChatDescription`]
     ///
     /// [`SetChatDescription`]: crate::payloads::SetChatDescription
-    #[serde(rename = "Bad Request: chat description is not modified")]
     #[error("Bad Request: chat description is not modified")]
     ChatDescriptionIsNotModified,
 
@@ -390,10 +631,6 @@ pub enum ApiError {
     /// 1. [`AnswerCallbackQuery`]
     ///
     /// [`AnswerCallbackQuery`]: crate::payloads::AnswerCallbackQuery
-    #[serde(
-        rename = "Bad Request: query is too old and response timeout expired or query id is \
-                  invalid"
-    )]
     #[error("Bad Request: query is too old and response timeout expired or query id is invalid")]
     InvalidQueryId,
 
@@ -404,7 +641,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Bad Request: BUTTON_URL_INVALID")]
     #[error("Bad Request: BUTTON_URL_INVALID")]
     ButtonUrlInvalid,
 
@@ -414,7 +650,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Bad Request: BUTTON_DATA_INVALID")]
     #[error("Bad Request: BUTTON_DATA_INVALID")]
     ButtonDataInvalid,
 
@@ -424,10 +659,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(
-        rename = "Bad Request: can't parse inline keyboard button: Text buttons are unallowed in \
-                  the inline keyboard"
-    )]
     #[error(
         "Bad Request: can't parse inline keyboard button: Text buttons are unallowed in the \
          inline keyboard"
@@ -440,24 +671,20 @@ pub enum ApiError {
     /// 1. [`GetFile`]
     ///
     /// [`GetFile`]: crate::payloads::GetFile
-    #[serde(rename = "Bad Request: wrong file id")]
     #[error("Bad Request: wrong file id")]
     WrongFileId,
 
     /// Occurs when bot tries to send files with wrong file identifier or HTTP
     /// url
-    #[serde(rename = "Bad Request: wrong file identifier/HTTP URL specified")]
     #[error("Bad Request: wrong file identifier/HTTP URL specified")]
     WrongFileIdOrUrl,
 
     /// Occurs when When sending files with an url to a site that doesn't
     /// respond.
-    #[serde(rename = "Bad Request: failed to get HTTP URL content")]
     #[error("Bad Request: failed to get HTTP URL content")]
     FailedToGetUrlContent,
 
     /// Occurs when bot tries to do some with group which was deactivated.
-    #[serde(rename = "Bad Request: group is deactivated")]
     #[error("Bad Request: group is deactivated")]
     GroupDeactivated,
 
@@ -467,7 +694,6 @@ pub enum ApiError {
     /// 1. [`SetChatPhoto`]
     ///
     /// [`SetChatPhoto`]: crate::payloads::SetChatPhoto
-    #[serde(rename = "Bad Request: Photo should be uploaded as an InputFile")]
     #[error("Bad Request: Photo should be uploaded as an InputFile")]
     PhotoAsInputFileRequired,
 
@@ -477,7 +703,6 @@ pub enum ApiError {
     /// 1. [`AddStickerToSet`]
     ///
     /// [`AddStickerToSet`]: crate::payloads::AddStickerToSet
-    #[serde(rename = "Bad Request: STICKERSET_INVALID")]
     #[error("Bad Request: STICKERSET_INVALID")]
     InvalidStickersSet,
 
@@ -488,7 +713,6 @@ pub enum ApiError {
     /// 1. [`CreateNewStickerSet`]
     ///
     /// [`CreateNewStickerSet`]: crate::payloads::CreateNewStickerSet
-    #[serde(rename = "Bad Request: sticker set name is already occupied")]
     #[error("Bad Request: sticker set name is already occupied")]
     StickerSetNameOccupied,
 
@@ -498,7 +722,6 @@ pub enum ApiError {
     /// 1. [`CreateNewStickerSet`]
     ///
     /// [`CreateNewStickerSet`]: crate::payloads::CreateNewStickerSet
-    #[serde(rename = "Bad Request: USER_IS_BOT")]
     #[error("Bad Request: USER_IS_BOT")]
     StickerSetOwnerIsBot,
 
@@ -515,7 +738,6 @@ pub enum ApiError {
     /// 1. [`CreateNewStickerSet`]
     ///
     /// [`CreateNewStickerSet`]: crate::payloads::CreateNewStickerSet
-    #[serde(rename = "Bad Request: invalid sticker set name is specified")]
     #[error("Bad Request: invalid sticker set name is specified")]
     InvalidStickerName,
 
@@ -526,7 +748,6 @@ pub enum ApiError {
     /// 1. [`PinChatMessage`]
     ///
     /// [`PinChatMessage`]: crate::payloads::PinChatMessage
-    #[serde(rename = "Bad Request: not enough rights to pin a message")]
     #[error("Bad Request: not enough rights to pin a message")]
     NotEnoughRightsToPinMessage,
 
@@ -539,7 +760,6 @@ pub enum ApiError {
     ///
     /// [`PinChatMessage`]: crate::payloads::PinChatMessage
     /// [`UnpinChatMessage`]: crate::payloads::UnpinChatMessage
-    #[serde(rename = "Bad Request: not enough rights to manage pinned messages in the chat")]
     #[error("Bad Request: not enough rights to manage pinned messages in the chat")]
     NotEnoughRightsToManagePins,
 
@@ -550,13 +770,11 @@ pub enum ApiError {
     /// 1. [`SetChatPermissions`]
     ///
     /// [`SetChatPermissions`]: crate::payloads::SetChatPermissions
-    #[serde(rename = "Bad Request: not enough rights to change chat permissions")]
     #[error("Bad Request: not enough rights to change chat permissions")]
     NotEnoughRightsToChangeChatPermissions,
 
     /// Occurs when bot tries to use method in group which is allowed only in a
     /// supergroup or channel.
-    #[serde(rename = "Bad Request: method is available only for supergroups and channel")]
     #[error("Bad Request: method is available only for supergroups and channel")]
     MethodNotAvailableInPrivateChats,
 
@@ -566,7 +784,6 @@ pub enum ApiError {
     /// 1. [`PromoteChatMember`]
     ///
     /// [`PromoteChatMember`]: crate::payloads::PromoteChatMember
-    #[serde(rename = "Bad Request: can't demote chat creator")]
     #[error("Bad Request: can't demote chat creator")]
     CantDemoteChatCreator,
 
@@ -576,7 +793,6 @@ pub enum ApiError {
     /// 1. [`RestrictChatMember`]
     ///
     /// [`RestrictChatMember`]: crate::payloads::RestrictChatMember
-    #[serde(rename = "Bad Request: can't restrict self")]
     #[error("Bad Request: can't restrict self")]
     CantRestrictSelf,
 
@@ -587,13 +803,11 @@ pub enum ApiError {
     /// 1. [`RestrictChatMember`]
     ///
     /// [`RestrictChatMember`]: crate::payloads::RestrictChatMember
-    #[serde(rename = "Bad Request: not enough rights to restrict/unrestrict chat member")]
     #[error("Bad Request: not enough rights to restrict/unrestrict chat member")]
     NotEnoughRightsToRestrict,
 
     /// Occurs when bot tries to post a message in a channel without "Post
     /// Messages" admin right.
-    #[serde(rename = "Bad Request: need administrator rights in the channel chat")]
     #[error("Bad Request: need administrator rights in the channel chat")]
     NotEnoughRightsToPostMessages,
 
@@ -603,7 +817,6 @@ pub enum ApiError {
     /// 1. [`SetWebhook`]
     ///
     /// [`SetWebhook`]: crate::payloads::SetWebhook
-    #[serde(rename = "Bad Request: bad webhook: HTTPS url must be provided for webhook")]
     #[error("Bad Request: bad webhook: HTTPS url must be provided for webhook")]
     WebhookRequireHttps,
 
@@ -614,10 +827,6 @@ pub enum ApiError {
     /// 1. [`SetWebhook`]
     ///
     /// [`SetWebhook`]: crate::payloads::SetWebhook
-    #[serde(
-        rename = "Bad Request: bad webhook: Webhook can be set up only on ports 80, 88, 443 or \
-                  8443"
-    )]
     #[error("Bad Request: bad webhook: Webhook can be set up only on ports 80, 88, 443 or 8443")]
     BadWebhookPort,
 
@@ -627,9 +836,6 @@ pub enum ApiError {
     /// 1. [`SetWebhook`]
     ///
     /// [`SetWebhook`]: crate::payloads::SetWebhook
-    #[serde(
-        rename = "Bad Request: bad webhook: Failed to resolve host: Name or service not known"
-    )]
     #[error("Bad Request: bad webhook: Failed to resolve host: Name or service not known")]
     UnknownHost,
 
@@ -639,7 +845,6 @@ pub enum ApiError {
     /// 1. [`SetWebhook`]
     ///
     /// [`SetWebhook`]: crate::payloads::SetWebhook
-    #[serde(rename = "Bad Request: can't parse URL")]
     #[error("Bad Request: can't parse URL")]
     CantParseUrl,
 
@@ -649,9 +854,16 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Bad Request: can't parse entities")]
-    #[error("Bad Request: can't parse entities")]
-    CantParseEntities,
+    #[error("Bad Request: can't parse entities: {reason}")]
+    CantParseEntities {
+        /// Explanation of what's wrong with the entities, as sent by
+        /// Telegram.
+        reason: String,
+
+        /// Offset (in bytes) in the text at which the error was found, if
+        /// Telegram has reported it.
+        byte_offset: Option<usize>,
+    },
 
     /// Occurs when bot tries to use getUpdates while webhook is active.
     ///
@@ -659,7 +871,6 @@ pub enum ApiError {
     /// 1. [`GetUpdates`]
     ///
     /// [`GetUpdates`]: crate::payloads::GetUpdates
-    #[serde(rename = "can't use getUpdates method while webhook is active")]
     #[error("can't use getUpdates method while webhook is active")]
     CantGetUpdates,
 
@@ -669,7 +880,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Unauthorized: bot was kicked from a chat")]
     #[error("Unauthorized: bot was kicked from a chat")]
     BotKicked,
 
@@ -680,7 +890,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Forbidden: bot was kicked from the supergroup chat")]
     #[error("Forbidden: bot was kicked from the supergroup chat")]
     BotKickedFromSupergroup,
 
@@ -690,7 +899,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Unauthorized: user is deactivated")]
     #[error("Unauthorized: user is deactivated")]
     UserDeactivated,
 
@@ -700,7 +908,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Unauthorized: bot can't initiate conversation with a user")]
     #[error("Unauthorized: bot can't initiate conversation with a user")]
     CantInitiateConversation,
 
@@ -710,7 +917,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Unauthorized: bot can't send messages to bots")]
     #[error("Unauthorized: bot can't send messages to bots")]
     CantTalkWithBots,
 
@@ -720,7 +926,6 @@ pub enum ApiError {
     /// 1. [`SendMessage`]
     ///
     /// [`SendMessage`]: crate::payloads::SendMessage
-    #[serde(rename = "Bad Request: wrong HTTP URL")]
     #[error("Bad Request: wrong HTTP URL")]
     WrongHttpUrl,
 
@@ -731,10 +936,6 @@ pub enum ApiError {
     /// 1. [`GetUpdates`]
     ///
     /// [`GetUpdates`]: crate::payloads::GetUpdates
-    #[serde(
-        rename = "Conflict: terminated by other getUpdates request; make sure that only one bot \
-                  instance is running"
-    )]
     #[error(
         "Conflict: terminated by other getUpdates request; make sure that only one bot instance \
          is running"
@@ -747,10 +948,18 @@ pub enum ApiError {
     /// 1. [`GetFile`]
     ///
     /// [`GetFile`]: crate::payloads::GetFile
-    #[serde(rename = "Bad Request: invalid file id")]
     #[error("Bad Request: invalid file id")]
     FileIdInvalid,
 
+    /// Occurs when the bot exceeds flood control and the response doesn't
+    /// have the `retry_after` parameter (otherwise
+    /// [`RequestError::RetryAfter`] is returned).
+    #[error("Too Many Requests: retry after {}", .retry_after.as_secs())]
+    TooManyRequests {
+        /// How long to wait before the request can be repeated.
+        retry_after: Duration,
+    },
+
     /// Error which is not known to `teloxide`.
     ///
     /// If you've received this error, please [open an issue] with the
@@ -761,6 +970,167 @@ pub enum ApiError {
     Unknown(String),
 }
 
+impl<'de> Deserialize<'de> for ApiError {
+    /// Parses the description of an error, unknown descriptions are
+    /// deserialized as [`ApiError::Unknown`].
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let description = <std::borrow::Cow<'_, str>>::deserialize(deserializer)?;
+        Ok(parse::parse(&description))
+    }
+}
+
+impl ApiError {
+    /// Returns the name of this variant, e.g. `"BotBlocked"` or `"Unknown"`.
+    ///
+    /// Unlike the description of the error, the name doesn't contain any
+    /// details (such as the text of [`ApiError::Unknown`]).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::BotBlocked => "BotBlocked",
+            Self::NotFound => "NotFound",
+            Self::MessageNotModified => "MessageNotModified",
+            Self::MessageIdInvalid => "MessageIdInvalid",
+            Self::MessageToForwardNotFound => "MessageToForwardNotFound",
+            Self::MessageToDeleteNotFound => "MessageToDeleteNotFound",
+            Self::MessageTextIsEmpty => "MessageTextIsEmpty",
+            Self::MessageCantBeEdited => "MessageCantBeEdited",
+            Self::MessageCantBeDeleted => "MessageCantBeDeleted",
+            Self::MessageToEditNotFound => "MessageToEditNotFound",
+            Self::MessageToReplyNotFound => "MessageToReplyNotFound",
+            Self::MessageIdentifierNotSpecified => "MessageIdentifierNotSpecified",
+            Self::MessageIsTooLong => "MessageIsTooLong",
+            Self::EditedMessageIsTooLong => "EditedMessageIsTooLong",
+            Self::ToMuchMessages => "ToMuchMessages",
+            Self::TooMuchInlineQueryResults => "TooMuchInlineQueryResults",
+            Self::PollHasAlreadyClosed => "PollHasAlreadyClosed",
+            Self::PollMustHaveMoreOptions => "PollMustHaveMoreOptions",
+            Self::PollCantHaveMoreOptions => "PollCantHaveMoreOptions",
+            Self::PollOptionsMustBeNonEmpty => "PollOptionsMustBeNonEmpty",
+            Self::PollQuestionMustBeNonEmpty => "PollQuestionMustBeNonEmpty",
+            Self::PollOptionsLengthTooLong { .. } => "PollOptionsLengthTooLong",
+            Self::PollQuestionLengthTooLong { .. } => "PollQuestionLengthTooLong",
+            Self::MessageWithPollNotFound => "MessageWithPollNotFound",
+            Self::MessageIsNotAPoll => "MessageIsNotAPoll",
+            Self::ChatNotFound => "ChatNotFound",
+            Self::UserNotFound => "UserNotFound",
+            Self::ChatDescriptionIsNotModified => "ChatDescriptionIsNotModified",
+            Self::InvalidQueryId => "InvalidQueryId",
+            Self::ButtonUrlInvalid => "ButtonUrlInvalid",
+            Self::ButtonDataInvalid => "ButtonDataInvalid",
+            Self::TextButtonsAreUnallowed => "TextButtonsAreUnallowed",
+            Self::WrongFileId => "WrongFileId",
+            Self::WrongFileIdOrUrl => "WrongFileIdOrUrl",
+            Self::FailedToGetUrlContent => "FailedToGetUrlContent",
+            Self::GroupDeactivated => "GroupDeactivated",
+            Self::PhotoAsInputFileRequired => "PhotoAsInputFileRequired",
+            Self::InvalidStickersSet => "InvalidStickersSet",
+            Self::StickerSetNameOccupied => "StickerSetNameOccupied",
+            Self::StickerSetOwnerIsBot => "StickerSetOwnerIsBot",
+            Self::InvalidStickerName => "InvalidStickerName",
+            Self::NotEnoughRightsToPinMessage => "NotEnoughRightsToPinMessage",
+            Self::NotEnoughRightsToManagePins => "NotEnoughRightsToManagePins",
+            Self::NotEnoughRightsToChangeChatPermissions => {
+                "NotEnoughRightsToChangeChatPermissions"
+            }
+            Self::MethodNotAvailableInPrivateChats => "MethodNotAvailableInPrivateChats",
+            Self::CantDemoteChatCreator => "CantDemoteChatCreator",
+            Self::CantRestrictSelf => "CantRestrictSelf",
+            Self::NotEnoughRightsToRestrict => "NotEnoughRightsToRestrict",
+            Self::NotEnoughRightsToPostMessages => "NotEnoughRightsToPostMessages",
+            Self::WebhookRequireHttps => "WebhookRequireHttps",
+            Self::BadWebhookPort => "BadWebhookPort",
+            Self::UnknownHost => "UnknownHost",
+            Self::CantParseUrl => "CantParseUrl",
+            Self::CantParseEntities { .. } => "CantParseEntities",
+            Self::CantGetUpdates => "CantGetUpdates",
+            Self::BotKicked => "BotKicked",
+            Self::BotKickedFromSupergroup => "BotKickedFromSupergroup",
+            Self::UserDeactivated => "UserDeactivated",
+            Self::CantInitiateConversation => "CantInitiateConversation",
+            Self::CantTalkWithBots => "CantTalkWithBots",
+            Self::WrongHttpUrl => "WrongHttpUrl",
+            Self::TerminatedByOtherGetUpdates => "TerminatedByOtherGetUpdates",
+            Self::FileIdInvalid => "FileIdInvalid",
+            Self::TooManyRequests { .. } => "TooManyRequests",
+            Self::Unknown(_) => "Unknown",
+        }
+    }
+
+    /// Returns `true` if the bot can't send messages to the chat anymore, i.e.
+    /// the bot was blocked or kicked, the user was deactivated, the chat
+    /// doesn't exist or was deactivated, or the bot isn't allowed to start a
+    /// conversation with the user.
+    pub fn is_chat_unreachable(&self) -> bool {
+        matches!(
+            self,
+            Self::BotBlocked
+                | Self::BotKicked
+                | Self::BotKickedFromSupergroup
+                | Self::UserDeactivated
+                | Self::ChatNotFound
+                | Self::GroupDeactivated
+                | Self::CantInitiateConversation
+                | Self::CantTalkWithBots
+        )
+    }
+
+    /// Returns `true` if the bot doesn't have enough rights in the chat to
+    /// perform the action.
+    pub fn is_permission_error(&self) -> bool {
+        matches!(
+            self,
+            Self::NotEnoughRightsToPinMessage
+                | Self::NotEnoughRightsToManagePins
+                | Self::NotEnoughRightsToChangeChatPermissions
+                | Self::NotEnoughRightsToRestrict
+                | Self::NotEnoughRightsToPostMessages
+        )
+    }
+
+    /// Returns `true` if the error is caused by invalid contents of the request
+    /// (e.g. a too long text, an invalid file id or broken markup), so sending
+    /// the same request again won't help.
+    pub fn is_user_input_error(&self) -> bool {
+        matches!(
+            self,
+            Self::MessageIdInvalid
+                | Self::MessageTextIsEmpty
+                | Self::MessageIdentifierNotSpecified
+                | Self::MessageIsTooLong
+                | Self::EditedMessageIsTooLong
+                | Self::ToMuchMessages
+                | Self::TooMuchInlineQueryResults
+                | Self::PollMustHaveMoreOptions
+                | Self::PollCantHaveMoreOptions
+                | Self::PollOptionsMustBeNonEmpty
+                | Self::PollQuestionMustBeNonEmpty
+                | Self::PollOptionsLengthTooLong { .. }
+                | Self::PollQuestionLengthTooLong { .. }
+                | Self::ButtonUrlInvalid
+                | Self::ButtonDataInvalid
+                | Self::TextButtonsAreUnallowed
+                | Self::WrongFileId
+                | Self::WrongFileIdOrUrl
+                | Self::FailedToGetUrlContent
+                | Self::PhotoAsInputFileRequired
+                | Self::InvalidStickersSet
+                | Self::StickerSetNameOccupied
+                | Self::StickerSetOwnerIsBot
+                | Self::InvalidStickerName
+                | Self::WebhookRequireHttps
+                | Self::BadWebhookPort
+                | Self::UnknownHost
+                | Self::CantParseUrl
+                | Self::CantParseEntities { .. }
+                | Self::WrongHttpUrl
+                | Self::FileIdInvalid
+        )
+    }
+}
+
 impl From<reqwest::Error> for DownloadError {
     fn from(error: reqwest::Error) -> Self {
         DownloadError::Network(hide_token(error))
@@ -773,6 +1143,12 @@ impl From<reqwest::Error> for RequestError {
     }
 }
 
+impl From<ValidationError> for RequestError {
+    fn from(error: ValidationError) -> Self {
+        RequestError::Validation(error)
+    }
+}
+
 /// Replaces token in the url in the error with `token:redacted` string.
 pub(crate) fn hide_token(mut error: reqwest::Error) -> reqwest::Error {
     let url = match error.url_mut() {
@@ -822,3 +1198,41 @@ pub(crate) fn hide_token(mut error: reqwest::Error) -> reqwest::Error {
     // couldn't find token in the url, hide the whole url
     error.without_url()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        errors::{ApiErrorResponse, ValidationError},
+        ApiError, RequestError,
+    };
+
+    #[test]
+    fn classification() {
+        let api = |kind: ApiError| RequestError::Api(kind.into());
+
+        assert!(RequestError::RetryAfter(Duration::from_secs(1)).is_retryable());
+        assert!(RequestError::Api(ApiErrorResponse::new(502, "Bad Gateway")).is_retryable());
+        assert!(!api(ApiError::BotBlocked).is_retryable());
+
+        assert!(api(ApiError::BotBlocked).is_chat_unreachable());
+        assert!(api(ApiError::GroupDeactivated).is_chat_unreachable());
+        assert!(
+            RequestError::Api(ApiErrorResponse::new(403, "Forbidden: something new"))
+                .is_chat_unreachable()
+        );
+        assert!(!RequestError::MigrateToChatId(1).is_chat_unreachable());
+
+        assert!(api(ApiError::NotEnoughRightsToPinMessage).is_permission_error());
+        assert!(!api(ApiError::ChatNotFound).is_permission_error());
+
+        assert!(api(ApiError::CantParseEntities {
+            reason: "Unexpected end tag at byte offset 3".to_owned(),
+            byte_offset: Some(3),
+        })
+        .is_user_input_error());
+        assert!(RequestError::Validation(ValidationError::TextLength(5000)).is_user_input_error());
+        assert!(!api(ApiError::BotBlocked).is_user_input_error());
+    }
+}
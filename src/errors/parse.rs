@@ -0,0 +1,546 @@
+//! Parsing of error descriptions sent by Telegram into [`ApiError`]s.
+
+use std::time::Duration;
+
+use crate::ApiError;
+
+/// Names of HTTP statuses which Telegram puts in front of descriptions, e.g.
+/// `Bad Request: chat not found`.
+///
+/// Telegram isn't consistent about them (e.g. the same error may be reported
+/// as `Unauthorized` or `Forbidden`), so they are ignored while parsing.
+const STATUSES: &[&str] = &[
+    "Bad Request",
+    "Unauthorized",
+    "Forbidden",
+    "Not Found",
+    "Conflict",
+    "Too Many Requests",
+];
+
+/// Known descriptions (without the status) of errors which don't have any
+/// parameters.
+///
+/// A description matches an entry if it starts with it (ignoring ASCII case)
+/// and the rest is either empty or separated by `:` or `;` (Telegram likes
+/// to add details to descriptions, e.g. `message is not modified: specified
+/// new message content [...]`).
+const KNOWN: &[(&str, ApiError)] = &[
+    ("bot was blocked by the user", ApiError::BotBlocked),
+    ("message is not modified", ApiError::MessageNotModified),
+    ("MESSAGE_ID_INVALID", ApiError::MessageIdInvalid),
+    (
+        "message to forward not found",
+        ApiError::MessageToForwardNotFound,
+    ),
+    (
+        "message to delete not found",
+        ApiError::MessageToDeleteNotFound,
+    ),
+    ("message text is empty", ApiError::MessageTextIsEmpty),
+    ("message can't be edited", ApiError::MessageCantBeEdited),
+    ("message can't be deleted", ApiError::MessageCantBeDeleted),
+    ("message to edit not found", ApiError::MessageToEditNotFound),
+    ("reply message not found", ApiError::MessageToReplyNotFound),
+    (
+        "message identifier is not specified",
+        ApiError::MessageIdentifierNotSpecified,
+    ),
+    ("message is too long", ApiError::MessageIsTooLong),
+    ("MESSAGE_TOO_LONG", ApiError::EditedMessageIsTooLong),
+    (
+        "Too much messages to send as an album",
+        ApiError::ToMuchMessages,
+    ),
+    ("RESULTS_TOO_MUCH", ApiError::TooMuchInlineQueryResults),
+    (
+        "poll has already been closed",
+        ApiError::PollHasAlreadyClosed,
+    ),
+    (
+        "poll must have at least 2 option",
+        ApiError::PollMustHaveMoreOptions,
+    ),
+    (
+        "poll can't have more than 10 options",
+        ApiError::PollCantHaveMoreOptions,
+    ),
+    (
+        "poll options must be non-empty",
+        ApiError::PollOptionsMustBeNonEmpty,
+    ),
+    (
+        "poll question must be non-empty",
+        ApiError::PollQuestionMustBeNonEmpty,
+    ),
+    (
+        "message with poll to stop not found",
+        ApiError::MessageWithPollNotFound,
+    ),
+    ("message is not a poll", ApiError::MessageIsNotAPoll),
+    ("chat not found", ApiError::ChatNotFound),
+    ("user not found", ApiError::UserNotFound),
+    (
+        "chat description is not modified",
+        ApiError::ChatDescriptionIsNotModified,
+    ),
+    (
+        "query is too old and response timeout expired or query id is invalid",
+        ApiError::InvalidQueryId,
+    ),
+    ("BUTTON_URL_INVALID", ApiError::ButtonUrlInvalid),
+    ("BUTTON_DATA_INVALID", ApiError::ButtonDataInvalid),
+    (
+        "can't parse inline keyboard button: Text buttons are unallowed in the inline keyboard",
+        ApiError::TextButtonsAreUnallowed,
+    ),
+    ("wrong file id", ApiError::WrongFileId),
+    (
+        "wrong file identifier/HTTP URL specified",
+        ApiError::WrongFileIdOrUrl,
+    ),
+    (
+        "failed to get HTTP URL content",
+        ApiError::FailedToGetUrlContent,
+    ),
+    ("group is deactivated", ApiError::GroupDeactivated),
+    (
+        "Photo should be uploaded as an InputFile",
+        ApiError::PhotoAsInputFileRequired,
+    ),
+    ("STICKERSET_INVALID", ApiError::InvalidStickersSet),
+    (
+        "sticker set name is already occupied",
+        ApiError::StickerSetNameOccupied,
+    ),
+    ("USER_IS_BOT", ApiError::StickerSetOwnerIsBot),
+    (
+        "invalid sticker set name is specified",
+        ApiError::InvalidStickerName,
+    ),
+    (
+        "not enough rights to pin a message",
+        ApiError::NotEnoughRightsToPinMessage,
+    ),
+    (
+        "not enough rights to manage pinned messages in the chat",
+        ApiError::NotEnoughRightsToManagePins,
+    ),
+    (
+        "not enough rights to change chat permissions",
+        ApiError::NotEnoughRightsToChangeChatPermissions,
+    ),
+    (
+        "method is available only for supergroups and channel",
+        ApiError::MethodNotAvailableInPrivateChats,
+    ),
+    ("can't demote chat creator", ApiError::CantDemoteChatCreator),
+    ("can't restrict self", ApiError::CantRestrictSelf),
+    (
+        "not enough rights to restrict/unrestrict chat member",
+        ApiError::NotEnoughRightsToRestrict,
+    ),
+    (
+        "need administrator rights in the channel chat",
+        ApiError::NotEnoughRightsToPostMessages,
+    ),
+    (
+        "bad webhook: HTTPS url must be provided for webhook",
+        ApiError::WebhookRequireHttps,
+    ),
+    (
+        "bad webhook: Webhook can be set up only on ports 80, 88, 443 or 8443",
+        ApiError::BadWebhookPort,
+    ),
+    (
+        "bad webhook: Failed to resolve host: Name or service not known",
+        ApiError::UnknownHost,
+    ),
+    ("can't parse URL", ApiError::CantParseUrl),
+    (
+        "can't use getUpdates method while webhook is active",
+        ApiError::CantGetUpdates,
+    ),
+    ("bot was kicked from a chat", ApiError::BotKicked),
+    ("bot was kicked from the group chat", ApiError::BotKicked),
+    ("bot was kicked from the channel chat", ApiError::BotKicked),
+    (
+        "bot was kicked from the supergroup chat",
+        ApiError::BotKickedFromSupergroup,
+    ),
+    ("user is deactivated", ApiError::UserDeactivated),
+    (
+        "bot can't initiate conversation with a user",
+        ApiError::CantInitiateConversation,
+    ),
+    (
+        "bot can't send messages to bots",
+        ApiError::CantTalkWithBots,
+    ),
+    ("wrong HTTP URL", ApiError::WrongHttpUrl),
+    (
+        "terminated by other getUpdates request",
+        ApiError::TerminatedByOtherGetUpdates,
+    ),
+    ("invalid file id", ApiError::FileIdInvalid),
+];
+
+/// Parses a description of an error sent by Telegram.
+///
+/// Descriptions which aren't recognized are returned as [`ApiError::Unknown`].
+pub(crate) fn parse(description: &str) -> ApiError {
+    // The invalid token error is the only one which consists of just the status
+    if description == "Not Found" {
+        return ApiError::NotFound;
+    }
+
+    let message = strip_status(description);
+
+    if let Some(reason) = strip_prefix(message, "can't parse entities") {
+        let reason = reason.trim_start_matches(':').trim_start();
+        return ApiError::CantParseEntities {
+            reason: reason.to_owned(),
+            byte_offset: number_after(reason, "byte offset "),
+        };
+    }
+
+    if let Some(limit) = strip_prefix(message, "poll options length must not exceed ") {
+        if let Ok(limit) = limit.parse() {
+            return ApiError::PollOptionsLengthTooLong { limit };
+        }
+    }
+
+    if let Some(limit) = strip_prefix(message, "poll question length must not exceed ") {
+        if let Ok(limit) = limit.parse() {
+            return ApiError::PollQuestionLengthTooLong { limit };
+        }
+    }
+
+    if let Some(secs) = strip_prefix(message, "retry after ") {
+        if let Ok(secs) = secs.parse() {
+            return ApiError::TooManyRequests {
+                retry_after: Duration::from_secs(secs),
+            };
+        }
+    }
+
+    KNOWN
+        .iter()
+        .find(|(known, _)| {
+            matches!(
+                strip_prefix(message, known),
+                Some(rest) if rest.is_empty() || rest.starts_with(':') || rest.starts_with(';')
+            )
+        })
+        .map(|(_, kind)| kind.clone())
+        .unwrap_or_else(|| ApiError::Unknown(description.to_owned()))
+}
+
+/// Removes the `<status>: ` part from the description, if it's there.
+fn strip_status(description: &str) -> &str {
+    STATUSES
+        .iter()
+        .find_map(|status| description.strip_prefix(status)?.strip_prefix(": "))
+        .unwrap_or(description)
+}
+
+/// Like [`str::strip_prefix`], but ignores ASCII case.
+fn strip_prefix<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+
+    match head.eq_ignore_ascii_case(prefix) {
+        true => Some(&s[prefix.len()..]),
+        false => None,
+    }
+}
+
+/// Parses the number which immediately follows the first occurrence of
+/// `before` in `s`.
+fn number_after(s: &str, before: &str) -> Option<usize> {
+    let start = s.find(before)? + before.len();
+    let digits = s[start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(&s[start..], |end| &s[start..start + end]);
+
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::parse;
+    use crate::{errors::ApiErrorResponse, ApiError};
+
+    #[test]
+    fn real_descriptions() {
+        let entities = |reason: &str, byte_offset| ApiError::CantParseEntities {
+            reason: reason.to_owned(),
+            byte_offset,
+        };
+
+        let cases = [
+            (
+                "Forbidden: bot was blocked by the user",
+                ApiError::BotBlocked,
+            ),
+            ("Not Found", ApiError::NotFound),
+            (
+                "Bad Request: message is not modified: specified new message content and reply \
+                 markup are exactly the same as a current content and reply markup of the message",
+                ApiError::MessageNotModified,
+            ),
+            (
+                "Bad Request: message is not modified",
+                ApiError::MessageNotModified,
+            ),
+            (
+                "Bad Request: MESSAGE_ID_INVALID",
+                ApiError::MessageIdInvalid,
+            ),
+            (
+                "Bad Request: message to forward not found",
+                ApiError::MessageToForwardNotFound,
+            ),
+            (
+                "Bad Request: message to delete not found",
+                ApiError::MessageToDeleteNotFound,
+            ),
+            (
+                "Bad Request: message text is empty",
+                ApiError::MessageTextIsEmpty,
+            ),
+            (
+                "Bad Request: message can't be edited",
+                ApiError::MessageCantBeEdited,
+            ),
+            (
+                "Bad Request: message can't be deleted",
+                ApiError::MessageCantBeDeleted,
+            ),
+            (
+                "Bad Request: message to edit not found",
+                ApiError::MessageToEditNotFound,
+            ),
+            (
+                "Bad Request: reply message not found",
+                ApiError::MessageToReplyNotFound,
+            ),
+            (
+                "Bad Request: message is too long",
+                ApiError::MessageIsTooLong,
+            ),
+            (
+                "Bad Request: MESSAGE_TOO_LONG",
+                ApiError::EditedMessageIsTooLong,
+            ),
+            (
+                "Bad Request: RESULTS_TOO_MUCH",
+                ApiError::TooMuchInlineQueryResults,
+            ),
+            (
+                "Bad Request: poll has already been closed",
+                ApiError::PollHasAlreadyClosed,
+            ),
+            (
+                "Bad Request: poll options length must not exceed 100",
+                ApiError::PollOptionsLengthTooLong { limit: 100 },
+            ),
+            (
+                "Bad Request: poll question length must not exceed 255",
+                ApiError::PollQuestionLengthTooLong { limit: 255 },
+            ),
+            (
+                "Bad Request: poll question length must not exceed 300",
+                ApiError::PollQuestionLengthTooLong { limit: 300 },
+            ),
+            (
+                "Bad Request: message is not a poll",
+                ApiError::MessageIsNotAPoll,
+            ),
+            ("Bad Request: chat not found", ApiError::ChatNotFound),
+            ("Bad Request: user not found", ApiError::UserNotFound),
+            (
+                "Bad Request: query is too old and response timeout expired or query ID is invalid",
+                ApiError::InvalidQueryId,
+            ),
+            (
+                "Bad Request: BUTTON_URL_INVALID",
+                ApiError::ButtonUrlInvalid,
+            ),
+            (
+                "Bad Request: BUTTON_DATA_INVALID",
+                ApiError::ButtonDataInvalid,
+            ),
+            (
+                "Bad Request: can't parse inline keyboard button: Text buttons are unallowed in \
+                 the inline keyboard",
+                ApiError::TextButtonsAreUnallowed,
+            ),
+            ("Bad Request: wrong file id", ApiError::WrongFileId),
+            (
+                "Bad Request: wrong file identifier/HTTP URL specified",
+                ApiError::WrongFileIdOrUrl,
+            ),
+            (
+                "Bad Request: failed to get HTTP URL content",
+                ApiError::FailedToGetUrlContent,
+            ),
+            (
+                "Bad Request: group is deactivated",
+                ApiError::GroupDeactivated,
+            ),
+            (
+                "Bad Request: STICKERSET_INVALID",
+                ApiError::InvalidStickersSet,
+            ),
+            ("Bad Request: USER_IS_BOT", ApiError::StickerSetOwnerIsBot),
+            (
+                "Bad Request: not enough rights to pin a message",
+                ApiError::NotEnoughRightsToPinMessage,
+            ),
+            (
+                "Bad Request: not enough rights to restrict/unrestrict chat member",
+                ApiError::NotEnoughRightsToRestrict,
+            ),
+            (
+                "Bad Request: need administrator rights in the channel chat",
+                ApiError::NotEnoughRightsToPostMessages,
+            ),
+            (
+                "Bad Request: can't demote chat creator",
+                ApiError::CantDemoteChatCreator,
+            ),
+            (
+                "Bad Request: bad webhook: HTTPS url must be provided for webhook",
+                ApiError::WebhookRequireHttps,
+            ),
+            (
+                "Bad Request: bad webhook: Webhook can be set up only on ports 80, 88, 443 or 8443",
+                ApiError::BadWebhookPort,
+            ),
+            (
+                "Bad Request: bad webhook: Failed to resolve host: Name or service not known",
+                ApiError::UnknownHost,
+            ),
+            ("Bad Request: can't parse URL", ApiError::CantParseUrl),
+            (
+                "Bad Request: can't parse entities: Can't find end of the entity starting at byte \
+                 offset 45",
+                entities(
+                    "Can't find end of the entity starting at byte offset 45",
+                    Some(45),
+                ),
+            ),
+            (
+                "Bad Request: can't parse entities: Character '.' is reserved and must be escaped \
+                 with the preceding '\\'",
+                entities(
+                    "Character '.' is reserved and must be escaped with the preceding '\\'",
+                    None,
+                ),
+            ),
+            (
+                "Bad Request: can't parse entities: Unsupported start tag \"foo\" at byte offset 0",
+                entities("Unsupported start tag \"foo\" at byte offset 0", Some(0)),
+            ),
+            ("Bad Request: can't parse entities", entities("", None)),
+            (
+                "Conflict: can't use getUpdates method while webhook is active; use deleteWebhook \
+                 to delete the webhook first",
+                ApiError::CantGetUpdates,
+            ),
+            (
+                "Unauthorized: bot was kicked from a chat",
+                ApiError::BotKicked,
+            ),
+            (
+                "Forbidden: bot was kicked from the group chat",
+                ApiError::BotKicked,
+            ),
+            (
+                "Forbidden: bot was kicked from the channel chat",
+                ApiError::BotKicked,
+            ),
+            (
+                "Forbidden: bot was kicked from the supergroup chat",
+                ApiError::BotKickedFromSupergroup,
+            ),
+            (
+                "Unauthorized: user is deactivated",
+                ApiError::UserDeactivated,
+            ),
+            ("Forbidden: user is deactivated", ApiError::UserDeactivated),
+            (
+                "Forbidden: bot can't initiate conversation with a user",
+                ApiError::CantInitiateConversation,
+            ),
+            (
+                "Forbidden: bot can't send messages to bots",
+                ApiError::CantTalkWithBots,
+            ),
+            ("Bad Request: wrong HTTP URL", ApiError::WrongHttpUrl),
+            (
+                "Conflict: terminated by other getUpdates request; make sure that only one bot \
+                 instance is running",
+                ApiError::TerminatedByOtherGetUpdates,
+            ),
+            ("Bad Request: invalid file id", ApiError::FileIdInvalid),
+            (
+                "Too Many Requests: retry after 35",
+                ApiError::TooManyRequests {
+                    retry_after: Duration::from_secs(35),
+                },
+            ),
+        ];
+
+        for (description, expected) in cases {
+            assert_eq!(parse(description), expected, "{:?}", description);
+        }
+    }
+
+    #[test]
+    fn unknown() {
+        let cases = [
+            "Bad Request: wrong file identifier",
+            "Bad Request: chat not found in the list",
+            "Too Many Requests: retry after soon",
+            "Internal Server Error",
+            "",
+        ];
+
+        for description in cases {
+            assert_eq!(
+                parse(description),
+                ApiError::Unknown(description.to_owned()),
+                "{:?}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        let kinds = [
+            ApiError::BotBlocked,
+            ApiError::NotFound,
+            ApiError::MessageNotModified,
+            ApiError::InvalidQueryId,
+            ApiError::TextButtonsAreUnallowed,
+            ApiError::CantGetUpdates,
+            ApiError::TerminatedByOtherGetUpdates,
+            ApiError::PollQuestionLengthTooLong { limit: 300 },
+            ApiError::CantParseEntities {
+                reason: "Unexpected end tag at byte offset 3".to_owned(),
+                byte_offset: Some(3),
+            },
+            ApiError::TooManyRequests {
+                retry_after: Duration::from_secs(5),
+            },
+        ];
+
+        for kind in kinds {
+            assert_eq!(ApiErrorResponse::from(kind.clone()).kind, kind);
+        }
+    }
+}
@@ -51,6 +51,17 @@
 //! - `erased` — enables [`ErasedRequester`] bot adaptor
 //! - `throttle` — enables [`Throttle`] bot adaptor
 //! - `cache_me` — enables [`CacheMe`] bot adaptor
+//! - `retry` — enables [`Retry`] bot adaptor
+//! - `record` — enables [`Record`] bot adaptor and [`Replay`] requester
+//! - `metrics` — enables [`Metrics`] bot adaptor
+//! - `validate` — enables [`Validate`] bot adaptor
+//! - `cache_file_ids` — enables [`CacheFileIds`] bot adaptor
+//! - `auto_migrate` — enables [`AutoMigrate`] bot adaptor
+//! - `tracing` — enables [`tracing`] spans in the [`Trace`] bot adaptor and
+//!   network requests
+//! - `webhook` — enables [`webhook`] module with a server receiving updates
+//! - `testing` — enables [`testing`] module with a mock bot and a fake Bot API
+//!   server for testing
 //! - `full` — enables all features except `nightly` and tls-related
 //! - `nightly` — enables nightly-only features, currently:
 //!   - Removes some future boxing using `#![feature(type_alias_impl_trait)]`
@@ -61,6 +72,14 @@
 //! [`ErasedRequester`]: adaptors::ErasedRequester
 //! [`Throttle`]: adaptors::Throttle
 //! [`CacheMe`]: adaptors::CacheMe
+//! [`Retry`]: adaptors::Retry
+//! [`Record`]: adaptors::Record
+//! [`Replay`]: adaptors::record::Replay
+//! [`Metrics`]: adaptors::Metrics
+//! [`Validate`]: adaptors::Validate
+//! [`CacheFileIds`]: adaptors::CacheFileIds
+//! [`AutoMigrate`]: adaptors::AutoMigrate
+//! [`tracing`]: https://docs.rs/tracing
 //! [`native-tls`]: https://docs.rs/native-tls
 //! [`rustls`]: https://docs.rs/rustls
 
@@ -104,9 +123,16 @@ pub mod adaptors;
 pub mod errors;
 pub mod net;
 pub mod payloads;
+pub mod polling;
 pub mod prelude;
 pub mod requests;
 pub mod types;
+pub mod utils;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 
 // reexported
 mod bot;
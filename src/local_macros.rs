@@ -377,8 +377,8 @@ macro_rules! impl_payload {
 }
 
 macro_rules! download_forward {
-    ($l:lifetime $T:ident $S:ty {$this:ident => $inner:expr}) => {
-        impl<$l, $T: $crate::net::Download<$l>> $crate::net::Download<$l> for $S {
+    ($l:lifetime $T:ident [$($G:ident),*] $S:ty {$this:ident => $inner:expr}) => {
+        impl<$l, $T: $crate::net::Download<$l> $(, $G)*> $crate::net::Download<$l> for $S {
             type Err = <$T as $crate::net::Download<$l>>::Err;
 
             type Fut = <$T as $crate::net::Download<$l>>::Fut;
@@ -403,6 +403,23 @@ macro_rules! download_forward {
                 ($inner).download_file_stream(path)
             }
         }
+
+        impl<$l, $T: $crate::net::DownloadWith<$l> $(, $G)*> $crate::net::DownloadWith<$l> for $S {
+            fn download_file_with(
+                &self,
+                path: &str,
+                destination: &'w mut (dyn tokio::io::AsyncWrite
+                             + core::marker::Unpin
+                             + core::marker::Send),
+                options: $crate::net::DownloadOptions,
+            ) -> Self::Fut {
+                let $this = self;
+                ($inner).download_file_with(path, destination, options)
+            }
+        }
+    };
+    ($l:lifetime $T:ident $S:ty {$this:ident => $inner:expr}) => {
+        download_forward! { $l $T [] $S { $this => $inner } }
     };
 }
 
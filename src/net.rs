@@ -2,14 +2,25 @@
 
 use std::time::Duration;
 
-pub use self::download::{download_file, download_file_stream, Download};
+pub use self::{
+    download::{
+        download_file, download_file_stream, download_file_with, Download, DownloadOptions,
+        DownloadProgress, DownloadWith,
+    },
+    file_downloader::{DownloadableFile, FileDownloader},
+};
 
 pub(crate) use self::{
+    download::read_local_file,
     request::{request_json, request_multipart},
     telegram_response::TelegramResponse,
 };
 
+#[cfg(feature = "record")]
+pub(crate) use self::request::{capture_response, deserialize_response};
+
 mod download;
+mod file_downloader;
 mod request;
 mod telegram_response;
 
@@ -62,12 +73,15 @@ pub fn client_from_env() -> reqwest::Client {
 /// ## Notes
 ///
 /// 1. The settings may change in the future.
-/// 2. If you are using the polling mechanism to get updates, the timeout
-///    configured in the client should be bigger than the polling timeout.
+/// 2. [`Bot`] overrides the timeout configured in the client for requests
+///    which need more time (long polling and file uploads) or if
+///    [`Bot::set_timeout`] was used.
 /// 3. If you alter the current settings listed above, your bot will not be
 ///    guaranteed to work over long time durations.
 ///
 /// [issue 223]: https://github.com/teloxide/teloxide/issues/223
+/// [`Bot`]: crate::Bot
+/// [`Bot::set_timeout`]: crate::Bot::set_timeout
 pub fn default_reqwest_settings() -> reqwest::ClientBuilder {
     reqwest::Client::builder()
         .connect_timeout(Duration::from_secs(5))
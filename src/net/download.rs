@@ -1,13 +1,13 @@
-use std::future::Future;
+use std::{fmt, future::Future, io::SeekFrom, path::PathBuf, sync::Arc};
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::{
     future::{ready, Either},
     stream::{once, unfold},
-    FutureExt, Stream, StreamExt,
+    FutureExt, Stream, StreamExt, TryStreamExt,
 };
-use reqwest::{Client, Response, Url};
-use tokio::io::{AsyncWrite, AsyncWriteExt};
+use reqwest::{header::RANGE, Client, Response, StatusCode, Url};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
 
 use crate::{errors::DownloadError, net::file_url};
 
@@ -79,6 +79,154 @@ pub trait Download<'w>
     fn download_file_stream(&self, path: &str) -> Self::Stream;
 }
 
+/// An extension of [`Download`] which allows to download files with
+/// [`DownloadOptions`].
+///
+/// This is a separate trait, so that implementors of [`Download`] outside of
+/// this library don't have to implement it.
+pub trait DownloadWith<'w>: Download<'w> {
+    /// Download a file from Telegram into `destination` with additional
+    /// `options`.
+    ///
+    /// This is the same as [`download_file`], but allows to track the
+    /// progress, resume partial downloads, limit and verify the size of the
+    /// file. See [`DownloadOptions`] for more.
+    ///
+    /// ## Examples
+    ///
+    /// Resume a download into a partially downloaded file:
+    ///
+    /// ```no_run
+    /// use teloxide_core::{
+    ///     net::{DownloadOptions, DownloadWith},
+    ///     requests::{Request, Requester},
+    ///     Bot,
+    /// };
+    /// use tokio::fs::OpenOptions;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let bot = Bot::new("TOKEN");
+    ///
+    /// let file = bot.get_file("*file_id*").send().await?;
+    /// let mut dst = OpenOptions::new()
+    ///     .create(true)
+    ///     .append(true)
+    ///     .open("/tmp/test.png")
+    ///     .await?;
+    /// let options = DownloadOptions::new()
+    ///     .resume_from(dst.metadata().await?.len())
+    ///     .expected_size(file.file_size.into())
+    ///     .on_progress(|progress| log::info!("downloaded {} bytes", progress.downloaded));
+    ///
+    /// bot.download_file_with(&file.file_path, &mut dst, options)
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`download_file`]: Self::download_file
+    fn download_file_with(
+        &self,
+        path: &str,
+        destination: &'w mut (dyn AsyncWrite + Unpin + Send),
+        options: DownloadOptions,
+    ) -> Self::Fut;
+}
+
+/// Options of a file download.
+///
+/// See [`DownloadWith::download_file_with`].
+#[derive(Clone, Default)]
+pub struct DownloadOptions {
+    progress: Option<Arc<dyn Fn(DownloadProgress) + Send + Sync>>,
+    resume_from: u64,
+    max_size: Option<u64>,
+    expected_size: Option<u64>,
+}
+
+/// Progress of a file download.
+///
+/// See [`DownloadOptions::on_progress`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// Number of bytes that were downloaded so far, including bytes that were
+    /// downloaded before resuming.
+    pub downloaded: u64,
+
+    /// Size of the file, if it's known from the `Content-Length` header.
+    pub total: Option<u64>,
+}
+
+impl DownloadOptions {
+    /// Creates default options, which download the whole file without any
+    /// checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a callback which is called with the download progress every time
+    /// a chunk of the file is received.
+    ///
+    /// The callback is first called when the response is received, at that
+    /// point the size of the file is known if the server reported it.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(DownloadProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Resumes the download from `offset`, i.e. downloads the file without the
+    /// first `offset` bytes.
+    ///
+    /// This uses the HTTP `Range` header, if the server ignores it, the first
+    /// `offset` bytes are skipped. This is meant to be used with a destination
+    /// which already contains `offset` bytes of the file (e.g. a partially
+    /// downloaded file opened in append mode).
+    pub fn resume_from(mut self, offset: u64) -> Self {
+        self.resume_from = offset;
+        self
+    }
+
+    /// Fails the download with [`DownloadError::TooLarge`] if the file is
+    /// larger than `limit` bytes.
+    ///
+    /// The download fails before anything is written, if the server reported
+    /// the size of the file, otherwise it fails as soon as `limit` is
+    /// exceeded.
+    pub fn max_size(mut self, limit: u64) -> Self {
+        self.max_size = Some(limit);
+        self
+    }
+
+    /// Fails the download with [`DownloadError::SizeMismatch`] if the size of
+    /// the downloaded file is not `size`.
+    ///
+    /// `size` is usually [`File::file_size`].
+    ///
+    /// [`File::file_size`]: crate::types::File::file_size
+    pub fn expected_size(mut self, size: u64) -> Self {
+        self.expected_size = Some(size);
+        self
+    }
+
+    fn report(&self, downloaded: u64, total: Option<u64>) {
+        if let Some(progress) = &self.progress {
+            progress(DownloadProgress { downloaded, total });
+        }
+    }
+}
+
+impl fmt::Debug for DownloadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DownloadOptions")
+            .field("resume_from", &self.resume_from)
+            .field("max_size", &self.max_size)
+            .field("expected_size", &self.expected_size)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Download a file from Telegram into `dst`.
 ///
 /// Note: if you don't need to use a different (from you're bot) client and
@@ -94,18 +242,142 @@ pub fn download_file<'o, D>(
 where
     D: ?Sized + AsyncWrite + Unpin,
 {
-    client
-        .get(file_url(api_url, token, path))
-        .send()
-        .then(move |r| async move {
-            let mut res = r?.error_for_status()?;
-
-            while let Some(chunk) = res.chunk().await? {
-                dst.write_all(&chunk).await?;
+    download_file_with(client, api_url, token, path, dst, DownloadOptions::new())
+}
+
+/// Download a file from Telegram into `dst` with additional `options`.
+///
+/// Note: if you don't need to use a different (from you're bot) client and
+/// don't need to get *all* performance (and you don't, c'mon it's very io-bound
+/// job), then it's recommended to use [`Download::download_file_with`].
+pub fn download_file_with<'o, D>(
+    client: &Client,
+    api_url: Url,
+    token: &str,
+    path: &str,
+    dst: &'o mut D,
+    options: DownloadOptions,
+) -> impl Future<Output = Result<(), DownloadError>> + 'o
+where
+    D: ?Sized + AsyncWrite + Unpin,
+{
+    let offset = options.resume_from;
+
+    let mut request = client.get(file_url(api_url, token, path));
+    if offset != 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+    }
+
+    let fut = async move {
+        // The file is already downloaded, there is nothing to resume
+        if offset != 0 && options.expected_size == Some(offset) {
+            options.report(offset, Some(offset));
+            return Ok(());
+        }
+
+        let res = request.send().await?.error_for_status()?;
+
+        // The server may ignore `Range` and send the whole file
+        let skip = match res.status() {
+            StatusCode::PARTIAL_CONTENT => 0,
+            _ => offset,
+        };
+        let total = res.content_length().map(|len| len + offset - skip);
+
+        let chunks = res.bytes_stream().map_err(DownloadError::from);
+        write_chunks(chunks, skip, total, dst, &options).await
+    };
+
+    #[cfg(feature = "tracing")]
+    let fut = tracing::Instrument::instrument(fut, tracing::debug_span!("telegram_download", path));
+
+    fut
+}
+
+/// Reads a file of a local Bot API server from the filesystem into `dst`.
+pub(crate) async fn read_local_file<D>(
+    path: PathBuf,
+    dst: &mut D,
+    options: DownloadOptions,
+) -> Result<(), DownloadError>
+where
+    D: ?Sized + AsyncWrite + Unpin,
+{
+    // Chunk size, arbitrary chosen to be 64KiB
+    const CHUNK: usize = 64 * 1024;
+
+    let offset = options.resume_from;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let total = file.metadata().await?.len();
+    if offset != 0 {
+        file.seek(SeekFrom::Start(offset)).await?;
+    }
+
+    let chunks = unfold(file, |mut file| async move {
+        let mut chunk = BytesMut::with_capacity(CHUNK);
+        match file.read_buf(&mut chunk).await {
+            Ok(0) => None,
+            Ok(_) => Some((Ok(chunk.freeze()), file)),
+            Err(err) => Some((Err(DownloadError::Io(err)), file)),
+        }
+    });
+    futures::pin_mut!(chunks);
+
+    write_chunks(chunks, 0, Some(total), dst, &options).await
+}
+
+/// Writes `chunks` of a file into `dst`, skipping the first `skip` bytes
+/// and checking limits of `options`.
+async fn write_chunks<S, D>(
+    mut chunks: S,
+    mut skip: u64,
+    total: Option<u64>,
+    dst: &mut D,
+    options: &DownloadOptions,
+) -> Result<(), DownloadError>
+where
+    S: Stream<Item = Result<Bytes, DownloadError>> + Unpin,
+    D: ?Sized + AsyncWrite + Unpin,
+{
+    if let (Some(total), Some(limit)) = (total, options.max_size) {
+        if total > limit {
+            return Err(DownloadError::TooLarge { limit });
+        }
+    }
+
+    let mut downloaded = options.resume_from;
+    options.report(downloaded, total);
+
+    while let Some(mut chunk) = chunks.try_next().await? {
+        if skip != 0 {
+            let n = skip.min(chunk.len() as u64);
+            let _ = chunk.split_to(n as usize);
+            skip -= n;
+        }
+
+        if chunk.is_empty() {
+            continue;
+        }
+
+        downloaded += chunk.len() as u64;
+        if let Some(limit) = options.max_size {
+            if downloaded > limit {
+                return Err(DownloadError::TooLarge { limit });
             }
+        }
 
-            Ok(())
-        })
+        dst.write_all(&chunk).await?;
+        options.report(downloaded, total);
+    }
+
+    match options.expected_size {
+        Some(expected) if expected != downloaded => Err(DownloadError::SizeMismatch {
+            expected,
+            actual: downloaded,
+        }),
+        _ => Ok(()),
+    }
 }
 
 /// Download a file from Telegram as [`Stream`].
@@ -119,10 +391,15 @@ pub fn download_file_stream(
     token: &str,
     path: &str,
 ) -> impl Stream<Item = reqwest::Result<Bytes>> + 'static {
-    client
-        .get(file_url(api_url, token, path))
-        .send()
-        .into_stream()
+    let send = client.get(file_url(api_url, token, path)).send();
+
+    // Note: only the request itself is instrumented, reading of the chunks is
+    // driven by the user
+    #[cfg(feature = "tracing")]
+    let send =
+        tracing::Instrument::instrument(send, tracing::debug_span!("telegram_download", path));
+
+    send.into_stream()
         .flat_map(|res| match res.and_then(Response::error_for_status) {
             Ok(res) => Either::Left(unfold(res, |mut res| async {
                 match res.chunk().await {
@@ -134,3 +411,81 @@ pub fn download_file_stream(
             Err(err) => Either::Right(once(ready(Err(err)))),
         })
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn download_with_options() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::{
+            net::{DownloadOptions, DownloadProgress, DownloadWith},
+            requests::{Request, Requester},
+            testing::fake_server::FakeServer,
+            DownloadError,
+        };
+
+        let server = FakeServer::start().unwrap();
+        let bot = server.bot();
+        let file_id = server.add_file(&b"hello world"[..]);
+        let file = bot.get_file(file_id).send().await.unwrap();
+
+        // Progress is reported
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let options = DownloadOptions::new().expected_size(11).on_progress({
+            let reports = Arc::clone(&reports);
+            move |progress| reports.lock().unwrap().push(progress)
+        });
+        let mut buf = Vec::new();
+        bot.download_file_with(&file.file_path, &mut buf, options)
+            .await
+            .unwrap();
+        assert_eq!(buf, b"hello world");
+
+        let reports = reports.lock().unwrap().clone();
+        assert_eq!(
+            reports.first(),
+            Some(&DownloadProgress {
+                downloaded: 0,
+                total: Some(11)
+            })
+        );
+        assert_eq!(
+            reports.last(),
+            Some(&DownloadProgress {
+                downloaded: 11,
+                total: Some(11)
+            })
+        );
+
+        // Partial download is resumed
+        let mut buf = b"hello".to_vec();
+        let options = DownloadOptions::new().resume_from(5).expected_size(11);
+        bot.download_file_with(&file.file_path, &mut buf, options)
+            .await
+            .unwrap();
+        assert_eq!(buf, b"hello world");
+
+        // Size guards
+        let mut buf = Vec::new();
+        let options = DownloadOptions::new().max_size(10);
+        let res = bot
+            .download_file_with(&file.file_path, &mut buf, options)
+            .await;
+        assert!(matches!(res, Err(DownloadError::TooLarge { limit: 10 })));
+        assert!(buf.is_empty());
+
+        let options = DownloadOptions::new().expected_size(12);
+        let res = bot
+            .download_file_with(&file.file_path, &mut buf, options)
+            .await;
+        assert!(matches!(
+            res,
+            Err(DownloadError::SizeMismatch {
+                expected: 12,
+                actual: 11
+            })
+        ));
+    }
+}
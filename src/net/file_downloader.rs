@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::io::AsyncWrite;
+
+use crate::{
+    errors::FileDownloadError,
+    net::Download,
+    requests::{Request, Requester},
+    types::{Animation, Audio, Document, File, PhotoSize, Sticker, Video, VideoNote, Voice},
+};
+
+/// How long a `file_path` returned by `GetFile` is used before it's resolved
+/// again. Telegram guarantees that it's valid for at least an hour.
+const PATH_TTL: Duration = Duration::from_secs(55 * 60);
+
+/// Downloads files by their `file_id`, optionally caching them on disk.
+///
+/// [`download`] resolves a `file_id` into a `file_path` via [`GetFile`] and
+/// downloads the file. Resolved paths are reused for some time and resolved
+/// again before they expire, so that repeated downloads don't need to call
+/// [`GetFile`] each time.
+///
+/// If a [cache directory] is set, files are stored there by their
+/// `file_unique_id`, so repeated downloads of the same file (even if it has a
+/// different `file_id`) are read from the disk. When the `file_unique_id` is
+/// known beforehand (e.g. when downloading a [`Document`]), cached files are
+/// read without any requests to Telegram.
+///
+/// ## Examples
+///
+/// ```no_run
+/// use teloxide_core::{net::FileDownloader, types::Document, Bot};
+///
+/// # async fn run(document: Document) -> Result<(), Box<dyn std::error::Error>> {
+/// let downloader = FileDownloader::new(Bot::new("TOKEN")).cache_dir("/tmp/telegram-files");
+///
+/// let mut buf = Vec::new();
+/// downloader.download(&document, &mut buf).await?;
+/// # Ok(()) }
+/// ```
+///
+/// [`download`]: FileDownloader::download
+/// [`GetFile`]: crate::payloads::GetFile
+/// [cache directory]: FileDownloader::cache_dir
+#[derive(Debug)]
+pub struct FileDownloader<B> {
+    bot: B,
+    cache_dir: Option<PathBuf>,
+    // file_id => (file_path, file_unique_id, when it was resolved)
+    paths: Mutex<HashMap<String, (String, String, Instant)>>,
+}
+
+impl<B> FileDownloader<B> {
+    /// Creates a new downloader which doesn't cache files on disk.
+    pub fn new(bot: B) -> Self {
+        Self {
+            bot,
+            cache_dir: None,
+            paths: Mutex::default(),
+        }
+    }
+
+    /// Sets the directory where downloaded files are cached.
+    ///
+    /// The directory is created if it doesn't exist.
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Returns a reference to the inner bot.
+    pub fn inner(&self) -> &B {
+        &self.bot
+    }
+
+    /// Unwraps the inner bot.
+    pub fn into_inner(self) -> B {
+        self.bot
+    }
+}
+
+impl<B> FileDownloader<B>
+where
+    B: Requester,
+{
+    /// Downloads `file` into `destination`.
+    ///
+    /// `file` can be a `file_id` or any media with a `file_id`, see
+    /// [`DownloadableFile`].
+    pub async fn download<F, E>(
+        &self,
+        file: &F,
+        destination: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<(), FileDownloadError<<B as Requester>::Err, E>>
+    where
+        F: DownloadableFile + ?Sized,
+        B: for<'w> Download<'w, Err = E>,
+    {
+        let dir = match &self.cache_dir {
+            Some(dir) => dir,
+            None => {
+                let (path, _) = self.resolve(file.file_id()).await?;
+                return self
+                    .bot
+                    .download_file(&path, destination)
+                    .await
+                    .map_err(FileDownloadError::Download);
+            }
+        };
+
+        if let Some(unique_id) = file.file_unique_id() {
+            if copy_cached(&cache_path(dir, unique_id), destination).await? {
+                return Ok(());
+            }
+        }
+
+        let (path, unique_id) = self.resolve(file.file_id()).await?;
+        let cached = cache_path(dir, &unique_id);
+        if copy_cached(&cached, destination).await? {
+            return Ok(());
+        }
+
+        // Download into a temporary file first, so that partially downloaded files are
+        // never read from the cache
+        tokio::fs::create_dir_all(dir).await?;
+        let tmp = cached.with_extension(format!("{}.part", uuid::Uuid::new_v4()));
+        let mut tmp_file = tokio::fs::File::create(&tmp).await?;
+        let res = self.bot.download_file(&path, &mut tmp_file).await;
+        drop(tmp_file);
+
+        if let Err(err) = res {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(FileDownloadError::Download(err));
+        }
+        tokio::fs::rename(&tmp, &cached).await?;
+
+        copy_cached(&cached, destination).await?;
+        Ok(())
+    }
+
+    /// Returns `file_path` and `file_unique_id` of a file, calling `GetFile`
+    /// if the file wasn't resolved recently.
+    async fn resolve<E>(
+        &self,
+        file_id: &str,
+    ) -> Result<(String, String), FileDownloadError<B::Err, E>> {
+        {
+            let paths = self.paths.lock().unwrap_or_else(|err| err.into_inner());
+            if let Some((path, unique_id, resolved)) = paths.get(file_id) {
+                if resolved.elapsed() < PATH_TTL {
+                    return Ok((path.clone(), unique_id.clone()));
+                }
+            }
+        }
+
+        let File {
+            file_path,
+            file_unique_id,
+            ..
+        } = self
+            .bot
+            .get_file(file_id)
+            .send()
+            .await
+            .map_err(FileDownloadError::GetFile)?;
+
+        let mut paths = self.paths.lock().unwrap_or_else(|err| err.into_inner());
+        paths.retain(|_, (_, _, resolved)| resolved.elapsed() < PATH_TTL);
+        paths.insert(
+            file_id.to_owned(),
+            (file_path.clone(), file_unique_id.clone(), Instant::now()),
+        );
+
+        Ok((file_path, file_unique_id))
+    }
+}
+
+/// Copies a cached file into `destination`, returns `false` if the file is not
+/// cached.
+async fn copy_cached(
+    path: &Path,
+    destination: &mut (dyn AsyncWrite + Unpin + Send),
+) -> io::Result<bool> {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+
+    tokio::io::copy(&mut file, destination).await?;
+    Ok(true)
+}
+
+fn cache_path(dir: &Path, unique_id: &str) -> PathBuf {
+    // `file_unique_id`s are url-safe base64, but we don't want to trust that when
+    // building a path
+    let name: String = unique_id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+
+    dir.join(name)
+}
+
+/// A file which can be downloaded by [`FileDownloader`].
+///
+/// This is implemented for `file_id`s (`str` and `String`) and media types
+/// which contain a `file_id`.
+pub trait DownloadableFile {
+    /// Returns the identifier of the file, used to download it.
+    fn file_id(&self) -> &str;
+
+    /// Returns the unique identifier of the file, if it's known.
+    fn file_unique_id(&self) -> Option<&str>;
+}
+
+impl DownloadableFile for str {
+    fn file_id(&self) -> &str {
+        self
+    }
+
+    fn file_unique_id(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl DownloadableFile for String {
+    fn file_id(&self) -> &str {
+        self
+    }
+
+    fn file_unique_id(&self) -> Option<&str> {
+        None
+    }
+}
+
+macro_rules! impl_downloadable_file {
+    ($($T:ty),*) => {
+        $(
+            impl DownloadableFile for $T {
+                fn file_id(&self) -> &str {
+                    &self.file_id
+                }
+
+                fn file_unique_id(&self) -> Option<&str> {
+                    Some(&self.file_unique_id)
+                }
+            }
+        )*
+    };
+}
+
+impl_downloadable_file!(
+    File, PhotoSize, Document, Audio, Video, Voice, Sticker, Animation, VideoNote
+);
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn download_and_cache() {
+        use crate::{
+            net::FileDownloader,
+            payloads::SendDocumentSetters,
+            requests::{Request, Requester},
+            testing::fake_server::FakeServer,
+            types::{ChatId, InputFile},
+        };
+
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        let bot = server.bot();
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+
+        let message = bot
+            .send_document(ChatId(1), InputFile::memory(&b"hello"[..]))
+            .caption("cap")
+            .send()
+            .await
+            .unwrap();
+        let document = message.document().unwrap();
+        let requests = server.requests().len();
+
+        // Without cache the path is resolved only once
+        let downloader = FileDownloader::new(bot.clone());
+        for _ in 0..2 {
+            let mut buf = Vec::new();
+            downloader
+                .download(&*document.file_id, &mut buf)
+                .await
+                .unwrap();
+            assert_eq!(buf, b"hello");
+        }
+        assert_eq!(server.requests().len(), requests + 1);
+
+        // Cached files are read from the disk without any requests
+        let downloader = FileDownloader::new(bot).cache_dir(&dir);
+        for _ in 0..2 {
+            let mut buf = Vec::new();
+            downloader.download(document, &mut buf).await.unwrap();
+            assert_eq!(buf, b"hello");
+        }
+        assert_eq!(server.requests().len(), requests + 2);
+        assert_eq!(
+            std::fs::read(dir.join(&document.file_unique_id)).unwrap(),
+            b"hello"
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "record")]
+use std::cell::RefCell;
 use std::time::Duration;
 
 use reqwest::{
@@ -10,13 +12,21 @@ use crate::{net::TelegramResponse, requests::ResponseResult, RequestError};
 
 const DELAY_ON_SERVER_ERROR: Duration = Duration::from_secs(10);
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "telegram_request",
+        skip_all,
+        fields(method = method_name, multipart = true, status = tracing::field::Empty)
+    )
+)]
 pub async fn request_multipart<T>(
     client: &Client,
     token: &str,
     api_url: reqwest::Url,
     method_name: &str,
     params: reqwest::multipart::Form,
-    _timeout_hint: Option<Duration>,
+    timeout: Option<Duration>,
 ) -> ResponseResult<T>
 where
     T: DeserializeOwned,
@@ -34,28 +44,41 @@ where
     // [#460]: https://github.com/teloxide/teloxide/issues/460
     let method_name = method_name.trim_end_matches("Inline");
 
-    let request = client
+    let mut request = client
         .post(crate::net::method_url(api_url, token, method_name))
         .multipart(params)
         .build()?;
 
-    // FIXME: uncomment this, when reqwest starts setting default timeout early
-    // if let Some(timeout) = timeout_hint {
-    //     *request.timeout_mut().get_or_insert(Duration::ZERO) += timeout;
-    // }
+    // Note: this replaces the timeout of the client
+    if let Some(timeout) = timeout {
+        *request.timeout_mut() = Some(timeout);
+    }
 
     let response = client.execute(request).await?;
 
-    process_response(response).await
+    process_response(response, method_name).await
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "telegram_request",
+        skip_all,
+        fields(
+            method = method_name,
+            multipart = false,
+            payload_size = params.len(),
+            status = tracing::field::Empty,
+        )
+    )
+)]
 pub async fn request_json<T>(
     client: &Client,
     token: &str,
     api_url: reqwest::Url,
     method_name: &str,
     params: Vec<u8>,
-    _timeout_hint: Option<Duration>,
+    timeout: Option<Duration>,
 ) -> ResponseResult<T>
 where
     T: DeserializeOwned,
@@ -73,36 +96,93 @@ where
     // [#460]: https://github.com/teloxide/teloxide/issues/460
     let method_name = method_name.trim_end_matches("Inline");
 
-    let request = client
+    let mut request = client
         .post(crate::net::method_url(api_url, token, method_name))
         .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
         .body(params)
         .build()?;
 
-    // FIXME: uncomment this, when reqwest starts setting default timeout early
-    // if let Some(timeout) = timeout_hint {
-    //     *request.timeout_mut().get_or_insert(Duration::ZERO) += timeout;
-    // }
+    // Note: this replaces the timeout of the client
+    if let Some(timeout) = timeout {
+        *request.timeout_mut() = Some(timeout);
+    }
 
     let response = client.execute(request).await?;
 
-    process_response(response).await
+    process_response(response, method_name).await
 }
 
-async fn process_response<T>(response: Response) -> ResponseResult<T>
+async fn process_response<T>(response: Response, method_name: &str) -> ResponseResult<T>
 where
     T: DeserializeOwned,
 {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("status", response.status().as_u16());
+
     if response.status().is_server_error() {
         tokio::time::sleep(DELAY_ON_SERVER_ERROR).await;
     }
 
     let text = response.text().await?;
 
+    deserialize_response(text, method_name)
+}
+
+/// Deserializes raw response text sent by Telegram in response to a call of
+/// `method_name`.
+pub(crate) fn deserialize_response<T>(text: String, method_name: &str) -> ResponseResult<T>
+where
+    T: DeserializeOwned,
+{
+    #[cfg(feature = "record")]
+    CAPTURED_RESPONSE.with(|captured| {
+        if let Some(captured) = &mut *captured.borrow_mut() {
+            *captured = Some(text.clone());
+        }
+    });
+
     serde_json::from_str::<TelegramResponse<T>>(&text)
         .map_err(|source| RequestError::InvalidJson {
             source,
             raw: text.into(),
         })?
-        .into()
+        .into_result(method_name)
+}
+
+#[cfg(feature = "record")]
+thread_local! {
+    /// Raw text of the last response deserialized inside of the innermost
+    /// [`capture_response`] call, `None` if there is no such call.
+    // `const` initializers are not supported by our MSRV
+    #[allow(clippy::missing_const_for_thread_local)]
+    static CAPTURED_RESPONSE: RefCell<Option<Option<String>>> = RefCell::new(None);
+}
+
+/// Calls `f`, capturing the raw text of the last response deserialized by
+/// [`deserialize_response`] on this thread during the call.
+///
+/// This is used by `Record` around polls of the inner futures, so that
+/// responses are recorded exactly as they were sent by Telegram.
+#[cfg(feature = "record")]
+pub(crate) fn capture_response<R>(f: impl FnOnce() -> R) -> (R, Option<String>) {
+    // Restores the outer capture even if `f` panics
+    struct Guard(Option<Option<String>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            let outer = self.0.take();
+            CAPTURED_RESPONSE.with(|captured| *captured.borrow_mut() = outer);
+        }
+    }
+
+    let mut guard = Guard(CAPTURED_RESPONSE.with(|captured| captured.replace(Some(None))));
+    let ret = f();
+    let text = CAPTURED_RESPONSE.with(|captured| captured.borrow_mut().take().flatten());
+
+    // Outer captures (e.g. of `Record<Record<Bot>>`) see the same response
+    if let (Some(outer), Some(text)) = (&mut guard.0, &text) {
+        *outer = Some(text.clone());
+    }
+
+    (ret, text)
 }
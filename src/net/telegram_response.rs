@@ -1,9 +1,10 @@
 use serde::Deserialize;
 
 use crate::{
+    errors::ApiErrorResponse,
     requests::ResponseResult,
     types::{False, ResponseParameters, True},
-    ApiError, RequestError,
+    RequestError,
 };
 
 #[derive(Deserialize)]
@@ -22,20 +23,20 @@ pub(crate) enum TelegramResponse<R> {
         #[allow(dead_code)]
         ok: False,
 
-        #[serde(rename = "description")]
-        error: ApiError,
+        description: String,
+
+        error_code: u16,
 
-        // // This field is present in the json sent by telegram, but isn't currently used anywhere
-        // // and as such - ignored
-        // error_code: u16,
         #[serde(rename = "parameters")]
         response_parameters: Option<ResponseParameters>,
     },
 }
 
-impl<R> From<TelegramResponse<R>> for ResponseResult<R> {
-    fn from(this: TelegramResponse<R>) -> ResponseResult<R> {
-        match this {
+impl<R> TelegramResponse<R> {
+    /// Converts the response into a result, `method` is the name of the method
+    /// which was called.
+    pub(crate) fn into_result(self, method: &str) -> ResponseResult<R> {
+        match self {
             TelegramResponse::Ok { response, .. } => Ok(response),
             TelegramResponse::Err {
                 response_parameters: Some(params),
@@ -44,7 +45,15 @@ impl<R> From<TelegramResponse<R>> for ResponseResult<R> {
                 ResponseParameters::RetryAfter(i) => RequestError::RetryAfter(i),
                 ResponseParameters::MigrateToChatId(to) => RequestError::MigrateToChatId(to),
             }),
-            TelegramResponse::Err { error, .. } => Err(RequestError::Api(error)),
+            TelegramResponse::Err {
+                description,
+                error_code,
+                ..
+            } => {
+                let error = ApiErrorResponse::new(error_code, description).with_method(method);
+
+                Err(RequestError::Api(error))
+            }
         }
     }
 }
@@ -59,13 +68,14 @@ mod tests {
         let s = r#"{"ok":false,"error_code":409,"description":"Conflict: terminated by other getUpdates request; make sure that only one bot instance is running"}"#;
         let val = serde_json::from_str::<TelegramResponse<Update>>(s).unwrap();
 
-        assert!(matches!(
-            val,
-            TelegramResponse::Err {
-                error: ApiError::TerminatedByOtherGetUpdates,
-                ..
+        match val.into_result("getUpdates") {
+            Err(RequestError::Api(err)) => {
+                assert_eq!(err.kind, ApiError::TerminatedByOtherGetUpdates);
+                assert_eq!(err.error_code, 409);
+                assert_eq!(err.method.as_deref(), Some("getUpdates"));
             }
-        ));
+            other => panic!("unexpected result: {:?}", other),
+        }
     }
 
     #[test]
@@ -73,8 +83,19 @@ mod tests {
         let s = r#"{"ok":false,"error_code":111,"description":"Unknown description that won't match anything"}"#;
         let val = serde_json::from_str::<TelegramResponse<Update>>(s).unwrap();
 
-        assert!(
-            matches!(val, TelegramResponse::Err { error: ApiError::Unknown(s), .. } if s == "Unknown description that won't match anything")
-        );
+        match val.into_result("getUpdates") {
+            Err(RequestError::Api(err)) => {
+                assert_eq!(
+                    err.kind,
+                    ApiError::Unknown("Unknown description that won't match anything".to_owned())
+                );
+                assert_eq!(err.error_code, 111);
+                assert_eq!(
+                    err.description,
+                    "Unknown description that won't match anything"
+                );
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
     }
 }
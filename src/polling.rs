@@ -0,0 +1,523 @@
+//! Long polling of updates.
+//!
+//! See [`Polling`] for more.
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use futures::{
+    future::{self, Either},
+    pin_mut,
+    stream::{self, Stream},
+};
+use tokio::sync::watch;
+
+use crate::{
+    payloads::GetUpdatesSetters,
+    requests::{Request, Requester},
+    types::{AllowedUpdate, Update},
+    ApiError, RequestError,
+};
+
+/// A stream of updates received using long polling ([`GetUpdates`]).
+///
+/// `Polling` manages the `offset` of [`GetUpdates`] requests: an update is
+/// considered processed (and will be confirmed by the next request) as soon as
+/// it's yielded from the stream.
+///
+/// Errors are yielded from the stream too, after an error the stream waits
+/// before sending the next request (exponentially increasing the delay on
+/// consecutive errors, or waiting as much as Telegram asks in case of
+/// [`RequestError::RetryAfter`]).
+///
+/// Note that `getUpdates` doesn't work if a webhook is set up, use
+/// [`delete_webhook`] before polling.
+///
+/// [`GetUpdates`]: crate::payloads::GetUpdates
+/// [`delete_webhook`]: crate::requests::Requester::delete_webhook
+///
+/// ## Examples
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use teloxide_core::{polling::Polling, types::AllowedUpdate, Bot};
+///
+/// # async {
+/// let (updates, shutdown) = Polling::new(Bot::from_env())
+///     .allowed_updates([AllowedUpdate::Message])
+///     .into_stream();
+///
+/// # let _ = shutdown;
+/// futures::pin_mut!(updates);
+/// while let Some(update) = updates.next().await {
+///     match update {
+///         Ok(update) => println!("{:?}", update),
+///         Err(err) => log::error!("Error while polling: {}", err),
+///     }
+/// }
+/// # };
+/// ```
+#[derive(Clone, Debug)]
+pub struct Polling<R> {
+    bot: R,
+    timeout: Duration,
+    limit: Option<u8>,
+    allowed_updates: Option<Vec<AllowedUpdate>>,
+    drop_pending_updates: bool,
+    max_backoff: Duration,
+}
+
+/// A handle that allows to gracefully stop [`Polling`].
+///
+/// See [`ShutdownToken::shutdown`].
+#[derive(Clone, Debug)]
+pub struct ShutdownToken {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl<R> Polling<R>
+where
+    R: Requester<Err = RequestError> + Send + Sync + 'static,
+    R::GetUpdates: Send,
+{
+    /// Creates new polling with default settings.
+    ///
+    /// The defaults are:
+    ///  - `timeout` of 10 seconds
+    ///  - `limit` not set (i.e. Telegram's default of 100)
+    ///  - `allowed_updates` not set (i.e. the previous setting is used)
+    ///  - pending updates are not dropped
+    ///  - maximum delay after errors is 1 minute
+    pub fn new(bot: R) -> Self {
+        Self {
+            bot,
+            timeout: Duration::from_secs(10),
+            limit: None,
+            allowed_updates: None,
+            drop_pending_updates: false,
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets timeout for long polling.
+    ///
+    /// Note: the timeout of the http-client used by the bot must be greater
+    /// than this timeout.
+    pub fn timeout(mut self, val: Duration) -> Self {
+        self.timeout = val;
+        self
+    }
+
+    /// Sets the maximum number of updates retrieved by one request.
+    ///
+    /// ## Panics
+    ///
+    /// If `val` is not in `1..=100`.
+    pub fn limit(mut self, val: u8) -> Self {
+        assert!((1..=100).contains(&val), "limit must be in 1..=100");
+        self.limit = Some(val);
+        self
+    }
+
+    /// Sets the types of updates to receive.
+    pub fn allowed_updates<I>(mut self, val: I) -> Self
+    where
+        I: IntoIterator<Item = AllowedUpdate>,
+    {
+        self.allowed_updates = Some(val.into_iter().collect());
+        self
+    }
+
+    /// Drop updates that are pending at the moment when polling starts.
+    pub fn drop_pending_updates(mut self) -> Self {
+        self.drop_pending_updates = true;
+        self
+    }
+
+    /// Sets the maximum delay between requests after errors.
+    pub fn max_backoff(mut self, val: Duration) -> Self {
+        self.max_backoff = val;
+        self
+    }
+
+    /// Turns this polling into a stream of updates.
+    ///
+    /// Returns the stream alongside with a token that allows to stop it.
+    pub fn into_stream(
+        self,
+    ) -> (
+        impl Stream<Item = Result<Update, RequestError>> + Send + 'static,
+        ShutdownToken,
+    ) {
+        let (tx, shutdown) = watch::channel(false);
+        let token = ShutdownToken { tx: Arc::new(tx) };
+
+        let state = State {
+            polling: self,
+            offset: None,
+            buffer: VecDeque::new(),
+            delay: None,
+            errors_in_row: 0,
+            shutdown,
+            started: false,
+        };
+
+        (stream::unfold(state, next), token)
+    }
+}
+
+impl ShutdownToken {
+    /// Stops the polling.
+    ///
+    /// In-flight request is cancelled, offset of the last update yielded from
+    /// the stream is acknowledged to Telegram and then the stream ends. Updates
+    /// that were received but not yet yielded will be received again.
+    pub fn shutdown(&self) {
+        // Error means that the stream was already dropped
+        let _ = self.tx.send(true);
+    }
+}
+
+struct State<R> {
+    polling: Polling<R>,
+    offset: Option<i32>,
+    buffer: VecDeque<Update>,
+    delay: Option<Duration>,
+    errors_in_row: u32,
+    shutdown: watch::Receiver<bool>,
+    started: bool,
+}
+
+impl<R> State<R> {
+    fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+}
+
+async fn next<R>(mut st: State<R>) -> Option<(Result<Update, RequestError>, State<R>)>
+where
+    R: Requester<Err = RequestError>,
+{
+    loop {
+        if st.is_shutting_down() {
+            acknowledge(&st).await;
+            return None;
+        }
+
+        if let Some(update) = st.buffer.pop_front() {
+            st.offset = Some(update.id + 1);
+            return Some((Ok(update), st));
+        }
+
+        if !st.started {
+            st.started = true;
+
+            if st.polling.drop_pending_updates {
+                if let Err(err) = drop_pending_updates(&mut st).await {
+                    st.started = false;
+                    return Some((Err(err), on_error(st, None)));
+                }
+                continue;
+            }
+        }
+
+        if let Some(delay) = st.delay.take() {
+            let sleep = tokio::time::sleep(delay);
+            let shutdown = shutdown_requested(&mut st.shutdown);
+            pin_mut!(sleep, shutdown);
+
+            future::select(sleep, shutdown).await;
+            continue;
+        }
+
+        let Polling {
+            bot,
+            timeout,
+            limit,
+            allowed_updates,
+            ..
+        } = &st.polling;
+
+        let mut req = bot.get_updates().timeout(timeout.as_secs() as u32);
+        if let Some(offset) = st.offset {
+            req = req.offset(offset);
+        }
+        if let Some(limit) = limit {
+            req = req.limit(*limit);
+        }
+        if let Some(allowed_updates) = allowed_updates {
+            req = req.allowed_updates(allowed_updates.iter().copied());
+        }
+
+        let res = {
+            let send = req.send();
+            let shutdown = shutdown_requested(&mut st.shutdown);
+            pin_mut!(send, shutdown);
+
+            match future::select(send, shutdown).await {
+                Either::Left((res, _)) => res,
+                // Shutdown was requested, cancel the request
+                Either::Right(_) => continue,
+            }
+        };
+
+        match res {
+            Ok(updates) => {
+                st.errors_in_row = 0;
+                st.buffer.extend(updates);
+            }
+            Err(err) => {
+                let retry_after = match &err {
+                    RequestError::RetryAfter(after) => Some(*after),
+                    RequestError::Api(err) if err.kind == ApiError::TerminatedByOtherGetUpdates => {
+                        log::warn!(
+                            "Polling was terminated by other getUpdates request, make sure that \
+                             only one bot instance is running"
+                        );
+                        None
+                    }
+                    _ => None,
+                };
+
+                return Some((Err(err), on_error(st, retry_after)));
+            }
+        }
+    }
+}
+
+/// Resolves when shutdown is requested.
+async fn shutdown_requested(shutdown: &mut watch::Receiver<bool>) {
+    // If all tokens were dropped, shutdown can't be requested anymore
+    if shutdown.changed().await.is_err() {
+        future::pending::<()>().await;
+    }
+}
+
+/// Schedules a delay before the next request.
+fn on_error<R>(mut st: State<R>, retry_after: Option<Duration>) -> State<R> {
+    st.errors_in_row += 1;
+    st.delay =
+        Some(retry_after.unwrap_or_else(|| backoff(st.errors_in_row, st.polling.max_backoff)));
+    st
+}
+
+/// Returns delay after `errors_in_row` consecutive errors.
+fn backoff(errors_in_row: u32, max: Duration) -> Duration {
+    let exp = errors_in_row.saturating_sub(1).min(16);
+    Duration::from_millis(250)
+        .checked_mul(1 << exp)
+        .map_or(max, |delay| delay.min(max))
+}
+
+/// Skips all pending updates, setting the offset after the last one.
+async fn drop_pending_updates<R>(st: &mut State<R>) -> Result<(), RequestError>
+where
+    R: Requester<Err = RequestError>,
+{
+    let updates = st
+        .polling
+        .bot
+        .get_updates()
+        .offset(-1)
+        .limit(1)
+        .timeout(0)
+        .send()
+        .await?;
+
+    if let Some(last) = updates.last() {
+        st.offset = Some(last.id + 1);
+    }
+
+    Ok(())
+}
+
+/// Confirms all updates yielded from the stream.
+async fn acknowledge<R>(st: &State<R>)
+where
+    R: Requester<Err = RequestError>,
+{
+    let offset = match st.offset {
+        Some(offset) => offset,
+        None => return,
+    };
+
+    let res = st
+        .polling
+        .bot
+        .get_updates()
+        .offset(offset)
+        .limit(1)
+        .timeout(0)
+        .send()
+        .await;
+
+    if let Err(err) = res {
+        log::error!("Couldn't acknowledge processed updates: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::polling::backoff;
+
+    #[cfg(feature = "testing")]
+    fn update(id: i32) -> crate::types::Update {
+        use crate::types::{PollAnswer, Update, UpdateKind, User, UserId};
+
+        Update {
+            id,
+            kind: UpdateKind::PollAnswer(PollAnswer {
+                poll_id: "poll".to_owned(),
+                user: User {
+                    id: UserId(1),
+                    is_bot: false,
+                    first_name: "User".to_owned(),
+                    last_name: None,
+                    username: None,
+                    language_code: None,
+                },
+                option_ids: vec![0],
+            }),
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn acknowledges_yielded_updates() {
+        use futures::StreamExt;
+
+        use crate::{payloads::GetUpdates, polling::Polling, testing::MockBot};
+
+        let bot = MockBot::new();
+        bot.respond::<GetUpdates>(Ok(vec![update(1), update(2)]));
+        bot.respond_with::<GetUpdates, _>(|_| Ok(vec![]));
+
+        let (updates, shutdown) = Polling::new(bot.clone())
+            .timeout(Duration::from_secs(5))
+            .limit(10)
+            .into_stream();
+        futures::pin_mut!(updates);
+
+        assert_eq!(updates.next().await.unwrap().unwrap().id, 1);
+        assert_eq!(updates.next().await.unwrap().unwrap().id, 2);
+
+        shutdown.shutdown();
+        assert!(updates.next().await.is_none());
+
+        let requests = bot.requests_of::<GetUpdates>();
+        assert_eq!(requests.len(), 2);
+
+        assert_eq!(requests[0].offset, None);
+        assert_eq!(requests[0].limit, Some(10));
+        assert_eq!(requests[0].timeout, Some(5));
+
+        // Acknowledgement of the last yielded update
+        assert_eq!(requests[1].offset, Some(3));
+        assert_eq!(requests[1].timeout, Some(0));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn errors_are_yielded_and_delay_requests() {
+        use futures::StreamExt;
+        use tokio::time::Instant;
+
+        use crate::{
+            payloads::GetUpdates, polling::Polling, testing::MockBot, ApiError, RequestError,
+        };
+
+        let retry_after = Duration::from_millis(50);
+
+        let bot = MockBot::new();
+        bot.respond::<GetUpdates>(Err(RequestError::RetryAfter(retry_after)));
+        bot.respond::<GetUpdates>(Err(RequestError::Api(
+            ApiError::TerminatedByOtherGetUpdates.into(),
+        )));
+        bot.respond::<GetUpdates>(Ok(vec![update(7)]));
+        bot.respond_with::<GetUpdates, _>(|_| Ok(vec![]));
+
+        let (updates, _shutdown) = Polling::new(bot.clone())
+            .max_backoff(Duration::ZERO)
+            .into_stream();
+        futures::pin_mut!(updates);
+
+        let start = Instant::now();
+        assert!(matches!(
+            updates.next().await,
+            Some(Err(RequestError::RetryAfter(_)))
+        ));
+        assert_eq!(
+            updates.next().await.unwrap().unwrap_err().api_error(),
+            Some(&ApiError::TerminatedByOtherGetUpdates)
+        );
+        assert!(start.elapsed() >= retry_after);
+
+        assert_eq!(updates.next().await.unwrap().unwrap().id, 7);
+
+        // The offset is not advanced by errors
+        let offsets: Vec<_> = bot
+            .requests_of::<GetUpdates>()
+            .iter()
+            .map(|r| r.offset)
+            .collect();
+        assert_eq!(offsets, [None, None, None]);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn drops_pending_updates() {
+        use futures::StreamExt;
+
+        use crate::{payloads::GetUpdates, polling::Polling, testing::MockBot};
+
+        let bot = MockBot::new();
+        bot.respond::<GetUpdates>(Ok(vec![update(41)]));
+        bot.respond::<GetUpdates>(Ok(vec![update(42)]));
+
+        let (updates, shutdown) = Polling::new(bot.clone())
+            .drop_pending_updates()
+            .into_stream();
+        futures::pin_mut!(updates);
+
+        assert_eq!(updates.next().await.unwrap().unwrap().id, 42);
+
+        let requests = bot.requests_of::<GetUpdates>();
+        assert_eq!(requests[0].offset, Some(-1));
+        assert_eq!(requests[1].offset, Some(42));
+
+        bot.respond::<GetUpdates>(Ok(vec![]));
+        shutdown.shutdown();
+        assert!(updates.next().await.is_none());
+        assert_eq!(bot.requests_of::<GetUpdates>()[2].offset, Some(43));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn shutdown_before_updates() {
+        use futures::StreamExt;
+
+        use crate::{polling::Polling, testing::MockBot};
+
+        let bot = MockBot::new();
+        let (updates, shutdown) = Polling::new(bot.clone()).into_stream();
+        futures::pin_mut!(updates);
+
+        shutdown.shutdown();
+        assert!(updates.next().await.is_none());
+
+        // Nothing to acknowledge
+        bot.assert_no_requests();
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let max = Duration::from_secs(5);
+
+        assert_eq!(backoff(1, max), Duration::from_millis(250));
+        assert_eq!(backoff(2, max), Duration::from_millis(500));
+        assert_eq!(backoff(5, max), Duration::from_secs(4));
+        assert_eq!(backoff(6, max), max);
+        assert_eq!(backoff(u32::MAX, max), max);
+    }
+}
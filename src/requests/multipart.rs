@@ -21,6 +21,12 @@ impl<P> MultipartRequest<P> {
     pub const fn new(bot: Bot, payload: P) -> Self {
         Self { bot, payload }
     }
+
+    /// Returns the payload of this request, dropping the bot.
+    #[cfg(any(feature = "testing", feature = "record"))]
+    pub(crate) fn into_payload(self) -> P {
+        self.payload
+    }
 }
 
 impl<P> Request for MultipartRequest<P>
@@ -17,6 +17,27 @@ use crate::adaptors::trace::{Settings, Trace};
 #[cfg(feature = "throttle")]
 use crate::adaptors::throttle::{Limits, Throttle};
 
+#[cfg(feature = "retry")]
+use crate::adaptors::retry::{Retry, RetryPolicy};
+
+#[cfg(feature = "record")]
+use crate::adaptors::Record;
+
+#[cfg(feature = "metrics")]
+use crate::adaptors::metrics::{Metrics, MetricsSink};
+
+#[cfg(feature = "validate")]
+use crate::{adaptors::Validate, errors::ValidationError};
+
+#[cfg(feature = "cache_file_ids")]
+use crate::adaptors::CacheFileIds;
+
+#[cfg(feature = "auto_migrate")]
+use crate::adaptors::AutoMigrate;
+
+#[cfg(any(feature = "record", feature = "metrics"))]
+use crate::RequestError;
+
 /// Extensions methods for [`Requester`].
 pub trait RequesterExt: Requester {
     /// Add `get_me` caching ability, see [`CacheMe`] for more.
@@ -69,6 +90,69 @@ pub trait RequesterExt: Requester {
         Throttle::new_spawn(self, limits)
     }
 
+    /// Retry requests that failed with transient errors, see [`Retry`] for
+    /// more.
+    #[cfg(feature = "retry")]
+    fn retry<P>(self, policy: P) -> Retry<Self, P>
+    where
+        Self: Sized,
+        Self::Err: AsResponseParameters,
+        P: RetryPolicy<Self::Err>,
+    {
+        Retry::new(self, policy)
+    }
+
+    /// Record requests and responses to `cassette`, see [`Record`] for more.
+    #[cfg(feature = "record")]
+    fn record<W>(self, cassette: W) -> Record<Self>
+    where
+        Self: Sized + Requester<Err = RequestError>,
+        W: std::io::Write + Send + 'static,
+    {
+        Record::new(self, cassette)
+    }
+
+    /// Collect metrics of requests, see [`Metrics`] for more.
+    #[cfg(feature = "metrics")]
+    fn metrics<S>(self, sink: S) -> Metrics<Self, S>
+    where
+        Self: Sized + Requester<Err = RequestError>,
+        S: MetricsSink,
+    {
+        Metrics::new(self, sink)
+    }
+
+    /// Check requests against limits of the Bot API before sending them, see
+    /// [`Validate`] for more.
+    #[cfg(feature = "validate")]
+    fn validate(self) -> Validate<Self>
+    where
+        Self: Sized,
+        Self::Err: From<ValidationError>,
+    {
+        Validate::new(self)
+    }
+
+    /// Reuse `file_id`s of already uploaded files, see [`CacheFileIds`] for
+    /// more.
+    #[cfg(feature = "cache_file_ids")]
+    fn cache_file_ids(self) -> CacheFileIds<Self>
+    where
+        Self: Sized,
+    {
+        CacheFileIds::new(self)
+    }
+
+    /// Handle migrations of groups to supergroups, see [`AutoMigrate`] for
+    /// more.
+    #[cfg(feature = "auto_migrate")]
+    fn auto_migrate(self) -> AutoMigrate<Self>
+    where
+        Self: Sized,
+    {
+        AutoMigrate::new(self)
+    }
+
     /// Specifies default [`ParseMode`], which will be used during all calls to:
     ///
     ///  - [`send_message`]
@@ -13,7 +13,7 @@
 mod error;
 mod serializers;
 
-use std::future::Future;
+use std::{collections::HashMap, future::Future};
 
 use reqwest::multipart::Form;
 use serde::Serialize;
@@ -22,64 +22,99 @@ use crate::requests::MultipartPayload;
 use error::Error;
 use serializers::MultipartSerializer;
 
+/// `file://` urls of files on disk by their ids, for files that are sent to a
+/// local Bot API server instead of being uploaded.
+type LocalFiles = HashMap<String, String>;
+
 /// Serializes given value into [`Form`] **taking all input files out**.
 ///
+/// If `local` is `true`, files on disk are sent as `file://` urls. The future
+/// also returns the total size of the attached files (the ones with a known
+/// size).
+///
 /// [`Form`]:  reqwest::multipart::Form
-pub(crate) fn to_form<T>(val: &mut T) -> Result<impl Future<Output = Form>, Error>
+pub(crate) fn to_form<T>(
+    val: &mut T,
+    local: bool,
+) -> Result<impl Future<Output = (Form, u64)>, Error>
 where
     T: Serialize + MultipartPayload,
 {
-    let mut form = val.serialize(MultipartSerializer::new())?;
+    let local_files = local_files(val, local);
+    let mut form = val.serialize(MultipartSerializer::new(&local_files))?;
 
     let mut vec = Vec::with_capacity(1);
     val.move_files(&mut |f| vec.push(f));
     let iter = vec.into_iter();
 
     let fut = async move {
+        let mut total_size = 0;
         for file in iter {
-            if file.needs_attach() {
+            if file.needs_attach() && !local_files.contains_key(file.id()) {
                 let id = file.id().to_owned();
                 if let Some(part) = file.into_part() {
-                    form = form.part(id, part.await);
+                    let (part, size) = part.await;
+                    form = form.part(id, part);
+                    total_size += size.unwrap_or(0);
                 }
             }
         }
 
-        form
+        (form, total_size)
     };
 
     Ok(fut)
 }
 
-/// Serializes given value into [`Form`].
+/// Serializes given value into [`Form`], see [`to_form`].
 ///
 /// [`Form`]:  reqwest::multipart::Form
-pub(crate) fn to_form_ref<T: ?Sized>(val: &T) -> Result<impl Future<Output = Form>, Error>
+pub(crate) fn to_form_ref<T: ?Sized>(
+    val: &T,
+    local: bool,
+) -> Result<impl Future<Output = (Form, u64)>, Error>
 where
     T: Serialize + MultipartPayload,
 {
-    let mut form = val.serialize(MultipartSerializer::new())?;
+    let local_files = local_files(val, local);
+    let mut form = val.serialize(MultipartSerializer::new(&local_files))?;
     let mut vec = Vec::with_capacity(1);
     val.copy_files(&mut |f| vec.push(f));
 
     let iter = vec.into_iter();
 
     let fut = async move {
+        let mut total_size = 0;
         for file in iter {
-            if file.needs_attach() {
+            if file.needs_attach() && !local_files.contains_key(file.id()) {
                 let id = file.id().to_owned();
                 if let Some(part) = file.into_part() {
-                    form = form.part(id, part.await);
+                    let (part, size) = part.await;
+                    form = form.part(id, part);
+                    total_size += size.unwrap_or(0);
                 }
             }
         }
 
-        form
+        (form, total_size)
     };
 
     Ok(fut)
 }
 
+fn local_files<T: ?Sized + MultipartPayload>(val: &T, local: bool) -> LocalFiles {
+    let mut local_files = LocalFiles::new();
+    if local {
+        val.copy_files(&mut |f| {
+            if let Some(url) = f.local_url() {
+                local_files.insert(f.id().to_owned(), url.into());
+            }
+        });
+    }
+
+    local_files
+}
+
 #[cfg(test)]
 mod tests {
     use tokio::fs::File;
@@ -105,6 +140,7 @@ mod tests {
                     length: 0,
                 },
             ]),
+            false,
         )
         .unwrap()
         .await;
@@ -114,48 +150,56 @@ mod tests {
     async fn test_send_media_group() {
         const CAPTION: &str = "caption";
 
-        to_form_ref(&payloads::SendMediaGroup::new(
-            ChatId(0),
-            [
-                InputMedia::Photo(
-                    InputMediaPhoto::new(InputFile::file("./media/logo.png"))
-                        .caption(CAPTION)
-                        .parse_mode(ParseMode::MarkdownV2)
-                        .caption_entities(entities()),
-                ),
-                InputMedia::Video(
-                    InputMediaVideo::new(InputFile::file_id("17")).supports_streaming(true),
-                ),
-                InputMedia::Animation(
-                    InputMediaAnimation::new(InputFile::read(
-                        File::open("./media/example.gif").await.unwrap(),
-                    ))
-                    .thumb(InputFile::read(
-                        File::open("./media/logo.png").await.unwrap(),
-                    ))
-                    .duration(17),
-                ),
-                InputMedia::Audio(
-                    InputMediaAudio::new(InputFile::url("https://example.com".parse().unwrap()))
+        to_form_ref(
+            &payloads::SendMediaGroup::new(
+                ChatId(0),
+                [
+                    InputMedia::Photo(
+                        InputMediaPhoto::new(InputFile::file("./media/logo.png"))
+                            .caption(CAPTION)
+                            .parse_mode(ParseMode::MarkdownV2)
+                            .caption_entities(entities()),
+                    ),
+                    InputMedia::Video(
+                        InputMediaVideo::new(InputFile::file_id("17")).supports_streaming(true),
+                    ),
+                    InputMedia::Animation(
+                        InputMediaAnimation::new(InputFile::read(
+                            File::open("./media/example.gif").await.unwrap(),
+                        ))
+                        .thumb(InputFile::read(
+                            File::open("./media/logo.png").await.unwrap(),
+                        ))
+                        .duration(17),
+                    ),
+                    InputMedia::Audio(
+                        InputMediaAudio::new(InputFile::url(
+                            "https://example.com".parse().unwrap(),
+                        ))
                         .performer("a"),
-                ),
-                InputMedia::Document(InputMediaDocument::new(InputFile::memory(
-                    &b"Hello world!"[..],
-                ))),
-            ],
-        ))
+                    ),
+                    InputMedia::Document(InputMediaDocument::new(InputFile::memory(
+                        &b"Hello world!"[..],
+                    ))),
+                ],
+            ),
+            false,
+        )
         .unwrap()
         .await;
     }
 
     #[tokio::test]
     async fn test_add_sticker_to_set() {
-        to_form_ref(&payloads::AddStickerToSet::new(
-            UserId(0),
-            "name",
-            InputSticker::Png(InputFile::file("./media/logo.png")),
-            "✈️⚙️",
-        ))
+        to_form_ref(
+            &payloads::AddStickerToSet::new(
+                UserId(0),
+                "name",
+                InputSticker::Png(InputFile::file("./media/logo.png")),
+                "✈️⚙️",
+            ),
+            false,
+        )
         .unwrap()
         .await;
     }
@@ -169,6 +213,7 @@ mod tests {
                     File::open("./media/logo.png").await.unwrap(),
                 ))
                 .allow_sending_without_reply(true),
+            false,
         )
         .unwrap()
         .await;
@@ -1,4 +1,4 @@
-use crate::serde_multipart::error::Error;
+use crate::serde_multipart::{error::Error, LocalFiles};
 
 use reqwest::multipart::{Form, Part};
 use serde::{
@@ -7,12 +7,16 @@ use serde::{
 };
 
 /// The main serializer that serializes top-level and structures
-pub(super) struct MultipartSerializer(Form);
+pub(super) struct MultipartSerializer<'a> {
+    form: Form,
+    local_files: &'a LocalFiles,
+}
 
 /// Serializer for maps (support for `#[serde(flatten)]`)
-pub(super) struct MultipartMapSerializer {
+pub(super) struct MultipartMapSerializer<'a> {
     form: Form,
     key: Option<String>,
+    local_files: &'a LocalFiles,
 }
 
 /// Serializer for single "fields" that are serialized as multipart "part"s.
@@ -21,12 +25,18 @@ pub(super) struct MultipartMapSerializer {
 /// - Strings and byte slices are serialized as-is, without any changes
 /// - Structs are serialized with JSON
 /// - C-like enums are serialized as their names
-struct PartSerializer;
+///
+/// `attach://` strings of files from `local_files` are replaced with their
+/// `file://` urls.
+struct PartSerializer<'a> {
+    local_files: &'a LocalFiles,
+}
 
 /// Struct or Seq -> Json -> Part serializer
-struct JsonPartSerializer {
+struct JsonPartSerializer<'a> {
     buf: String,
     state: PartSerializerStructState,
+    local_files: &'a LocalFiles,
 }
 
 /// State for `PartSerializerStruct`
@@ -38,18 +48,21 @@ enum PartSerializerStructState {
     Rest,
 }
 
-impl MultipartSerializer {
-    pub(super) fn new() -> Self {
-        Self(Form::new())
+impl<'a> MultipartSerializer<'a> {
+    pub(super) fn new(local_files: &'a LocalFiles) -> Self {
+        Self {
+            form: Form::new(),
+            local_files,
+        }
     }
 }
 
-impl Serializer for MultipartSerializer {
+impl<'a> Serializer for MultipartSerializer<'a> {
     type Ok = Form;
     type Error = Error;
 
     // for `serde(flatten)` (e.g.: in CreateNewStickerSet)
-    type SerializeMap = MultipartMapSerializer;
+    type SerializeMap = MultipartMapSerializer<'a>;
 
     // The main serializer - struct
     type SerializeStruct = Self;
@@ -65,6 +78,7 @@ impl Serializer for MultipartSerializer {
         Ok(MultipartMapSerializer {
             form: Form::new(),
             key: None,
+            local_files: self.local_files,
         })
     }
 
@@ -224,7 +238,7 @@ impl Serializer for MultipartSerializer {
     }
 }
 
-impl SerializeStruct for MultipartSerializer {
+impl SerializeStruct for MultipartSerializer<'_> {
     type Ok = Form;
     type Error = Error;
 
@@ -236,18 +250,20 @@ impl SerializeStruct for MultipartSerializer {
     where
         T: Serialize,
     {
-        let part = value.serialize(PartSerializer {})?;
-        take_mut::take(&mut self.0, |f| f.part(key, part));
+        let part = value.serialize(PartSerializer {
+            local_files: self.local_files,
+        })?;
+        take_mut::take(&mut self.form, |f| f.part(key, part));
 
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(self.0)
+        Ok(self.form)
     }
 }
 
-impl SerializeMap for MultipartMapSerializer {
+impl SerializeMap for MultipartMapSerializer<'_> {
     type Ok = Form;
     type Error = Error;
 
@@ -271,7 +287,9 @@ impl SerializeMap for MultipartMapSerializer {
             .take()
             .expect("Value serialized before key or key is not string");
 
-        let part = value.serialize(PartSerializer {})?;
+        let part = value.serialize(PartSerializer {
+            local_files: self.local_files,
+        })?;
 
         take_mut::take(&mut self.form, |f| f.part(key, part));
         Ok(())
@@ -282,12 +300,12 @@ impl SerializeMap for MultipartMapSerializer {
     }
 }
 
-impl Serializer for PartSerializer {
+impl<'a> Serializer for PartSerializer<'a> {
     type Ok = Part;
     type Error = Error;
 
-    type SerializeStruct = JsonPartSerializer;
-    type SerializeSeq = JsonPartSerializer;
+    type SerializeStruct = JsonPartSerializer<'a>;
+    type SerializeSeq = JsonPartSerializer<'a>;
 
     // Unimplemented
     type SerializeTuple = Impossible<Self::Ok, Self::Error>;
@@ -345,7 +363,10 @@ impl Serializer for PartSerializer {
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        Ok(Part::text(v.to_owned()))
+        match local_url(self.local_files, v) {
+            Some(url) => Ok(Part::text(url.to_owned())),
+            None => Ok(Part::text(v.to_owned())),
+        }
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
@@ -376,6 +397,7 @@ impl Serializer for PartSerializer {
         Ok(JsonPartSerializer {
             buf: String::new(),
             state: PartSerializerStructState::Empty,
+            local_files: self.local_files,
         })
     }
 
@@ -383,6 +405,7 @@ impl Serializer for PartSerializer {
         Ok(JsonPartSerializer {
             buf: String::new(),
             state: PartSerializerStructState::Empty,
+            local_files: self.local_files,
         })
     }
 
@@ -464,7 +487,7 @@ impl Serializer for PartSerializer {
     }
 }
 
-impl SerializeStruct for JsonPartSerializer {
+impl SerializeStruct for JsonPartSerializer<'_> {
     type Ok = Part;
     type Error = Error;
 
@@ -479,7 +502,7 @@ impl SerializeStruct for JsonPartSerializer {
         use std::fmt::Write;
         use PartSerializerStructState::*;
 
-        let value = serde_json::to_string(value)?;
+        let value = replace_local_files(self.local_files, serde_json::to_string(value)?);
         match self.state {
             Empty => {
                 self.state = Rest;
@@ -506,7 +529,7 @@ impl SerializeStruct for JsonPartSerializer {
     }
 }
 
-impl SerializeSeq for JsonPartSerializer {
+impl SerializeSeq for JsonPartSerializer<'_> {
     type Ok = Part;
 
     type Error = Error;
@@ -518,7 +541,7 @@ impl SerializeSeq for JsonPartSerializer {
         use std::fmt::Write;
         use PartSerializerStructState::*;
 
-        let value = serde_json::to_string(value)?;
+        let value = replace_local_files(self.local_files, serde_json::to_string(value)?);
         match self.state {
             Empty => {
                 self.state = Rest;
@@ -544,3 +567,23 @@ impl SerializeSeq for JsonPartSerializer {
         }
     }
 }
+
+/// Returns the `file://` url of a local file if `value` is its `attach://`
+/// string.
+fn local_url<'a>(local_files: &'a LocalFiles, value: &str) -> Option<&'a str> {
+    let id = value.strip_prefix("attach://")?;
+    local_files.get(id).map(String::as_str)
+}
+
+/// Replaces `attach://` strings of local files in the json with their
+/// `file://` urls.
+fn replace_local_files(local_files: &LocalFiles, mut json: String) -> String {
+    for (id, url) in local_files {
+        let attach = format!("\"attach://{}\"", id);
+        if json.contains(&attach) {
+            json = json.replace(&attach, &serde_json::Value::from(url.as_str()).to_string());
+        }
+    }
+
+    json
+}
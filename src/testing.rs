@@ -0,0 +1,577 @@
+//! Tools for testing code which uses [`Requester`]s.
+//!
+//! See [`MockBot`] for more. For integration tests with a real [`Bot`] see
+//! [`fake_server`].
+//!
+//! [`Requester`]: crate::requests::Requester
+//! [`Bot`]: crate::Bot
+
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    fmt::{self, Debug},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+};
+
+use url::Url;
+
+use crate::{
+    payloads::{self, SendMessage},
+    requests::{HasPayload, Payload, Request, Requester, ResponseResult},
+    types::*,
+    Bot,
+};
+
+pub mod fake_server;
+
+/// A [`Requester`] which doesn't send requests to Telegram, but records them
+/// and returns scripted responses.
+///
+/// Every sent request is recorded (in the order of sending), recorded requests
+/// can be inspected with [`requests`], [`requests_of`] and assertion helpers
+/// like [`assert_one`].
+///
+/// Responses are scripted per method (i.e. per [`Payload::NAME`]):
+/// - [`respond`] queues a response which will be returned once,
+/// - [`respond_with`] sets a function which computes a response when there are
+///   no queued responses left.
+///
+/// Sending a request for which no response is scripted causes a panic.
+///
+/// `MockBot` is cheap to clone, all clones share recorded requests and
+/// scripted responses.
+///
+/// [`Requester`]: crate::requests::Requester
+/// [`requests`]: MockBot::requests
+/// [`requests_of`]: MockBot::requests_of
+/// [`assert_one`]: MockBot::assert_one
+/// [`respond`]: MockBot::respond
+/// [`respond_with`]: MockBot::respond_with
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     payloads::SendMessage, prelude::*, testing::MockBot, types::ChatId, ApiError, RequestError,
+/// };
+///
+/// async fn greet<R: Requester>(bot: R, chat: ChatId) -> Result<(), R::Err> {
+///     bot.send_message(chat, "Hi!").send().await?;
+///     Ok(())
+/// }
+///
+/// # async {
+/// let bot = MockBot::new();
+/// bot.respond::<SendMessage>(Err(RequestError::Api(ApiError::BotBlocked.into())));
+///
+/// assert!(greet(bot.clone(), ChatId(1)).await.is_err());
+/// bot.assert_message_sent(ChatId(1), "Hi!");
+/// # };
+/// ```
+#[derive(Clone, Default)]
+pub struct MockBot {
+    state: Arc<Mutex<State>>,
+}
+
+/// A request recorded by [`MockBot`].
+#[derive(Clone)]
+pub struct RecordedRequest {
+    name: &'static str,
+    payload: Arc<dyn Any + Send + Sync>,
+    debug: String,
+}
+
+/// Request returned by [`MockBot`] methods.
+#[must_use = "Requests are lazy and do nothing unless sent"]
+#[derive(Clone)]
+pub struct MockRequest<P> {
+    bot: MockBot,
+    payload: P,
+}
+
+/// Future returned by [`MockRequest`]s.
+#[must_use = "Futures are lazy and do nothing unless polled or awaited"]
+pub struct MockSend<P> {
+    inner: Option<(MockBot, P)>,
+}
+
+// Responders are called without holding the lock of the state, so that they can
+// inspect the bot
+type Responder = Arc<Mutex<dyn FnMut(&(dyn Any + Send + Sync)) -> Box<dyn Any + Send> + Send>>;
+
+#[derive(Default)]
+struct State {
+    requests: Vec<RecordedRequest>,
+    // Values are `Box<ResponseResult<P::Output>>` where `P::NAME` is the key
+    queued: HashMap<&'static str, VecDeque<Box<dyn Any + Send>>>,
+    responders: HashMap<&'static str, Responder>,
+}
+
+impl MockBot {
+    /// Creates new mock bot without any scripted responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a response to a request of type `P`.
+    ///
+    /// Queued responses are returned in the order they were queued, before
+    /// falling back to the function set by [`respond_with`].
+    ///
+    /// [`respond_with`]: MockBot::respond_with
+    pub fn respond<P>(&self, response: ResponseResult<P::Output>)
+    where
+        P: Payload,
+        P::Output: Send + 'static,
+    {
+        self.state()
+            .queued
+            .entry(P::NAME)
+            .or_default()
+            .push_back(Box::new(response));
+    }
+
+    /// Sets a function which computes responses to requests of type `P`.
+    pub fn respond_with<P, F>(&self, mut f: F)
+    where
+        P: Payload + 'static,
+        P::Output: Send + 'static,
+        F: FnMut(&P) -> ResponseResult<P::Output> + Send + 'static,
+    {
+        let responder: Responder =
+            Arc::new(Mutex::new(move |payload: &(dyn Any + Send + Sync)| {
+                let payload = payload
+                    .downcast_ref::<P>()
+                    .expect("payload type must match its name");
+                Box::new(f(payload)) as Box<dyn Any + Send>
+            }));
+
+        self.state().responders.insert(P::NAME, responder);
+    }
+
+    /// Returns all requests sent so far, in the order they were sent.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Returns payloads of all requests of type `P` sent so far, in the order
+    /// they were sent.
+    pub fn requests_of<P>(&self) -> Vec<P>
+    where
+        P: Payload + Clone + 'static,
+    {
+        self.state()
+            .requests
+            .iter()
+            .filter_map(RecordedRequest::payload::<P>)
+            .cloned()
+            .collect()
+    }
+
+    /// Forgets all recorded requests.
+    ///
+    /// Scripted responses are not affected.
+    pub fn clear(&self) {
+        self.state().requests.clear();
+    }
+
+    /// Asserts that exactly one request of type `P` matching `predicate` was
+    /// sent and returns its payload.
+    ///
+    /// ## Panics
+    ///
+    /// If there are no or more than one such requests.
+    #[track_caller]
+    pub fn assert_one<P, F>(&self, predicate: F) -> P
+    where
+        P: Payload + Clone + 'static,
+        F: Fn(&P) -> bool,
+    {
+        let mut matching: Vec<_> = self
+            .requests_of::<P>()
+            .into_iter()
+            .filter(|p| predicate(p))
+            .collect();
+
+        match matching.len() {
+            1 => matching.pop().unwrap(),
+            n => panic!(
+                "expected exactly one matching `{}` request, found {}; all requests: {:#?}",
+                P::NAME,
+                n,
+                self.requests()
+            ),
+        }
+    }
+
+    /// Asserts that exactly one [`SendMessage`] request with `text` was sent
+    /// to `chat_id`.
+    ///
+    /// ## Panics
+    ///
+    /// If there are no or more than one such requests.
+    #[track_caller]
+    pub fn assert_message_sent<C>(&self, chat_id: C, text: &str) -> SendMessage
+    where
+        C: Into<Recipient>,
+    {
+        let chat_id = chat_id.into();
+        self.assert_one::<SendMessage, _>(|p| p.chat_id == chat_id && p.text == text)
+    }
+
+    /// Asserts that no requests were sent.
+    ///
+    /// ## Panics
+    ///
+    /// If any request was sent.
+    #[track_caller]
+    pub fn assert_no_requests(&self) {
+        let requests = self.requests();
+        assert!(
+            requests.is_empty(),
+            "expected no requests, found: {:#?}",
+            requests
+        );
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // We never panic while holding the lock
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn execute<P>(&self, payload: P) -> ResponseResult<P::Output>
+    where
+        P: Payload + Debug + Send + Sync + 'static,
+        P::Output: 'static,
+    {
+        let mut state = self.state();
+
+        let payload = Arc::new(payload);
+        state.requests.push(RecordedRequest {
+            name: P::NAME,
+            debug: format!("{:?}", payload),
+            payload: payload.clone(),
+        });
+
+        let queued = state.queued.get_mut(P::NAME).and_then(VecDeque::pop_front);
+        let response = match queued {
+            Some(response) => response,
+            None => match state.responders.get(P::NAME).cloned() {
+                Some(responder) => {
+                    drop(state);
+
+                    // Panics in responders fail the test anyway
+                    let mut responder = responder.lock().unwrap_or_else(|err| err.into_inner());
+                    responder(&*payload)
+                }
+                None => {
+                    drop(state);
+                    panic!("no response is scripted for `{}` request", P::NAME)
+                }
+            },
+        };
+
+        *response
+            .downcast::<ResponseResult<P::Output>>()
+            .expect("response type must match payload name")
+    }
+}
+
+impl Debug for MockBot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockBot")
+            .field("requests", &self.state().requests)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RecordedRequest {
+    /// Returns the name of the method, i.e. [`Payload::NAME`].
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the payload of this request if it's of type `P`.
+    pub fn payload<P>(&self) -> Option<&P>
+    where
+        P: Payload + 'static,
+    {
+        self.payload.downcast_ref()
+    }
+}
+
+impl Debug for RecordedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.debug)
+    }
+}
+
+macro_rules! f {
+    ($m:ident $this:ident ($($arg:ident : $T:ty),*)) => {
+        MockRequest::new(
+            $this.clone(),
+            Bot::payload_builder().$m($($arg),*).into_payload(),
+        )
+    };
+}
+
+macro_rules! fty {
+    ($T:ident) => {
+        MockRequest<payloads::$T>
+    };
+}
+
+impl Requester for MockBot {
+    type Err = crate::RequestError;
+
+    requester_forward! {
+        get_updates,
+        set_webhook,
+        delete_webhook,
+        get_webhook_info,
+        get_me,
+        log_out,
+        close,
+        send_message,
+        forward_message,
+        copy_message,
+        send_photo,
+        send_audio,
+        send_document,
+        send_video,
+        send_animation,
+        send_voice,
+        send_video_note,
+        send_media_group,
+        send_location,
+        edit_message_live_location,
+        edit_message_live_location_inline,
+        stop_message_live_location,
+        stop_message_live_location_inline,
+        send_venue,
+        send_contact,
+        send_poll,
+        send_dice,
+        send_chat_action,
+        get_user_profile_photos,
+        get_file,
+        ban_chat_member,
+        kick_chat_member,
+        unban_chat_member,
+        restrict_chat_member,
+        promote_chat_member,
+        set_chat_administrator_custom_title,
+        ban_chat_sender_chat,
+        unban_chat_sender_chat,
+        set_chat_permissions,
+        export_chat_invite_link,
+        create_chat_invite_link,
+        edit_chat_invite_link,
+        revoke_chat_invite_link,
+        approve_chat_join_request,
+        decline_chat_join_request,
+        set_chat_photo,
+        delete_chat_photo,
+        set_chat_title,
+        set_chat_description,
+        pin_chat_message,
+        unpin_chat_message,
+        unpin_all_chat_messages,
+        leave_chat,
+        get_chat,
+        get_chat_administrators,
+        get_chat_member_count,
+        get_chat_members_count,
+        get_chat_member,
+        set_chat_sticker_set,
+        delete_chat_sticker_set,
+        answer_callback_query,
+        set_my_commands,
+        get_my_commands,
+        set_chat_menu_button,
+        get_chat_menu_button,
+        set_my_default_administrator_rights,
+        get_my_default_administrator_rights,
+        delete_my_commands,
+        answer_inline_query,
+        answer_web_app_query,
+        edit_message_text,
+        edit_message_text_inline,
+        edit_message_caption,
+        edit_message_caption_inline,
+        edit_message_media,
+        edit_message_media_inline,
+        edit_message_reply_markup,
+        edit_message_reply_markup_inline,
+        stop_poll,
+        delete_message,
+        send_sticker,
+        get_sticker_set,
+        upload_sticker_file,
+        create_new_sticker_set,
+        add_sticker_to_set,
+        set_sticker_position_in_set,
+        delete_sticker_from_set,
+        set_sticker_set_thumb,
+        send_invoice,
+        answer_shipping_query,
+        answer_pre_checkout_query,
+        set_passport_data_errors,
+        send_game,
+        set_game_score,
+        set_game_score_inline,
+        get_game_high_scores
+        => f, fty
+    }
+}
+
+impl<P> MockRequest<P> {
+    pub(crate) fn new(bot: MockBot, payload: P) -> Self {
+        Self { bot, payload }
+    }
+}
+
+impl<P> HasPayload for MockRequest<P>
+where
+    P: Payload,
+{
+    type Payload = P;
+
+    fn payload_mut(&mut self) -> &mut Self::Payload {
+        &mut self.payload
+    }
+
+    fn payload_ref(&self) -> &Self::Payload {
+        &self.payload
+    }
+}
+
+impl<P> Request for MockRequest<P>
+where
+    P: Payload + Clone + Debug + Send + Sync + 'static,
+    P::Output: Send + 'static,
+{
+    type Err = crate::RequestError;
+    type Send = MockSend<P>;
+    type SendRef = MockSend<P>;
+
+    fn send(self) -> Self::Send {
+        MockSend {
+            inner: Some((self.bot, self.payload)),
+        }
+    }
+
+    fn send_ref(&self) -> Self::SendRef {
+        MockSend {
+            inner: Some((self.bot.clone(), self.payload.clone())),
+        }
+    }
+}
+
+impl<P> Future for MockSend<P>
+where
+    P: Payload + Debug + Send + Sync + 'static,
+    P::Output: 'static,
+{
+    type Output = ResponseResult<P::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        let (bot, payload) = self
+            .inner
+            .take()
+            .expect("`MockSend` polled after completion");
+
+        Poll::Ready(bot.execute(payload))
+    }
+}
+
+// `MockSend` doesn't pin its fields
+impl<P> Unpin for MockSend<P> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        payloads::{GetMe, SendMessage, SendMessageSetters},
+        requests::{Request, Requester},
+        testing::MockBot,
+        types::{ChatId, Me, ParseMode, User, UserId},
+        ApiError, RequestError,
+    };
+
+    fn me() -> Me {
+        Me {
+            user: User {
+                id: UserId(42),
+                is_bot: true,
+                first_name: "Bot".to_owned(),
+                last_name: None,
+                username: Some("bot".to_owned()),
+                language_code: None,
+            },
+            can_join_groups: false,
+            can_read_all_group_messages: false,
+            supports_inline_queries: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn records_requests_in_order() {
+        let bot = MockBot::new();
+        bot.respond_with::<GetMe, _>(|_| Ok(me()));
+        bot.respond_with::<SendMessage, _>(|_| Err(RequestError::Api(ApiError::BotBlocked.into())));
+
+        bot.get_me().send().await.unwrap();
+        bot.send_message(ChatId(1), "a")
+            .parse_mode(ParseMode::Html)
+            .send()
+            .await
+            .unwrap_err();
+        bot.send_message(ChatId(2), "b").send().await.unwrap_err();
+
+        let names: Vec<_> = bot.requests().iter().map(|r| r.name()).collect();
+        assert_eq!(names, ["GetMe", "SendMessage", "SendMessage"]);
+
+        let sent = bot.assert_message_sent(ChatId(1), "a");
+        assert_eq!(sent.parse_mode, Some(ParseMode::Html));
+        assert_eq!(bot.requests_of::<SendMessage>().len(), 2);
+
+        bot.clear();
+        bot.assert_no_requests();
+    }
+
+    #[tokio::test]
+    async fn queued_responses_come_first() {
+        let bot = MockBot::new();
+        bot.respond::<GetMe>(Err(RequestError::Api(ApiError::NotFound.into())));
+        bot.respond_with::<GetMe, _>(|_| Ok(me()));
+
+        let req = bot.get_me();
+        assert_eq!(
+            req.send_ref().await.unwrap_err().api_error(),
+            Some(&ApiError::NotFound)
+        );
+        assert_eq!(req.send_ref().await.unwrap().user.id, UserId(42));
+        assert_eq!(req.send().await.unwrap().user.id, UserId(42));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "no response is scripted for `GetMe` request")]
+    async fn unscripted_request_panics() {
+        let _ = MockBot::new().get_me().send().await;
+    }
+
+    #[tokio::test]
+    async fn responders_can_inspect_the_bot() {
+        let bot = MockBot::new();
+        let inner = bot.clone();
+        bot.respond_with::<GetMe, _>(move |_| {
+            assert_eq!(inner.requests_of::<GetMe>().len(), 1);
+            Ok(me())
+        });
+
+        bot.get_me().send().await.unwrap();
+    }
+}
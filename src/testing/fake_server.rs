@@ -0,0 +1,899 @@
+//! A fake Telegram Bot API server for integration tests.
+//!
+//! See [`FakeServer`] for more.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use hyper::{
+    body,
+    header::{HeaderValue, CONTENT_TYPE, RANGE},
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Map, Value};
+use tokio::sync::{watch, Notify};
+
+use crate::{errors::ApiErrorResponse, types::ChatId, ApiError, Bot};
+
+/// The token accepted by [`FakeServer`] by default.
+pub const FAKE_TOKEN: &str = "1234567:FAKE_TOKEN";
+
+/// A fake Telegram Bot API server listening on localhost.
+///
+/// The server serves `/bot<token>/<method>` and `/file/bot<token>/<path>`
+/// routes, so a real [`Bot`] (see [`FakeServer::bot`]) can be used with it,
+/// including file uploads and downloads.
+///
+/// The server keeps a small in-memory state:
+/// - chats (added with [`add_private_chat`] and [`add_group`]),
+/// - messages, with incrementing ids,
+/// - files, uploaded by the bot or added with [`add_file`],
+/// - updates, which are returned from `getUpdates` (added with
+///   [`push_update`]).
+///
+/// Supported methods are `getMe`, `getUpdates`, `getChat`, `sendMessage`,
+/// `editMessageText`, `deleteMessage`, `sendPhoto`, `sendDocument`,
+/// `sendChatAction` and `getFile`, other methods return `Not Found` error.
+///
+/// Errors can be injected using [`fail_next`].
+///
+/// [`add_private_chat`]: FakeServer::add_private_chat
+/// [`add_group`]: FakeServer::add_group
+/// [`add_file`]: FakeServer::add_file
+/// [`push_update`]: FakeServer::push_update
+/// [`fail_next`]: FakeServer::fail_next
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     prelude::*,
+///     testing::fake_server::{FakeError, FakeServer},
+///     types::ChatId,
+///     ApiError,
+/// };
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let server = FakeServer::start().unwrap();
+/// server.add_private_chat(ChatId(1), "Alice");
+/// server.fail_next("SendMessage", FakeError::Api(ApiError::BotBlocked));
+///
+/// let bot = server.bot();
+/// let res = bot.send_message(ChatId(1), "hi").send().await;
+/// assert_eq!(res.unwrap_err().api_error(), Some(&ApiError::BotBlocked));
+///
+/// let message = bot.send_message(ChatId(1), "hi").send().await.unwrap();
+/// assert_eq!(message.text(), Some("hi"));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FakeServer {
+    local_addr: SocketAddr,
+    token: Arc<str>,
+    state: Arc<Mutex<State>>,
+    new_update: Arc<Notify>,
+    shutdown: Arc<watch::Sender<bool>>,
+}
+
+/// An error injected with [`FakeServer::fail_next`].
+#[derive(Debug, Clone)]
+pub enum FakeError {
+    /// API error, the `error_code` is deduced from the description.
+    Api(ApiError),
+
+    /// Flood control error with `retry_after` parameter (in seconds).
+    RetryAfter(u32),
+
+    /// Error with `migrate_to_chat_id` parameter.
+    MigrateToChatId(i64),
+
+    /// An error with arbitrary `error_code` and `description`.
+    Raw {
+        error_code: u16,
+        description: String,
+    },
+}
+
+/// A request received by [`FakeServer`].
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    /// Name of the method as it was in the url.
+    pub method: String,
+
+    /// Parameters of the request.
+    ///
+    /// Note that parameters sent using `multipart/form-data` are all strings.
+    pub params: Map<String, Value>,
+
+    /// Files attached to the request (keyed by the name of the part).
+    pub files: HashMap<String, Bytes>,
+}
+
+#[derive(Default)]
+struct State {
+    chats: HashMap<i64, Value>,
+    messages: HashMap<(i64, i64), Value>,
+    last_message_id: i64,
+    files: HashMap<String, StoredFile>,
+    last_file_id: u64,
+    updates: VecDeque<Value>,
+    errors: HashMap<String, VecDeque<FakeError>>,
+    requests: Vec<ReceivedRequest>,
+}
+
+struct StoredFile {
+    unique_id: String,
+    path: String,
+    data: Bytes,
+}
+
+type ApiResult = Result<Value, FakeError>;
+
+impl FakeServer {
+    /// Starts the server on a random port (spawning it with `tokio::spawn`).
+    ///
+    /// The server is stopped when [`shutdown`](Self::shutdown) is called or
+    /// all its clones are dropped.
+    pub fn start() -> Result<Self, hyper::Error> {
+        Self::with_token(FAKE_TOKEN)
+    }
+
+    /// Starts the server which accepts `token`.
+    pub fn with_token(token: &str) -> Result<Self, hyper::Error> {
+        let builder = Server::try_bind(&([127, 0, 0, 1], 0).into())?;
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+
+        let token: Arc<str> = token.into();
+        let state = Arc::new(Mutex::new(State::default()));
+        let new_update = Arc::new(Notify::new());
+
+        let handler = Handler {
+            token: Arc::clone(&token),
+            state: Arc::clone(&state),
+            new_update: Arc::clone(&new_update),
+        };
+        let make_service = make_service_fn(move |_| {
+            let handler = handler.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handler.clone().handle(req))) }
+        });
+
+        let server = builder.serve(make_service);
+        let local_addr = server.local_addr();
+        let server = server.with_graceful_shutdown(async move {
+            // Error means that all clones of the `FakeServer` were dropped
+            while shutdown_rx.changed().await.is_ok() {
+                if *shutdown_rx.borrow() {
+                    return;
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                log::error!("Fake server error: {}", err);
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            token,
+            state,
+            new_update,
+            shutdown: Arc::new(shutdown_tx),
+        })
+    }
+
+    /// Returns the url of the server which can be passed to
+    /// [`Bot::set_api_url`].
+    pub fn url(&self) -> reqwest::Url {
+        reqwest::Url::parse(&format!("http://{}/", self.local_addr)).expect("valid url")
+    }
+
+    /// Returns a [`Bot`] which sends requests to this server.
+    pub fn bot(&self) -> Bot {
+        Bot::new(&*self.token).set_api_url(self.url())
+    }
+
+    /// Stops the server.
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.send(true);
+    }
+
+    /// Adds a private chat.
+    pub fn add_private_chat(&self, id: ChatId, first_name: &str) {
+        let chat = json!({ "id": id.0, "type": "private", "first_name": first_name });
+        self.state().chats.insert(id.0, chat);
+    }
+
+    /// Adds a group chat (a supergroup if the `id` is a supergroup id).
+    pub fn add_group(&self, id: ChatId, title: &str) {
+        let kind = match id.is_channel_or_supergroup() {
+            true => "supergroup",
+            false => "group",
+        };
+        let chat = json!({ "id": id.0, "type": kind, "title": title });
+        self.state().chats.insert(id.0, chat);
+    }
+
+    /// Adds a file which can be downloaded by the bot, returns its `file_id`.
+    pub fn add_file(&self, data: impl Into<Bytes>) -> String {
+        self.state().store_file(data.into(), "documents")
+    }
+
+    /// Returns the contents of a file uploaded by the bot (or added with
+    /// [`add_file`](Self::add_file)).
+    pub fn file(&self, file_id: &str) -> Option<Bytes> {
+        self.state().files.get(file_id).map(|f| f.data.clone())
+    }
+
+    /// Returns a message sent by the bot, if it wasn't deleted.
+    pub fn message(&self, chat_id: ChatId, message_id: i32) -> Option<Value> {
+        let key = (chat_id.0, i64::from(message_id));
+        self.state().messages.get(&key).cloned()
+    }
+
+    /// Adds an update which will be returned from `getUpdates`.
+    ///
+    /// `update_id` is set automatically if it's not present.
+    pub fn push_update(&self, mut update: Value) {
+        let mut state = self.state();
+        if update.get("update_id").is_none() {
+            let last = state.updates.back().and_then(|u| u["update_id"].as_i64());
+            update["update_id"] = last.map_or(1, |id| id + 1).into();
+        }
+        state.updates.push_back(update);
+        drop(state);
+
+        self.new_update.notify_waiters();
+    }
+
+    /// Makes the next request to `method` fail with `error`.
+    ///
+    /// `method` is case insensitive. If called multiple times, errors are
+    /// returned in the order they were injected.
+    pub fn fail_next(&self, method: &str, error: FakeError) {
+        self.state()
+            .errors
+            .entry(method.to_lowercase())
+            .or_default()
+            .push_back(error);
+    }
+
+    /// Returns all requests received so far, in the order they were received.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state().requests.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+#[derive(Clone)]
+struct Handler {
+    token: Arc<str>,
+    state: Arc<Mutex<State>>,
+    new_update: Arc<Notify>,
+}
+
+impl Handler {
+    async fn handle(self, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let path = req.uri().path().to_owned();
+
+        if let Some(rest) = path.strip_prefix("/file/bot") {
+            let range = req.headers().get(RANGE).cloned();
+            return Ok(self.download(rest, range));
+        }
+
+        let (token, method) = match path.strip_prefix("/bot").and_then(|r| r.split_once('/')) {
+            Some(it) => it,
+            None => return Ok(error_response(&FakeError::not_found())),
+        };
+
+        if token != &*self.token {
+            return Ok(error_response(&FakeError::Raw {
+                error_code: 401,
+                description: "Unauthorized".to_owned(),
+            }));
+        }
+
+        let request = match parse_request(method.to_owned(), req).await {
+            Some(request) => request,
+            None => {
+                return Ok(error_response(&FakeError::bad_request(
+                    "can't parse request",
+                )))
+            }
+        };
+
+        let res = self.call(request).await;
+        Ok(match res {
+            Ok(result) => json_response(StatusCode::OK, &json!({ "ok": true, "result": result })),
+            Err(err) => error_response(&err),
+        })
+    }
+
+    fn download(&self, rest: &str, range: Option<HeaderValue>) -> Response<Body> {
+        let file = rest
+            .split_once('/')
+            .filter(|(token, _)| *token == &*self.token)
+            .and_then(|(_, path)| {
+                let state = lock(&self.state);
+                let file = state.files.values().find(|f| f.path == path);
+                file.map(|f| f.data.clone())
+            });
+
+        // Only `bytes={start}-` ranges are supported
+        let start = range.as_ref().and_then(|range| {
+            let range = range.to_str().ok()?.strip_prefix("bytes=")?;
+            range.strip_suffix('-')?.parse::<usize>().ok()
+        });
+
+        match (file, start) {
+            (Some(data), Some(start)) if start <= data.len() => {
+                let mut response = Response::new(Body::from(data.slice(start..)));
+                *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+                response
+            }
+            (Some(data), _) => Response::new(Body::from(data)),
+            (None, _) => {
+                let mut response = Response::new(Body::empty());
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                response
+            }
+        }
+    }
+
+    async fn call(&self, request: ReceivedRequest) -> ApiResult {
+        let method = request.method.to_lowercase();
+
+        {
+            let mut state = lock(&self.state);
+            state.requests.push(request.clone());
+
+            if let Some(err) = state.errors.get_mut(&method).and_then(VecDeque::pop_front) {
+                return Err(err);
+            }
+        }
+
+        if method == "getupdates" {
+            return self.get_updates(&request).await;
+        }
+
+        let mut state = lock(&self.state);
+        let p = &request.params;
+        match &*method {
+            "getme" => Ok(json!({
+                "id": 1234567,
+                "is_bot": true,
+                "first_name": "Fake",
+                "username": "fake_bot",
+                "can_join_groups": true,
+                "can_read_all_group_messages": false,
+                "supports_inline_queries": false,
+            })),
+            "getchat" => state.chat(p).cloned(),
+            "sendchataction" => state.chat(p).map(|_| Value::Bool(true)),
+            "sendmessage" => {
+                let text = str_param(p, "text").unwrap_or_default();
+                check_text(&text)?;
+
+                let mut fields = Map::new();
+                fields.insert("text".to_owned(), text.into());
+                if let Some(entities) = p.get("entities") {
+                    fields.insert("entities".to_owned(), json_param(entities));
+                }
+                state.send(p, fields)
+            }
+            "editmessagetext" => {
+                let text = str_param(p, "text").unwrap_or_default();
+                check_text(&text)?;
+
+                let message = state.message_mut(p, "message to edit not found")?;
+                if message["text"] == text {
+                    return Err(FakeError::Api(ApiError::MessageNotModified));
+                }
+                message["text"] = text.into();
+                message["edit_date"] = now().into();
+                Ok(message.clone())
+            }
+            "deletemessage" => {
+                let message = state.message_mut(p, "message to delete not found")?;
+                let key = (
+                    message["chat"]["id"].as_i64().unwrap_or_default(),
+                    message["message_id"].as_i64().unwrap_or_default(),
+                );
+                state.messages.remove(&key);
+                Ok(Value::Bool(true))
+            }
+            "sendphoto" | "senddocument" => {
+                let field = match &*method {
+                    "sendphoto" => "photo",
+                    _ => "document",
+                };
+
+                state.chat(p)?;
+                let file_id = state.input_file(&request, field)?;
+                let file = &state.files[&file_id];
+                let meta = json!({
+                    "file_id": file_id,
+                    "file_unique_id": file.unique_id,
+                    "file_size": file.data.len(),
+                });
+
+                let mut fields = Map::new();
+                match field {
+                    "photo" => {
+                        let mut size = meta;
+                        size["width"] = 1.into();
+                        size["height"] = 1.into();
+                        fields.insert("photo".to_owned(), json!([size]));
+                    }
+                    _ => {
+                        fields.insert("document".to_owned(), meta);
+                    }
+                }
+                if let Some(caption) = str_param(p, "caption") {
+                    fields.insert("caption".to_owned(), caption.into());
+                }
+                state.send(p, fields)
+            }
+            "getfile" => {
+                let file_id = str_param(p, "file_id").unwrap_or_default();
+                let file = state
+                    .files
+                    .get(&file_id)
+                    .ok_or_else(|| FakeError::bad_request("invalid file_id"))?;
+
+                Ok(json!({
+                    "file_id": file_id,
+                    "file_unique_id": file.unique_id,
+                    "file_size": file.data.len(),
+                    "file_path": file.path,
+                }))
+            }
+            _ => Err(FakeError::not_found()),
+        }
+    }
+
+    async fn get_updates(&self, request: &ReceivedRequest) -> ApiResult {
+        let p = &request.params;
+        let offset = int_param(p, "offset").unwrap_or(0);
+        let limit = int_param(p, "limit").unwrap_or(100) as usize;
+        let timeout = Duration::from_secs(int_param(p, "timeout").unwrap_or(0) as u64);
+
+        let take = |state: &mut State| {
+            // Updates with ids lower than `offset` are confirmed
+            state
+                .updates
+                .retain(|u| u["update_id"].as_i64().unwrap_or(0) >= offset);
+            state
+                .updates
+                .iter()
+                .take(limit)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let notified = self.new_update.notified();
+        let updates = take(&mut lock(&self.state));
+        if !updates.is_empty() || timeout.is_zero() {
+            return Ok(updates.into());
+        }
+
+        // Long polling: wait for an update or a timeout
+        let _ = tokio::time::timeout(timeout, notified).await;
+        Ok(take(&mut lock(&self.state)).into())
+    }
+}
+
+impl State {
+    fn chat(&self, p: &Map<String, Value>) -> Result<&Value, FakeError> {
+        int_param(p, "chat_id")
+            .and_then(|id| self.chats.get(&id))
+            .ok_or(FakeError::Api(ApiError::ChatNotFound))
+    }
+
+    fn message_mut(
+        &mut self,
+        p: &Map<String, Value>,
+        not_found: &str,
+    ) -> Result<&mut Value, FakeError> {
+        self.chat(p)?;
+
+        let chat_id = int_param(p, "chat_id");
+        let message_id = int_param(p, "message_id");
+        chat_id
+            .zip(message_id)
+            .and_then(move |key| self.messages.get_mut(&key))
+            .ok_or_else(|| FakeError::bad_request(not_found))
+    }
+
+    fn send(&mut self, p: &Map<String, Value>, fields: Map<String, Value>) -> ApiResult {
+        let chat = self.chat(p)?.clone();
+        let chat_id = chat["id"].as_i64().unwrap();
+
+        self.last_message_id += 1;
+        let mut message = json!({
+            "message_id": self.last_message_id,
+            "date": now(),
+            "chat": chat,
+        });
+        message.as_object_mut().unwrap().extend(fields);
+        if let Some(markup) = p.get("reply_markup") {
+            message["reply_markup"] = json_param(markup);
+        }
+
+        self.messages
+            .insert((chat_id, self.last_message_id), message.clone());
+        Ok(message)
+    }
+
+    /// Resolves an `InputFile` parameter, storing uploaded file. Returns its
+    /// `file_id`.
+    fn input_file(&mut self, request: &ReceivedRequest, field: &str) -> Result<String, FakeError> {
+        let value = str_param(&request.params, field).unwrap_or_default();
+
+        match value.strip_prefix("attach://") {
+            Some(name) => {
+                let data = request
+                    .files
+                    .get(name)
+                    .ok_or_else(|| FakeError::bad_request("file must be non-empty"))?;
+                Ok(self.store_file(data.clone(), &format!("{}s", field)))
+            }
+            None if self.files.contains_key(&value) => Ok(value),
+            None => Err(FakeError::Api(ApiError::WrongFileIdOrUrl)),
+        }
+    }
+
+    fn store_file(&mut self, data: Bytes, dir: &str) -> String {
+        self.last_file_id += 1;
+        let n = self.last_file_id;
+
+        let file_id = format!("FAKE_FILE_ID_{}", n);
+        let file = StoredFile {
+            unique_id: format!("FAKE_UNIQUE_ID_{}", n),
+            path: format!("{}/file_{}", dir, n),
+            data,
+        };
+        self.files.insert(file_id.clone(), file);
+        file_id
+    }
+}
+
+impl FakeError {
+    fn not_found() -> Self {
+        Self::Raw {
+            error_code: 404,
+            description: "Not Found".to_owned(),
+        }
+    }
+
+    fn bad_request(description: &str) -> Self {
+        Self::Raw {
+            error_code: 400,
+            description: format!("Bad Request: {}", description),
+        }
+    }
+
+    fn to_json(&self) -> (u16, Value) {
+        match self {
+            FakeError::Api(err) => {
+                let err = ApiErrorResponse::from(err.clone());
+                (err.error_code, json!({ "description": err.description }))
+            }
+            FakeError::RetryAfter(secs) => (
+                429,
+                json!({
+                    "description": format!("Too Many Requests: retry after {}", secs),
+                    "parameters": { "retry_after": secs },
+                }),
+            ),
+            FakeError::MigrateToChatId(id) => (
+                400,
+                json!({
+                    "description": "Bad Request: group chat was upgraded to a supergroup chat",
+                    "parameters": { "migrate_to_chat_id": id },
+                }),
+            ),
+            FakeError::Raw {
+                error_code,
+                description,
+            } => (*error_code, json!({ "description": description })),
+        }
+    }
+}
+
+async fn parse_request(method: String, req: Request<Body>) -> Option<ReceivedRequest> {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let body = body::to_bytes(req.into_body()).await.ok()?;
+
+    let mut request = ReceivedRequest {
+        method,
+        params: Map::new(),
+        files: HashMap::new(),
+    };
+
+    if let Some(boundary) = content_type
+        .strip_prefix("multipart/form-data")
+        .and_then(|rest| rest.split("boundary=").nth(1))
+    {
+        for part in multipart_parts(&body, boundary.trim_matches('"'))? {
+            match part.file_name {
+                Some(_) => request.files.insert(part.name, part.data),
+                None => {
+                    let text = String::from_utf8(part.data.to_vec()).ok()?;
+                    request.params.insert(part.name, text.into());
+                    None
+                }
+            };
+        }
+    } else if !body.is_empty() {
+        request.params = serde_json::from_slice(&body).ok()?;
+    }
+
+    Some(request)
+}
+
+struct MultipartPart {
+    name: String,
+    file_name: Option<String>,
+    data: Bytes,
+}
+
+/// A minimal `multipart/form-data` parser, just enough for requests sent by
+/// [`Bot`].
+fn multipart_parts(body: &Bytes, boundary: &str) -> Option<Vec<MultipartPart>> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+
+    let mut rest = &body[..];
+    rest = &rest[find(rest, delimiter.as_bytes())? + delimiter.len()..];
+
+    // The last delimiter is followed by `--`
+    while !rest.starts_with(b"--") {
+        rest = rest.strip_prefix(b"\r\n")?;
+        let end = find(rest, format!("\r\n{}", delimiter).as_bytes())?;
+        let (part, tail) = rest.split_at(end);
+        rest = &tail[2 + delimiter.len()..];
+
+        let headers_end = find(part, b"\r\n\r\n")?;
+        let headers = std::str::from_utf8(&part[..headers_end]).ok()?;
+        let data = body.slice_ref(&part[headers_end + 4..]);
+
+        let disposition = headers
+            .split("\r\n")
+            .find(|h| h.to_lowercase().starts_with("content-disposition"))?;
+        let param = |key: &str| {
+            disposition
+                .split(';')
+                .map(str::trim)
+                .find_map(|kv| kv.strip_prefix(key)?.strip_prefix('='))
+                .map(|v| v.trim_matches('"').to_owned())
+        };
+
+        parts.push(MultipartPart {
+            name: param("name")?,
+            file_name: param("filename"),
+            data,
+        });
+    }
+
+    Some(parts)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn check_text(text: &str) -> Result<(), FakeError> {
+    if text.is_empty() {
+        return Err(FakeError::Api(ApiError::MessageTextIsEmpty));
+    }
+
+    if text.encode_utf16().count() > 4096 {
+        return Err(FakeError::Api(ApiError::MessageIsTooLong));
+    }
+
+    Ok(())
+}
+
+fn str_param(p: &Map<String, Value>, key: &str) -> Option<String> {
+    match p.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn int_param(p: &Map<String, Value>, key: &str) -> Option<i64> {
+    match p.get(key)? {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Complex parameters are sent as JSON strings in `multipart/form-data`.
+fn json_param(value: &Value) -> Value {
+    match value {
+        Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| value.clone()),
+        other => other.clone(),
+    }
+}
+
+fn json_response(status: StatusCode, value: &Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(value.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn error_response(err: &FakeError) -> Response<Body> {
+    let (error_code, mut body) = err.to_json();
+    body["ok"] = false.into();
+    body["error_code"] = error_code.into();
+
+    let status = StatusCode::from_u16(error_code).unwrap_or(StatusCode::BAD_REQUEST);
+    json_response(status, &body)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+
+    use crate::{
+        net::Download,
+        payloads::SendDocumentSetters,
+        polling::Polling,
+        requests::{Request, Requester},
+        testing::fake_server::{FakeError, FakeServer},
+        types::{ChatId, InputFile},
+        ApiError, RequestError,
+    };
+
+    #[tokio::test]
+    async fn messages() {
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        let bot = server.bot();
+
+        let first = bot.send_message(ChatId(1), "one").send().await.unwrap();
+        let second = bot.send_message(ChatId(1), "two").send().await.unwrap();
+        assert_eq!(second.id, first.id + 1);
+
+        let edited = bot
+            .edit_message_text(ChatId(1), first.id, "one!")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(edited.text(), Some("one!"));
+
+        let res = bot
+            .edit_message_text(ChatId(1), first.id, "one!")
+            .send()
+            .await;
+        assert_eq!(
+            res.unwrap_err().api_error(),
+            Some(&ApiError::MessageNotModified)
+        );
+
+        bot.delete_message(ChatId(1), first.id)
+            .send()
+            .await
+            .unwrap();
+        assert!(server.message(ChatId(1), first.id).is_none());
+
+        let res = bot.send_message(ChatId(2), "?").send().await;
+        assert_eq!(res.unwrap_err().api_error(), Some(&ApiError::ChatNotFound));
+    }
+
+    #[tokio::test]
+    async fn injected_errors() {
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        server.fail_next("sendMessage", FakeError::RetryAfter(7));
+        server.fail_next("sendMessage", FakeError::MigrateToChatId(-100));
+        let bot = server.bot();
+
+        let res = bot.send_message(ChatId(1), "hi").send().await;
+        assert!(matches!(res, Err(RequestError::RetryAfter(d)) if d == Duration::from_secs(7)));
+
+        let res = bot.send_message(ChatId(1), "hi").send().await;
+        assert!(matches!(res, Err(RequestError::MigrateToChatId(-100))));
+
+        bot.send_message(ChatId(1), "hi").send().await.unwrap();
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn upload_and_download() {
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        let bot = server.bot();
+
+        let message = bot
+            .send_document(
+                ChatId(1),
+                InputFile::memory(&b"hello"[..]).file_name("a.txt"),
+            )
+            .caption("cap")
+            .send()
+            .await
+            .unwrap();
+        let document = message.document().unwrap();
+        assert_eq!(message.caption(), Some("cap"));
+        assert_eq!(server.file(&document.file_id).unwrap(), &b"hello"[..]);
+
+        let file = bot.get_file(&document.file_id).send().await.unwrap();
+        let mut buf = Vec::new();
+        bot.download_file(&file.file_path, &mut buf).await.unwrap();
+        assert_eq!(buf, b"hello");
+
+        // Resending by file_id doesn't upload the file again
+        let resent = bot
+            .send_document(ChatId(1), InputFile::file_id(&document.file_id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resent.document().unwrap().file_id, document.file_id);
+    }
+
+    #[tokio::test]
+    async fn polling() {
+        let server = FakeServer::start().unwrap();
+        let message = serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": { "id": 1, "type": "private", "first_name": "Alice" },
+            "text": "hi",
+        });
+        server.push_update(serde_json::json!({ "message": message }));
+        server.push_update(serde_json::json!({ "message": message }));
+
+        let (updates, shutdown) = Polling::new(server.bot())
+            .timeout(Duration::from_secs(1))
+            .into_stream();
+        futures::pin_mut!(updates);
+
+        assert_eq!(updates.next().await.unwrap().unwrap().id, 1);
+        shutdown.shutdown();
+        assert!(updates.next().await.is_none());
+
+        // The first update was acknowledged
+        let offsets: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.params.get("offset").and_then(|o| o.as_i64()))
+            .collect();
+        assert_eq!(offsets.last(), Some(&Some(2)));
+    }
+}
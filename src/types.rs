@@ -222,10 +222,11 @@ mod passport_data;
 mod passport_element_error;
 mod passport_file;
 
-pub use non_telegram_types::{country_code::*, currency::*, until_date::*};
+pub use non_telegram_types::{country_code::*, currency::*, formatted_text::*, until_date::*};
 mod non_telegram_types {
     pub(super) mod country_code;
     pub(super) mod currency;
+    pub(super) mod formatted_text;
     pub(crate) mod mime;
     pub(super) mod until_date;
 }
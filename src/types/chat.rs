@@ -81,6 +81,7 @@ pub struct ChatPrivate {
     /// `private`.
     #[serde(rename = "type")]
     #[serde(deserialize_with = "assert_private_field")]
+    #[serde(serialize_with = "serialize_private_field")]
     pub type_: (),
 
     /// A username, for private chats, supergroups and channels if
@@ -210,6 +211,13 @@ where
     des.deserialize_str(PrivateChatKindVisitor)
 }
 
+fn serialize_private_field<S>(_: &(), ser: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    ser.serialize_str("private")
+}
+
 impl Chat {
     pub fn is_private(&self) -> bool {
         matches!(self.kind, ChatKind::Private(_))
@@ -491,6 +499,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn private_chat_serialize() {
+        let json = r#"{"id":0,"type":"private","username":"username","first_name":"Anon"}"#;
+        let chat: Chat = from_str(json).unwrap();
+
+        assert_eq!(serde_json::to_string(&chat).unwrap(), json);
+    }
+
     #[test]
     fn private_chat_de_wrong_type_field() {
         assert!(from_str::<Chat>(r#"{"id":0,"type":"WRONG"}"#).is_err());
@@ -1,7 +1,7 @@
 use bytes::{Bytes, BytesMut};
 use futures::{
     future::{ready, Either},
-    stream,
+    stream, FutureExt, Stream, TryStreamExt,
 };
 use once_cell::sync::OnceCell;
 use rc_box::ArcBox;
@@ -35,9 +35,31 @@ use crate::types::InputSticker;
 pub struct InputFile {
     id: OnceCell<Arc<str>>,
     file_name: Option<Cow<'static, str>>,
+    progress: Option<ProgressCallback>,
     inner: InnerFile,
 }
 
+/// Progress of an upload of an [`InputFile`].
+///
+/// See [`InputFile::on_progress`] and [`InputFile::watch_progress`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// Number of bytes that were sent so far.
+    pub sent: u64,
+
+    /// Size of the file, if it's known.
+    pub total: Option<u64>,
+}
+
+#[derive(Clone)]
+struct ProgressCallback(Arc<dyn Fn(UploadProgress) + Send + Sync>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressCallback").finish_non_exhaustive()
+    }
+}
+
 #[derive(Clone)]
 enum InnerFile {
     Read(Read),
@@ -104,6 +126,47 @@ impl InputFile {
         self
     }
 
+    /// Sets a callback which is called with the upload progress every time a
+    /// chunk of this file is sent.
+    ///
+    /// The callback is called only when the file is uploaded, i.e. it's never
+    /// called for urls and file ids. Note that clones of this `InputFile`
+    /// share the callback.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use teloxide_core::types::InputFile;
+    ///
+    /// let file = InputFile::file("video.mp4").on_progress(|progress| match progress.total {
+    ///     Some(total) => log::info!("sent {}/{} bytes", progress.sent, total),
+    ///     None => log::info!("sent {} bytes", progress.sent),
+    /// });
+    /// ```
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(UploadProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Returns this file along with a receiver of its upload progress.
+    ///
+    /// This is a shorthand for [`on_progress`] with a callback that sends the
+    /// progress into a [`watch`] channel.
+    ///
+    /// [`on_progress`]: InputFile::on_progress
+    pub fn watch_progress(self) -> (Self, watch::Receiver<UploadProgress>) {
+        let (tx, rx) = watch::channel(UploadProgress::default());
+
+        // Error indicates that all receivers were dropped, but we don't care.
+        let this = self.on_progress(move |progress| {
+            let _ = tx.send(progress);
+        });
+        (this, rx)
+    }
+
     /// Creates an `InputFile` from a in-memory bytes.
     ///
     /// Note: in some cases (e.g. sending the same `InputFile` multiple times)
@@ -117,6 +180,7 @@ impl InputFile {
     fn new(inner: InnerFile) -> Self {
         Self {
             file_name: None,
+            progress: None,
             inner,
             id: OnceCell::new(),
         }
@@ -137,6 +201,72 @@ impl InputFile {
         !matches!(self.inner, Url(_) | FileId(_))
     }
 
+    /// Returns size of the file contents if it's known without reading them
+    /// (i.e. for in-memory files and files on disk).
+    #[cfg(feature = "metrics")]
+    pub(crate) fn size_hint(&self) -> Option<u64> {
+        match &self.inner {
+            Bytes(bytes) => Some(bytes.len() as u64),
+            File(path) => std::fs::metadata(path).ok().map(|meta| meta.len()),
+            Read(_) | Url(_) | FileId(_) => None,
+        }
+    }
+
+    /// Returns size of the file contents if it's known without reading them
+    /// (i.e. for in-memory files and files on disk), without blocking.
+    #[cfg(feature = "validate")]
+    pub(crate) async fn size(&self) -> Option<u64> {
+        match &self.inner {
+            Bytes(bytes) => Some(bytes.len() as u64),
+            File(path) => tokio::fs::metadata(path).await.ok().map(|meta| meta.len()),
+            Read(_) | Url(_) | FileId(_) => None,
+        }
+    }
+
+    /// Returns a key which identifies contents of this file, if it's a file on
+    /// disk or in-memory bytes.
+    ///
+    /// Neither reading metadata of files nor hashing blocks the executor.
+    #[cfg(feature = "cache_file_ids")]
+    pub(crate) async fn source_key(&self) -> Option<SourceKey> {
+        use sha2::{Digest, Sha256};
+
+        match &self.inner {
+            File(path) => {
+                let meta = tokio::fs::metadata(path).await.ok()?;
+                let canonical = tokio::fs::canonicalize(path).await;
+                Some(SourceKey::File {
+                    path: canonical.unwrap_or_else(|_| path.clone()),
+                    len: meta.len(),
+                    modified: meta.modified().ok(),
+                })
+            }
+            // A cryptographic digest, so that different contents can't be crafted to
+            // have the same key
+            Bytes(bytes) => {
+                let contents = bytes.clone();
+                let sha256 = tokio::task::spawn_blocking(move || Sha256::digest(&contents).into())
+                    .await
+                    .ok()?;
+                Some(SourceKey::Bytes {
+                    sha256,
+                    len: bytes.len(),
+                })
+            }
+            Read(_) | Url(_) | FileId(_) => None,
+        }
+    }
+
+    /// Returns a `file://` url of a file on disk, which can be sent to a local
+    /// Bot API server instead of uploading the file.
+    pub(crate) fn local_url(&self) -> Option<url::Url> {
+        match &self.inner {
+            // Note: joining an absolute path replaces the current dir
+            File(path) => url::Url::from_file_path(std::env::current_dir().ok()?.join(path)).ok(),
+            _ => None,
+        }
+    }
+
     /// Takes this file out.
     ///
     /// **Note**: this replaces `self` with a dummy value, this function should
@@ -179,6 +309,21 @@ impl InputFile {
     }
 }
 
+/// Identity of contents of an uploaded file, see [`InputFile::source_key`].
+#[cfg(feature = "cache_file_ids")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum SourceKey {
+    File {
+        path: PathBuf,
+        len: u64,
+        modified: Option<std::time::SystemTime>,
+    },
+    Bytes {
+        sha256: [u8; 32],
+        len: usize,
+    },
+}
+
 impl fmt::Debug for InnerFile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -204,8 +349,11 @@ impl Serialize for InputFile {
 // internal api
 
 impl InputFile {
-    pub(crate) fn into_part(mut self) -> Option<impl Future<Output = Part>> {
+    /// Converts this file into a part of a `multipart/form-data` form, also
+    /// returning the size of the part if it's known.
+    pub(crate) fn into_part(mut self) -> Option<impl Future<Output = (Part, Option<u64>)>> {
         let filename = self.take_or_guess_filename();
+        let progress = self.progress;
 
         match self.inner {
             // Url and FileId are serialized just as strings, they don't need additional parts
@@ -213,33 +361,72 @@ impl InputFile {
 
             File(path_to_file) => {
                 let fut = async {
-                    let body = match tokio::fs::File::open(path_to_file).await {
+                    let (body, total) = match tokio::fs::File::open(path_to_file).await {
                         Ok(file) => {
+                            let total = file.metadata().await.ok().map(|meta| meta.len());
                             let file = FramedRead::new(file, BytesDecoder);
 
-                            Body::wrap_stream(file)
+                            (wrap_stream(file, total, progress), total)
                         }
                         Err(err) => {
                             // explicit type needed for `Bytes: From<?T>` in `wrap_stream`
                             let err = Err::<Bytes, _>(err);
-                            Body::wrap_stream(stream::iter([err]))
+                            (Body::wrap_stream(stream::iter([err])), None)
                         }
                     };
 
-                    Part::stream(body).file_name(filename)
+                    (Part::stream(body).file_name(filename), total)
                 };
 
                 Some(Either::Left(fut))
             }
             Bytes(data) => {
-                let stream = Part::stream(data).file_name(filename);
-                Some(Either::Right(Either::Left(ready(stream))))
+                let size = data.len() as u64;
+                let body = match progress {
+                    // Split the data into chunks, so that progress is reported while it's sent
+                    Some(progress) => {
+                        // Chunk size, arbitrary chosen to be 64KiB
+                        const CHUNK: usize = 64 * 1024;
+
+                        let total = data.len();
+                        let chunks = (0..total).step_by(CHUNK).map(move |start| {
+                            Ok::<_, Infallible>(data.slice(start..total.min(start + CHUNK)))
+                        });
+
+                        wrap_stream(stream::iter(chunks), Some(total as u64), Some(progress))
+                    }
+                    None => Body::from(data),
+                };
+
+                let stream = Part::stream(body).file_name(filename);
+                Some(Either::Right(Either::Left(ready((stream, Some(size))))))
             }
-            Read(read) => Some(Either::Right(Either::Right(read.into_part(filename)))),
+            Read(read) => Some(Either::Right(Either::Right(
+                read.into_part(filename, progress).map(|part| (part, None)),
+            ))),
         }
     }
 }
 
+/// Converts a stream of chunks into a `Body`, reporting the upload progress
+/// to `progress` when a chunk is sent.
+fn wrap_stream<S, E>(stream: S, total: Option<u64>, progress: Option<ProgressCallback>) -> Body
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + Sync + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+{
+    match progress {
+        Some(ProgressCallback(callback)) => {
+            let mut sent = 0;
+            Body::wrap_stream(stream.inspect_ok(move |chunk| {
+                sent += chunk.len() as u64;
+                callback(UploadProgress { sent, total });
+            }))
+        }
+        None => Body::wrap_stream(stream),
+    }
+}
+
 /// Adaptor for `AsyncRead` that allows clonning and converting to
 /// `multipart/form-data`
 #[derive(Clone)]
@@ -262,7 +449,11 @@ impl Read {
         }
     }
 
-    pub(crate) async fn into_part(mut self, filename: Cow<'static, str>) -> Part {
+    pub(crate) async fn into_part(
+        mut self,
+        filename: Cow<'static, str>,
+        progress: Option<ProgressCallback>,
+    ) -> Part {
         if !self.inner.is_taken() {
             let res = ArcBox::<TakeCell<dyn AsyncRead + Send + Unpin>>::try_from(self.inner);
             match res {
@@ -271,7 +462,7 @@ impl Read {
                 Ok(arc_box) => {
                     let fr = FramedRead::new(ExclusiveArcAsyncRead(arc_box), BytesDecoder);
 
-                    let body = Body::wrap_stream(fr);
+                    let body = wrap_stream(fr, None, progress);
                     return Part::stream(body).file_name(filename);
                 }
                 // move the arc back into `self`
@@ -281,12 +472,12 @@ impl Read {
 
         // Slow path: either wait until someone will read the whole `dyn AsynсRead` into
         // a buffer, or be the one who reads
-        let body = self.into_shared_body().await;
+        let body = self.into_shared_body(progress).await;
 
         Part::stream(body).file_name(filename)
     }
 
-    async fn into_shared_body(mut self) -> Body {
+    async fn into_shared_body(mut self, progress: Option<ProgressCallback>) -> Body {
         match self.inner.take() {
             // Read `dyn AsyncRead` into a buffer
             Some(mut read_ref) => {
@@ -345,7 +536,9 @@ impl Read {
         // unwrap: `OnceCell` is initialized in the match above before sending
         // notification, so at this point it's already initialized.
         match buf.get().unwrap() {
-            Ok(_) => {
+            Ok(chunks) => {
+                let total = chunks.iter().map(|chunk| chunk.len() as u64).sum();
+
                 // We can't use `.iter()` here, because the iterator must capture `buf`
                 let mut i = 0;
                 let iter = iter::from_fn(move || match buf.get().unwrap() {
@@ -359,7 +552,7 @@ impl Read {
                     Err(_) => unreachable!(),
                 });
 
-                Body::wrap_stream(stream::iter(iter))
+                wrap_stream(stream::iter(iter), Some(total), progress)
             }
 
             Err(err) => {
@@ -410,6 +603,11 @@ pub(crate) trait InputFileLike {
 
 impl InputFileLike for InputFile {
     fn copy_into(&self, into: &mut dyn FnMut(InputFile)) {
+        // Initialize the id, so that the copy is attached with the same name
+        if self.needs_attach() {
+            self.id();
+        }
+
         into(self.clone())
     }
 
@@ -445,3 +643,43 @@ impl InputFileLike for InputSticker {
         input_file.move_into(into)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn upload_progress() {
+        use crate::{
+            requests::{Request, Requester},
+            testing::fake_server::FakeServer,
+            types::{ChatId, InputFile, UploadProgress},
+        };
+
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        let bot = server.bot();
+
+        let data = vec![1; 100 * 1024];
+        let (file, progress) = InputFile::memory(data).file_name("a.bin").watch_progress();
+        assert_eq!(*progress.borrow(), UploadProgress::default());
+
+        bot.send_document(ChatId(1), file).send().await.unwrap();
+        assert_eq!(
+            *progress.borrow(),
+            UploadProgress {
+                sent: 100 * 1024,
+                total: Some(100 * 1024)
+            }
+        );
+
+        let (file, progress) = InputFile::read(&[1, 2, 3][..]).watch_progress();
+        bot.send_document(ChatId(1), file).send().await.unwrap();
+        assert_eq!(
+            *progress.borrow(),
+            UploadProgress {
+                sent: 3,
+                total: None
+            }
+        );
+    }
+}
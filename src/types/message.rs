@@ -3,12 +3,16 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::types::{
-    Animation, Audio, BareChatId, Chat, ChatId, Contact, Dice, Document, Game,
-    InlineKeyboardMarkup, Invoice, Location, MessageAutoDeleteTimerChanged, MessageEntity,
-    PassportData, PhotoSize, Poll, ProximityAlertTriggered, Sticker, SuccessfulPayment, True, User,
-    Venue, Video, VideoChatEnded, VideoChatParticipantsInvited, VideoChatScheduled,
-    VideoChatStarted, VideoNote, Voice, WebAppData,
+use crate::{
+    types::{
+        Animation, Audio, BareChatId, BotCommandRef, Chat, ChatId, Contact, Dice, Document, Game,
+        InlineKeyboardMarkup, Invoice, Location, MessageAutoDeleteTimerChanged, MessageEntity,
+        MessageEntityKind, MessageEntityRef, PassportData, PhotoSize, Poll,
+        ProximityAlertTriggered, Sticker, SuccessfulPayment, True, User, Venue, Video,
+        VideoChatEnded, VideoChatParticipantsInvited, VideoChatScheduled, VideoChatStarted,
+        VideoNote, Voice, WebAppData,
+    },
+    utils::{html, markdown},
 };
 
 /// This object represents a message.
@@ -1095,6 +1099,174 @@ impl Message {
         // /[a-zA-Z0-9_]{5,32}/ and chat/message ids are integers.
         Some(reqwest::Url::parse(&url).unwrap())
     }
+
+    /// Returns the text of the message with entities rendered into an [HTML]
+    /// string.
+    ///
+    /// See also [`utils::html::render`].
+    ///
+    /// [HTML]: https://core.telegram.org/bots/api#html-style
+    /// [`utils::html::render`]: crate::utils::html::render
+    pub fn html_text(&self) -> Option<String> {
+        self.text()
+            .zip(self.entities())
+            .map(|(text, entities)| html::render(text, entities))
+    }
+
+    /// Returns the text of the message with entities rendered into a
+    /// [MarkdownV2] string.
+    ///
+    /// See also [`utils::markdown::render`].
+    ///
+    /// [MarkdownV2]: https://core.telegram.org/bots/api#markdownv2-style
+    /// [`utils::markdown::render`]: crate::utils::markdown::render
+    pub fn markdown_text(&self) -> Option<String> {
+        self.text()
+            .zip(self.entities())
+            .map(|(text, entities)| markdown::render(text, entities))
+    }
+
+    /// Returns the caption of the message with entities rendered into an
+    /// [HTML] string.
+    ///
+    /// See also [`utils::html::render`].
+    ///
+    /// [HTML]: https://core.telegram.org/bots/api#html-style
+    /// [`utils::html::render`]: crate::utils::html::render
+    pub fn html_caption(&self) -> Option<String> {
+        self.caption()
+            .zip(self.caption_entities())
+            .map(|(text, entities)| html::render(text, entities))
+    }
+
+    /// Returns the caption of the message with entities rendered into a
+    /// [MarkdownV2] string.
+    ///
+    /// See also [`utils::markdown::render`].
+    ///
+    /// [MarkdownV2]: https://core.telegram.org/bots/api#markdownv2-style
+    /// [`utils::markdown::render`]: crate::utils::markdown::render
+    pub fn markdown_caption(&self) -> Option<String> {
+        self.caption()
+            .zip(self.caption_entities())
+            .map(|(text, entities)| markdown::render(text, entities))
+    }
+
+    /// Returns entities of the message text paired with the text they refer
+    /// to.
+    ///
+    /// See also [`MessageEntityRef`].
+    pub fn parse_entities(&self) -> Option<Vec<MessageEntityRef<'_>>> {
+        self.text()
+            .zip(self.entities())
+            .map(|(text, entities)| MessageEntityRef::parse(text, entities))
+    }
+
+    /// Returns entities of the message caption paired with the text they refer
+    /// to.
+    ///
+    /// See also [`MessageEntityRef`].
+    pub fn parse_caption_entities(&self) -> Option<Vec<MessageEntityRef<'_>>> {
+        self.caption()
+            .zip(self.caption_entities())
+            .map(|(text, entities)| MessageEntityRef::parse(text, entities))
+    }
+
+    /// Returns bot commands from the text or the caption of the message.
+    ///
+    /// Arguments of a command are the text following it up to the next
+    /// command.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use teloxide_core::types::Message;
+    /// # fn example(message: Message) {
+    /// // For a message with text "/ban@my_bot 10m spam"
+    /// for command in message.bot_commands() {
+    ///     // Prints "ban my_bot 10m spam"
+    ///     println!(
+    ///         "{} {} {}",
+    ///         command.command(),
+    ///         command.bot_username().unwrap_or_default(),
+    ///         command.args()
+    ///     );
+    /// }
+    /// # }
+    /// ```
+    pub fn bot_commands(&self) -> impl Iterator<Item = BotCommandRef<'_>> {
+        let commands: Vec<_> = self
+            .text_or_caption_entities()
+            .into_iter()
+            .filter(|e| matches!(e.kind(), MessageEntityKind::BotCommand))
+            .collect();
+
+        let ends: Vec<_> = commands
+            .iter()
+            .skip(1)
+            .map(MessageEntityRef::start)
+            .chain(commands.last().map(|e| e.message_text().len()))
+            .collect();
+
+        commands.into_iter().zip(ends).map(|(command, end)| {
+            let args = &command.message_text()[command.end()..end.max(command.end())];
+            BotCommandRef::new(command.text(), args)
+        })
+    }
+
+    /// Returns mentions of users by their usernames (e.g. `@username`) from
+    /// the text or the caption of the message.
+    pub fn mentions(&self) -> impl Iterator<Item = &str> {
+        self.entities_of_kind(|kind| matches!(kind, MessageEntityKind::Mention))
+    }
+
+    /// Returns hashtags (e.g. `#hashtag`) from the text or the caption of the
+    /// message.
+    pub fn hashtags(&self) -> impl Iterator<Item = &str> {
+        self.entities_of_kind(|kind| matches!(kind, MessageEntityKind::Hashtag))
+    }
+
+    /// Returns URLs from the text or the caption of the message, including
+    /// URLs of text links.
+    ///
+    /// Note that URLs written in the text are returned as is, so they might
+    /// lack a scheme (e.g. `example.com`).
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        self.text_or_caption_entities()
+            .into_iter()
+            .filter_map(|e| match e.kind() {
+                MessageEntityKind::Url => Some(e.text()),
+                MessageEntityKind::TextLink { url } => Some(url.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Returns mentions of users without usernames, along with the mentioning
+    /// text, from the text or the caption of the message.
+    pub fn text_mentions(&self) -> impl Iterator<Item = (&User, &str)> {
+        self.text_or_caption_entities()
+            .into_iter()
+            .filter_map(|e| match e.kind() {
+                MessageEntityKind::TextMention { user } => Some((user, e.text())),
+                _ => None,
+            })
+    }
+
+    fn text_or_caption_entities(&self) -> Vec<MessageEntityRef<'_>> {
+        self.parse_entities()
+            .or_else(|| self.parse_caption_entities())
+            .unwrap_or_default()
+    }
+
+    fn entities_of_kind(
+        &self,
+        f: impl Fn(&MessageEntityKind) -> bool,
+    ) -> impl Iterator<Item = &str> {
+        self.text_or_caption_entities()
+            .into_iter()
+            .filter(move |e| f(e.kind()))
+            .map(|e| e.text())
+    }
 }
 
 #[cfg(test)]
@@ -1472,4 +1644,49 @@ mod tests {
         // cases inclusing this
         // assert!(message.sender_chat().is_some());
     }
+
+    #[test]
+    fn entities_extraction() {
+        let json = r#"{
+          "message_id": 1,
+          "chat": {"id": 1, "first_name": "A", "type": "private"},
+          "date": 1567927221,
+          "text": "😂 /ban@bot @spammer 10m #spam\n/info example.com link hi",
+          "entities": [
+            {"type": "bot_command", "offset": 3, "length": 8},
+            {"type": "mention", "offset": 12, "length": 8},
+            {"type": "hashtag", "offset": 25, "length": 5},
+            {"type": "bot_command", "offset": 31, "length": 5},
+            {"type": "url", "offset": 37, "length": 11},
+            {"type": "text_link", "offset": 49, "length": 4, "url": "https://example.org/"},
+            {"type": "text_mention", "offset": 54, "length": 2, "user": {"id": 2, "is_bot": false, "first_name": "B"}}
+          ]
+        }"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+
+        let commands: Vec<_> = message
+            .bot_commands()
+            .map(|c| (c.command(), c.bot_username(), c.args()))
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                ("ban", Some("bot"), "@spammer 10m #spam"),
+                ("info", None, "example.com link hi")
+            ]
+        );
+
+        assert_eq!(message.mentions().collect::<Vec<_>>(), ["@spammer"]);
+        assert_eq!(message.hashtags().collect::<Vec<_>>(), ["#spam"]);
+        assert_eq!(
+            message.urls().collect::<Vec<_>>(),
+            ["example.com", "https://example.org/"]
+        );
+
+        let mentions: Vec<_> = message
+            .text_mentions()
+            .map(|(user, text)| (user.id, text))
+            .collect();
+        assert_eq!(mentions, [(UserId(2), "hi")]);
+    }
 }
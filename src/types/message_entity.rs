@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use serde::{Deserialize, Serialize};
 
 use crate::types::{User, UserId};
@@ -77,6 +79,140 @@ pub enum MessageEntityKind {
     Spoiler,
 }
 
+/// A [`MessageEntity`] paired with the text it refers to.
+///
+/// Offsets of [`MessageEntity`] are measured in UTF-16 code units, which makes
+/// them hard to use with Rust strings. This type converts them into byte
+/// offsets.
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::types::{MessageEntity, MessageEntityKind, MessageEntityRef};
+///
+/// let text = "🦀 Ferris";
+/// let entities = [MessageEntity::new(MessageEntityKind::Bold, 3, 6)];
+///
+/// let parsed = MessageEntityRef::parse(text, &entities);
+/// assert_eq!(parsed[0].text(), "Ferris");
+/// assert_eq!(parsed[0].range(), 5..11);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MessageEntityRef<'a> {
+    message: &'a str,
+    range: Range<usize>,
+    kind: &'a MessageEntityKind,
+}
+
+impl<'a> MessageEntityRef<'a> {
+    /// Pairs `entities` with the `text` they refer to.
+    ///
+    /// Entities which don't fit into the text are skipped.
+    pub fn parse(text: &'a str, entities: &'a [MessageEntity]) -> Vec<Self> {
+        // Byte offsets of all UTF-16 offsets (offsets pointing into the middle of
+        // a surrogate pair are mapped to the start of the char)
+        let mut byte_offsets = Vec::with_capacity(text.len() + 1);
+        for (idx, c) in text.char_indices() {
+            for _ in 0..c.len_utf16() {
+                byte_offsets.push(idx);
+            }
+        }
+        byte_offsets.push(text.len());
+
+        entities
+            .iter()
+            .filter_map(|entity| {
+                let start = *byte_offsets.get(entity.offset)?;
+                let end = *byte_offsets.get(entity.offset.checked_add(entity.length)?)?;
+
+                Some(Self {
+                    message: text,
+                    range: start..end,
+                    kind: &entity.kind,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the kind of the entity.
+    pub fn kind(&self) -> &'a MessageEntityKind {
+        self.kind
+    }
+
+    /// Returns the text the entity refers to.
+    pub fn text(&self) -> &'a str {
+        &self.message[self.range.clone()]
+    }
+
+    /// Returns the byte range of the entity in the message text.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the byte offset of the start of the entity in the message
+    /// text.
+    pub fn start(&self) -> usize {
+        self.range.start
+    }
+
+    /// Returns the byte offset of the end of the entity in the message text.
+    pub fn end(&self) -> usize {
+        self.range.end
+    }
+
+    /// Returns the whole text of the message.
+    pub fn message_text(&self) -> &'a str {
+        self.message
+    }
+}
+
+/// A bot command found in a message, e.g. `/start@my_bot arg`.
+///
+/// Returned by [`Message::bot_commands`].
+///
+/// [`Message::bot_commands`]: crate::types::Message::bot_commands
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BotCommandRef<'a> {
+    command: &'a str,
+    bot_username: Option<&'a str>,
+    args: &'a str,
+}
+
+impl<'a> BotCommandRef<'a> {
+    /// Splits the text of a bot command entity and the arguments following
+    /// it.
+    pub(crate) fn new(entity: &'a str, args: &'a str) -> Self {
+        let entity = entity.strip_prefix('/').unwrap_or(entity);
+        let (command, bot_username) = match entity.split_once('@') {
+            Some((command, bot_username)) => (command, Some(bot_username)),
+            None => (entity, None),
+        };
+
+        Self {
+            command,
+            bot_username,
+            args: args.trim(),
+        }
+    }
+
+    /// Returns the command without the leading `/`, e.g. `start`.
+    pub fn command(&self) -> &'a str {
+        self.command
+    }
+
+    /// Returns the username of the bot the command is addressed to, if any,
+    /// e.g. `my_bot` for `/start@my_bot`.
+    pub fn bot_username(&self) -> Option<&'a str> {
+        self.bot_username
+    }
+
+    /// Returns text following the command (up to the next command), with
+    /// leading and trailing whitespace removed.
+    pub fn args(&self) -> &'a str {
+        self.args
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,4 +269,30 @@ mod tests {
             None
         );
     }
+
+    #[test]
+    fn entity_refs() {
+        use MessageEntityKind::*;
+
+        let text = "a🦀ü /start@bot";
+        let entities = [
+            MessageEntity::new(Bold, 0, 4),
+            MessageEntity::new(BotCommand, 5, 10),
+            MessageEntity::new(Italic, 2, 1),
+            MessageEntity::new(Code, 10, 6),
+        ];
+
+        let parsed = MessageEntityRef::parse(text, &entities);
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].text(), "a🦀ü");
+        assert_eq!(parsed[1].text(), "/start@bot");
+        assert_eq!(parsed[1].range(), 8..18);
+        // Offset in the middle of a surrogate pair
+        assert_eq!(parsed[2].text(), "🦀");
+
+        let command = BotCommandRef::new(parsed[1].text(), "  a b ");
+        assert_eq!(command.command(), "start");
+        assert_eq!(command.bot_username(), Some("bot"));
+        assert_eq!(command.args(), "a b");
+    }
 }
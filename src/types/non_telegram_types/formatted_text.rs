@@ -0,0 +1,263 @@
+use reqwest::Url;
+
+use crate::types::{MessageEntity, MessageEntityKind, UserId};
+
+/// Text with formatting, represented as [`MessageEntity`]s.
+///
+/// This is an alternative to [`ParseMode`]: instead of escaping the text and
+/// letting Telegram parse markup, the builder computes entities (with offsets
+/// in UTF-16 code units, as Telegram requires) while the text is built.
+///
+/// Formatting methods append text to the end, e.g. `text.bold("hi")` appends
+/// bold `hi`. Methods which accept `impl Into<FormattedText>` can be nested.
+///
+/// [`ParseMode`]: crate::types::ParseMode
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     payloads::SendMessageSetters,
+///     prelude::*,
+///     types::{ChatId, FormattedText},
+/// };
+///
+/// # async {
+/// # let bot = Bot::new("TOKEN");
+/// let (text, entities) = FormattedText::new()
+///     .plain("Hello, ")
+///     .bold(FormattedText::new().italic("dear").plain(" reader"))
+///     .plain("! Try ")
+///     .code("cargo doc")
+///     .into_parts();
+///
+/// assert_eq!(text, "Hello, dear reader! Try cargo doc");
+/// bot.send_message(ChatId(1), text)
+///     .entities(entities)
+///     .send()
+///     .await?;
+/// # Ok::<_, teloxide_core::RequestError>(()) };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FormattedText {
+    text: String,
+    entities: Vec<MessageEntity>,
+    // Length of `text` in UTF-16 code units
+    utf16_len: usize,
+}
+
+impl FormattedText {
+    /// Creates empty text.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the text without formatting.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns entities of the text.
+    pub fn entities(&self) -> &[MessageEntity] {
+        &self.entities
+    }
+
+    /// Returns length of the text in UTF-16 code units (which is how Telegram
+    /// measures texts).
+    pub fn utf16_len(&self) -> usize {
+        self.utf16_len
+    }
+
+    /// Returns `true` if the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Splits this into the text and the entities, which can be passed to
+    /// e.g. [`SendMessage`] (`text` and `entities`) or [`SendPhoto`]
+    /// (`caption` and `caption_entities`).
+    ///
+    /// [`SendMessage`]: crate::payloads::SendMessage
+    /// [`SendPhoto`]: crate::payloads::SendPhoto
+    pub fn into_parts(self) -> (String, Vec<MessageEntity>) {
+        (self.text, self.entities)
+    }
+
+    /// Appends text without formatting.
+    pub fn plain(mut self, text: &str) -> Self {
+        self.push_str(text);
+        self
+    }
+
+    /// Appends another (possibly formatted) text.
+    pub fn append(mut self, text: impl Into<FormattedText>) -> Self {
+        self.push(text.into());
+        self
+    }
+
+    /// Appends bold text.
+    pub fn bold(self, text: impl Into<FormattedText>) -> Self {
+        self.entity(text, MessageEntityKind::Bold)
+    }
+
+    /// Appends italic text.
+    pub fn italic(self, text: impl Into<FormattedText>) -> Self {
+        self.entity(text, MessageEntityKind::Italic)
+    }
+
+    /// Appends underlined text.
+    pub fn underline(self, text: impl Into<FormattedText>) -> Self {
+        self.entity(text, MessageEntityKind::Underline)
+    }
+
+    /// Appends strikethrough text.
+    pub fn strikethrough(self, text: impl Into<FormattedText>) -> Self {
+        self.entity(text, MessageEntityKind::Strikethrough)
+    }
+
+    /// Appends spoiler text.
+    pub fn spoiler(self, text: impl Into<FormattedText>) -> Self {
+        self.entity(text, MessageEntityKind::Spoiler)
+    }
+
+    /// Appends inline code.
+    ///
+    /// Code can't contain other entities, so it only accepts plain text.
+    pub fn code(self, text: &str) -> Self {
+        self.entity(text, MessageEntityKind::Code)
+    }
+
+    /// Appends a pre-formatted code block, optionally with the programming
+    /// language of the code.
+    ///
+    /// Code can't contain other entities, so it only accepts plain text.
+    pub fn pre(self, text: &str, language: Option<String>) -> Self {
+        self.entity(text, MessageEntityKind::Pre { language })
+    }
+
+    /// Appends a text link to `url`.
+    pub fn link(self, text: impl Into<FormattedText>, url: Url) -> Self {
+        self.entity(text, MessageEntityKind::TextLink { url })
+    }
+
+    /// Appends a mention of a user by their id (which works even for users
+    /// without usernames).
+    ///
+    /// See also [`MessageEntity::user_mention`].
+    pub fn user_mention(self, text: impl Into<FormattedText>, user_id: UserId) -> Self {
+        self.entity(text, MessageEntityKind::TextLink { url: user_id.url() })
+    }
+
+    /// Appends text with an arbitrary entity.
+    ///
+    /// The entity is not added if the text is empty, since Telegram doesn't
+    /// allow empty entities.
+    pub fn entity(mut self, text: impl Into<FormattedText>, kind: MessageEntityKind) -> Self {
+        let text = text.into();
+        let offset = self.utf16_len;
+        let length = text.utf16_len;
+
+        if length != 0 {
+            // The outer entity goes before the inner ones, so that entities stay
+            // sorted by offset
+            self.entities.push(MessageEntity::new(kind, offset, length));
+        }
+        self.push(text);
+
+        self
+    }
+
+    fn push_str(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.utf16_len += text.encode_utf16().count();
+    }
+
+    fn push(&mut self, other: FormattedText) {
+        let offset = self.utf16_len;
+
+        self.entities
+            .extend(other.entities.into_iter().map(|mut entity| {
+                entity.offset += offset;
+                entity
+            }));
+        self.text.push_str(&other.text);
+        self.utf16_len += other.utf16_len;
+    }
+}
+
+impl From<&str> for FormattedText {
+    fn from(text: &str) -> Self {
+        Self::new().plain(text)
+    }
+}
+
+impl From<String> for FormattedText {
+    fn from(text: String) -> Self {
+        let utf16_len = text.encode_utf16().count();
+
+        Self {
+            text,
+            entities: Vec::new(),
+            utf16_len,
+        }
+    }
+}
+
+impl From<FormattedText> for (String, Vec<MessageEntity>) {
+    fn from(text: FormattedText) -> Self {
+        text.into_parts()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{FormattedText, MessageEntity, MessageEntityKind::*, UserId};
+
+    #[test]
+    fn utf16_offsets() {
+        let text = FormattedText::new()
+            .plain("🦀 ")
+            .bold("ü🦀")
+            .plain(" ")
+            .pre("fn main() {}", Some("rust".to_owned()));
+
+        assert_eq!(text.text(), "🦀 ü🦀 fn main() {}");
+        assert_eq!(text.utf16_len(), 19);
+        assert_eq!(
+            text.entities(),
+            [
+                MessageEntity::new(Bold, 3, 3),
+                MessageEntity::new(
+                    Pre {
+                        language: Some("rust".to_owned())
+                    },
+                    7,
+                    12
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn nesting() {
+        let (text, entities) = FormattedText::new()
+            .plain("a")
+            .bold(
+                FormattedText::new()
+                    .italic("b")
+                    .user_mention("c", UserId(1)),
+            )
+            .spoiler("")
+            .into_parts();
+
+        assert_eq!(text, "abc");
+        assert_eq!(
+            entities,
+            [
+                MessageEntity::new(Bold, 1, 2),
+                MessageEntity::new(Italic, 1, 1),
+                MessageEntity::user_mention(UserId(1), 2, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Utilities for producing and parsing formatted text.
+//!
+//! [`markdown`] and [`html`] help to build texts for the
+//! [`ParseMode::MarkdownV2`] and [`ParseMode::Html`] parse modes
+//! respectively, escaping special characters as Telegram requires. They also
+//! allow to convert formatted strings into text with [`MessageEntity`]s and
+//! back, without sending anything to Telegram.
+//!
+//! If you'd rather not deal with markup at all, see [`FormattedText`] which
+//! produces entities directly.
+//!
+//! [`split`] and [`broadcast`] help to send messages which are too long or
+//! have to be sent to many chats.
+//!
+//! [`ParseMode::MarkdownV2`]: crate::types::ParseMode::MarkdownV2
+//! [`ParseMode::Html`]: crate::types::ParseMode::Html
+//! [`MessageEntity`]: crate::types::MessageEntity
+//! [`FormattedText`]: crate::types::FormattedText
+
+use thiserror::Error;
+
+pub mod broadcast;
+pub mod html;
+pub mod markdown;
+pub mod split;
+
+mod parse;
+mod render;
+
+/// An error returned by [`markdown::parse`] and [`html::parse`] when a string
+/// is not correctly formatted.
+///
+/// Telegram reports such strings with [`ApiError::CantParseEntities`].
+///
+/// [`ApiError::CantParseEntities`]: crate::ApiError::CantParseEntities
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{message} at byte offset {offset}")]
+pub struct ParseError {
+    offset: usize,
+    message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(offset: usize, message: impl Into<String>) -> Self {
+        Self {
+            offset,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the offset (in bytes) in the parsed string at which the error
+    /// was found.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
@@ -0,0 +1,469 @@
+//! Sending the same message to many chats.
+//!
+//! See [`Broadcast`] for more.
+
+use std::{fmt, sync::Arc};
+
+use futures::{future, stream, StreamExt};
+use tokio::sync::watch;
+
+use crate::{
+    payloads::{
+        CopyMessage, ForwardMessage, SendAnimation, SendAudio, SendContact, SendDice, SendDocument,
+        SendInvoice, SendLocation, SendMediaGroup, SendMessage, SendPhoto, SendPoll, SendSticker,
+        SendVenue, SendVideo, SendVideoNote, SendVoice,
+    },
+    requests::{Output, Payload, Request},
+    types::{ChatId, Recipient},
+    ApiError, RequestError,
+};
+
+/// Sends one request to many chats.
+///
+/// `Broadcast` takes a template request and a list of recipients. For every
+/// recipient the request is cloned, its `chat_id` is replaced and the request
+/// is sent. Up to [`concurrency`] requests are sent at the same time.
+///
+/// `Broadcast` doesn't limit the rate of requests by itself, so the template
+/// request should be created by a bot wrapped in the [`Throttle`] adaptor,
+/// otherwise Telegram will start failing requests with
+/// [`RequestError::RetryAfter`] pretty quickly.
+///
+/// The result is a [`BroadcastReport`] which tells what happened with every
+/// recipient, e.g. which chats blocked the bot and should be removed from the
+/// subscribers list.
+///
+/// [`concurrency`]: Broadcast::concurrency
+/// [`Throttle`]: crate::adaptors::Throttle
+///
+/// ## Examples
+///
+/// ```no_run
+/// use teloxide_core::{prelude::*, types::ChatId, utils::broadcast::Broadcast};
+///
+/// # async {
+/// let bot = Bot::new("TOKEN");
+/// let subscribers = vec![ChatId(1), ChatId(2), ChatId(3)];
+///
+/// let request = bot.send_message(ChatId(0), "New version is out!");
+/// let (broadcast, _cancel) = Broadcast::new(request, subscribers)
+///     .on_progress(|p| log::info!("{}/{} messages sent", p.done, p.total))
+///     .start();
+///
+/// let report = broadcast.await;
+/// for chat in report.unreachable() {
+///     log::info!("removing {} from subscribers", chat);
+/// }
+/// # };
+/// ```
+pub struct Broadcast<R> {
+    request: R,
+    recipients: Vec<Recipient>,
+    concurrency: usize,
+    on_progress: Option<Arc<dyn Fn(BroadcastProgress) + Send + Sync>>,
+}
+
+/// Progress of a [`Broadcast`], passed to [`Broadcast::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastProgress {
+    /// Number of recipients for which the request was completed (either
+    /// successfully or not).
+    pub done: usize,
+
+    /// Total number of recipients.
+    pub total: usize,
+}
+
+/// A handle that allows to cancel a [`Broadcast`].
+///
+/// See [`CancelToken::cancel`].
+#[derive(Clone, Debug)]
+pub struct CancelToken {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+/// Result of a [`Broadcast`].
+#[derive(Debug)]
+pub struct BroadcastReport<T> {
+    deliveries: Vec<Delivery<T>>,
+}
+
+/// What happened with a request sent to one of the recipients of a
+/// [`Broadcast`].
+#[derive(Debug)]
+pub struct Delivery<T> {
+    /// The recipient.
+    pub recipient: Recipient,
+
+    /// Result of sending the request.
+    pub status: DeliveryStatus<T>,
+}
+
+/// Result of sending a request to one of the recipients of a [`Broadcast`].
+#[derive(Debug)]
+pub enum DeliveryStatus<T> {
+    /// The request was successfully sent.
+    Sent(T),
+
+    /// The bot was blocked by the user ([`ApiError::BotBlocked`]).
+    BotBlocked,
+
+    /// The user was deactivated ([`ApiError::UserDeactivated`]).
+    UserDeactivated,
+
+    /// The chat doesn't exist or the bot isn't a member of it
+    /// ([`ApiError::ChatNotFound`]).
+    ChatNotFound,
+
+    /// The group was migrated to a supergroup with the specified identifier
+    /// ([`RequestError::MigrateToChatId`]).
+    MigrateToChatId(ChatId),
+
+    /// The request failed with some other error.
+    Failed(RequestError),
+
+    /// The request wasn't sent because the broadcast was cancelled.
+    Cancelled,
+}
+
+impl<R> Broadcast<R>
+where
+    R: Request<Err = RequestError> + Clone,
+    R::Payload: BroadcastPayload,
+{
+    /// Creates a new broadcast of `request` to `recipients`.
+    ///
+    /// `chat_id` of the `request` itself is ignored. By default up to 30
+    /// requests are sent concurrently.
+    pub fn new<I>(request: R, recipients: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Recipient>,
+    {
+        Self {
+            request,
+            recipients: recipients.into_iter().map(Into::into).collect(),
+            concurrency: 30,
+            on_progress: None,
+        }
+    }
+
+    /// Sets the maximum number of requests which are sent at the same time.
+    ///
+    /// ## Panics
+    ///
+    /// If `val` is 0.
+    pub fn concurrency(mut self, val: usize) -> Self {
+        assert_ne!(val, 0, "concurrency must be positive");
+        self.concurrency = val;
+        self
+    }
+
+    /// Sets a callback that is called every time a request is completed.
+    pub fn on_progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(BroadcastProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+
+    /// Starts the broadcast.
+    ///
+    /// Returns a future that resolves to the report alongside with a token
+    /// that allows to cancel the broadcast. Requests are only sent while the
+    /// future is polled.
+    pub fn start(
+        self,
+    ) -> (
+        impl future::Future<Output = BroadcastReport<Output<R>>>,
+        CancelToken,
+    ) {
+        let (tx, cancelled) = watch::channel(false);
+        let token = CancelToken { tx: Arc::new(tx) };
+
+        let Self {
+            request,
+            recipients,
+            concurrency,
+            on_progress,
+        } = self;
+        let total = recipients.len();
+
+        let deliveries = stream::iter(recipients.into_iter().enumerate())
+            .map(move |(idx, recipient)| {
+                // Checked when the request is about to be sent, not when the broadcast starts
+                let cancelled = *cancelled.borrow();
+                let mut request = request.clone();
+
+                async move {
+                    if cancelled {
+                        return (idx, recipient, DeliveryStatus::Cancelled);
+                    }
+
+                    *request.payload_mut().chat_id_mut() = recipient.clone();
+                    let status = DeliveryStatus::from_result(request.send().await);
+                    (idx, recipient, status)
+                }
+            })
+            .buffer_unordered(concurrency)
+            .enumerate()
+            .map(move |(done, delivery)| {
+                if let Some(on_progress) = &on_progress {
+                    on_progress(BroadcastProgress {
+                        done: done + 1,
+                        total,
+                    });
+                }
+                delivery
+            })
+            .collect::<Vec<_>>();
+
+        let report = async move {
+            let mut deliveries = deliveries.await;
+            deliveries.sort_unstable_by_key(|&(idx, _, _)| idx);
+
+            let deliveries = deliveries
+                .into_iter()
+                .map(|(_, recipient, status)| Delivery { recipient, status })
+                .collect();
+
+            BroadcastReport { deliveries }
+        };
+
+        (report, token)
+    }
+}
+
+impl<R> fmt::Debug for Broadcast<R>
+where
+    R: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Broadcast")
+            .field("request", &self.request)
+            .field("recipients", &self.recipients)
+            .field("concurrency", &self.concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CancelToken {
+    /// Cancels the broadcast.
+    ///
+    /// Requests which are already being sent are completed, the rest of the
+    /// recipients are reported as [`DeliveryStatus::Cancelled`].
+    pub fn cancel(&self) {
+        // Error means that the broadcast was already dropped
+        let _ = self.tx.send(true);
+    }
+}
+
+impl<T> BroadcastReport<T> {
+    /// Returns results for all recipients, in the order they were passed to
+    /// [`Broadcast::new`].
+    pub fn deliveries(&self) -> &[Delivery<T>] {
+        &self.deliveries
+    }
+
+    /// Unwraps the results for all recipients.
+    pub fn into_deliveries(self) -> Vec<Delivery<T>> {
+        self.deliveries
+    }
+
+    /// Returns the number of recipients to which the request was successfully
+    /// sent.
+    pub fn sent(&self) -> usize {
+        self.deliveries
+            .iter()
+            .filter(|d| matches!(d.status, DeliveryStatus::Sent(_)))
+            .count()
+    }
+
+    /// Returns recipients which can't receive messages from the bot anymore,
+    /// i.e. which [blocked the bot], were [deactivated] or [not found].
+    ///
+    /// [blocked the bot]: DeliveryStatus::BotBlocked
+    /// [deactivated]: DeliveryStatus::UserDeactivated
+    /// [not found]: DeliveryStatus::ChatNotFound
+    pub fn unreachable(&self) -> impl Iterator<Item = &Recipient> {
+        self.deliveries
+            .iter()
+            .filter(|d| d.status.is_unreachable())
+            .map(|d| &d.recipient)
+    }
+
+    /// Returns groups which were migrated to supergroups, alongside with the
+    /// new identifiers.
+    pub fn migrated(&self) -> impl Iterator<Item = (&Recipient, ChatId)> {
+        self.deliveries.iter().filter_map(|d| match d.status {
+            DeliveryStatus::MigrateToChatId(id) => Some((&d.recipient, id)),
+            _ => None,
+        })
+    }
+
+    /// Returns recipients to which the request wasn't sent because of other
+    /// errors or because the broadcast was cancelled, i.e. recipients to which
+    /// it makes sense to try to send the request again.
+    pub fn retryable(&self) -> impl Iterator<Item = &Recipient> {
+        self.deliveries
+            .iter()
+            .filter(|d| {
+                matches!(
+                    d.status,
+                    DeliveryStatus::Failed(_) | DeliveryStatus::Cancelled
+                )
+            })
+            .map(|d| &d.recipient)
+    }
+}
+
+impl<T> DeliveryStatus<T> {
+    /// Returns `true` if the recipient can't receive messages from the bot
+    /// anymore.
+    pub fn is_unreachable(&self) -> bool {
+        matches!(
+            self,
+            Self::BotBlocked | Self::UserDeactivated | Self::ChatNotFound
+        )
+    }
+
+    fn from_result(res: Result<T, RequestError>) -> Self {
+        match res {
+            Ok(output) => Self::Sent(output),
+            Err(RequestError::MigrateToChatId(id)) => Self::MigrateToChatId(ChatId(id)),
+            Err(err) => match err.api_error() {
+                Some(ApiError::BotBlocked) => Self::BotBlocked,
+                Some(ApiError::UserDeactivated) => Self::UserDeactivated,
+                Some(ApiError::ChatNotFound) => Self::ChatNotFound,
+                _ => Self::Failed(err),
+            },
+        }
+    }
+}
+
+/// Payloads which can be used in a [`Broadcast`].
+///
+/// This is implemented for payloads of all methods which send a message to a
+/// chat specified by `chat_id`.
+pub trait BroadcastPayload: Payload {
+    /// Returns a mutable reference to the `chat_id` of the payload.
+    fn chat_id_mut(&mut self) -> &mut Recipient;
+}
+
+macro_rules! impl_broadcast_payload {
+    ($($T:ty),*) => {
+        $(
+            impl BroadcastPayload for $T {
+                fn chat_id_mut(&mut self) -> &mut Recipient {
+                    &mut self.chat_id
+                }
+            }
+        )*
+    };
+}
+
+impl_broadcast_payload!(
+    SendMessage,
+    ForwardMessage,
+    CopyMessage,
+    SendPhoto,
+    SendAudio,
+    SendDocument,
+    SendVideo,
+    SendAnimation,
+    SendVoice,
+    SendVideoNote,
+    SendMediaGroup,
+    SendLocation,
+    SendVenue,
+    SendContact,
+    SendPoll,
+    SendDice,
+    SendSticker,
+    SendInvoice
+);
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn report() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::{
+            requests::Requester,
+            testing::fake_server::{FakeError, FakeServer},
+            types::{ChatId, Recipient},
+            utils::broadcast::{Broadcast, DeliveryStatus},
+            ApiError,
+        };
+
+        let server = FakeServer::start().unwrap();
+        for id in 1..=3 {
+            server.add_private_chat(ChatId(id), "Alice");
+        }
+        server.fail_next("sendMessage", FakeError::Api(ApiError::BotBlocked));
+        server.fail_next("sendMessage", FakeError::MigrateToChatId(-100));
+        let bot = server.bot();
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let (broadcast, _) = Broadcast::new(
+            bot.send_message(ChatId(0), "hi"),
+            [ChatId(1), ChatId(2), ChatId(3), ChatId(4)],
+        )
+        .concurrency(1)
+        .on_progress({
+            let progress = Arc::clone(&progress);
+            move |p| progress.lock().unwrap().push((p.done, p.total))
+        })
+        .start();
+        let report = broadcast.await;
+
+        let statuses = report.deliveries();
+        assert!(matches!(statuses[0].status, DeliveryStatus::BotBlocked));
+        assert!(matches!(
+            statuses[1].status,
+            DeliveryStatus::MigrateToChatId(ChatId(-100))
+        ));
+        match &statuses[2].status {
+            DeliveryStatus::Sent(message) => assert_eq!(message.chat.id, ChatId(3)),
+            other => panic!("unexpected status: {:?}", other),
+        }
+        assert!(matches!(statuses[3].status, DeliveryStatus::ChatNotFound));
+
+        assert_eq!(report.sent(), 1);
+        assert_eq!(
+            report.unreachable().cloned().collect::<Vec<_>>(),
+            [Recipient::Id(ChatId(1)), Recipient::Id(ChatId(4))]
+        );
+        assert_eq!(*progress.lock().unwrap(), [(1, 4), (2, 4), (3, 4), (4, 4)]);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn cancel() {
+        use crate::{
+            requests::Requester,
+            testing::fake_server::FakeServer,
+            types::ChatId,
+            utils::broadcast::{Broadcast, DeliveryStatus},
+        };
+
+        let server = FakeServer::start().unwrap();
+        server.add_private_chat(ChatId(1), "Alice");
+        let bot = server.bot();
+
+        let (broadcast, cancel) =
+            Broadcast::new(bot.send_message(ChatId(0), "hi"), [ChatId(1)]).start();
+        cancel.cancel();
+        let report = broadcast.await;
+
+        assert!(matches!(
+            report.deliveries()[0].status,
+            DeliveryStatus::Cancelled
+        ));
+        assert_eq!(report.retryable().count(), 1);
+        assert!(server.requests().is_empty());
+    }
+}
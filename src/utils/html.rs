@@ -0,0 +1,646 @@
+//! Utilities for the [HTML] parse mode.
+//!
+//! Functions which format text (e.g. [`bold`]) accept text which is already
+//! escaped (with [`escape`]) or formatted, so they can be nested. Functions
+//! which produce code ([`code_inline`], [`code_block`] and
+//! [`code_block_with_lang`]) accept raw code and escape it themselves, since
+//! code can't contain other formatting.
+//!
+//! [`parse`] and [`render`] convert HTML strings into text with
+//! [`MessageEntity`]s and back.
+//!
+//! [HTML]: https://core.telegram.org/bots/api#html-style
+//!
+//! ## Examples
+//!
+//! ```
+//! use teloxide_core::{
+//!     types::UserId,
+//!     utils::html::{bold, code_inline, escape, italic, user_mention},
+//! };
+//!
+//! let text = format!(
+//!     "{}, {} {}",
+//!     user_mention(UserId(42), &escape("<Ferris>")),
+//!     bold(&italic(&escape("please run"))),
+//!     code_inline("a < b"),
+//! );
+//!
+//! assert_eq!(
+//!     text,
+//!     r#"<a href="tg://user/?id=42">&lt;Ferris&gt;</a>, <b><i>please run</i></b> <code>a &lt; b</code>"#
+//! );
+//! ```
+
+use crate::{
+    types::{MessageEntity, MessageEntityKind, UserId},
+    utils::{
+        parse::{parse_url, EntitiesBuilder, Start},
+        render::{self, Markup},
+        ParseError,
+    },
+};
+
+/// Applies the bold font style to the string.
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn bold(s: &str) -> String {
+    format!("<b>{}</b>", s)
+}
+
+/// Applies the italic font style to the string.
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn italic(s: &str) -> String {
+    format!("<i>{}</i>", s)
+}
+
+/// Applies the underline font style to the string.
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn underline(s: &str) -> String {
+    format!("<u>{}</u>", s)
+}
+
+/// Applies the strikethrough font style to the string.
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn strikethrough(s: &str) -> String {
+    format!("<s>{}</s>", s)
+}
+
+/// Hides the string under a spoiler.
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn spoiler(s: &str) -> String {
+    format!("<tg-spoiler>{}</tg-spoiler>", s)
+}
+
+/// Builds an inline link with an anchor.
+///
+/// The url is escaped (including `"`, since it's put into an attribute).
+///
+/// `text` will not be automatically escaped because it can contain nested
+/// markup.
+pub fn link(url: &str, text: &str) -> String {
+    format!("<a href=\"{}\">{}</a>", escape_attribute(url), text)
+}
+
+/// Builds an inline user mention link with an anchor.
+///
+/// Such mentions work even for users without usernames.
+///
+/// `text` will not be automatically escaped because it can contain nested
+/// markup.
+pub fn user_mention(user_id: UserId, text: &str) -> String {
+    link(user_id.url().as_str(), text)
+}
+
+/// Formats the code block.
+///
+/// Escapes HTML characters inside the block.
+pub fn code_block(code: &str) -> String {
+    format!("<pre>{}</pre>", escape(code))
+}
+
+/// Formats the code block with a specific language syntax.
+///
+/// Escapes HTML characters inside the block.
+pub fn code_block_with_lang(code: &str, lang: &str) -> String {
+    format!(
+        "<pre><code class=\"language-{}\">{}</code></pre>",
+        escape_attribute(lang),
+        escape(code)
+    )
+}
+
+/// Formats the string as an inline code.
+///
+/// Escapes HTML characters inside the string.
+pub fn code_inline(s: &str) -> String {
+    format!("<code>{}</code>", escape(s))
+}
+
+/// Escapes the string to be shown "as is" within the Telegram [HTML] message
+/// style.
+///
+/// Only `<`, `>` and `&` are escaped, as Telegram requires.
+///
+/// [HTML]: https://core.telegram.org/bots/api#html-style
+pub fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '&' => res.push_str("&amp;"),
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+/// Escapes the string to be used as a (double quoted) attribute value, e.g.
+/// the url of a link.
+pub fn escape_attribute(s: &str) -> String {
+    escape(s).replace('"', "&quot;")
+}
+
+/// Parses an [HTML] string into text and [`MessageEntity`]s, the same way
+/// Telegram does it.
+///
+/// Returns an error if the string is not correctly formatted, e.g. if it
+/// contains unsupported or unclosed tags.
+///
+/// [HTML]: https://core.telegram.org/bots/api#html-style
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     types::{MessageEntity, MessageEntityKind::*},
+///     utils::html,
+/// };
+///
+/// let (text, entities) = html::parse("<b>bold <i>italic bold</i></b> &lt;3").unwrap();
+///
+/// assert_eq!(text, "bold italic bold <3");
+/// assert_eq!(
+///     entities,
+///     [
+///         MessageEntity::new(Bold, 0, 16),
+///         MessageEntity::new(Italic, 5, 11)
+///     ]
+/// );
+///
+/// assert!(html::parse("<b>unclosed").is_err());
+/// ```
+pub fn parse(s: &str) -> Result<(String, Vec<MessageEntity>), ParseError> {
+    struct Open {
+        tag: String,
+        // `None` if the tag doesn't produce an entity
+        kind: Option<MessageEntityKind>,
+        start: Start,
+        // Offset of the tag in `s`
+        offset: usize,
+    }
+
+    let mut builder = EntitiesBuilder::default();
+    let mut stack: Vec<Open> = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = s[i..].chars().next() {
+        match c {
+            '&' => {
+                let (c, len) = char_reference(&s[i..]).unwrap_or(('&', 1));
+                builder.push(c);
+                i += len;
+            }
+            '<' if s[i..].starts_with("</") => {
+                let end = s[i..]
+                    .find('>')
+                    .ok_or_else(|| ParseError::new(i, "Unclosed end tag"))?;
+                let tag = s[i + 2..i + end].trim().to_ascii_lowercase();
+
+                let open = match stack.pop() {
+                    Some(open) if open.tag == tag => open,
+                    Some(open) => {
+                        return Err(ParseError::new(
+                            i,
+                            format!(
+                                "Unmatched end tag, expected \"</{}>\", found \"</{}>\"",
+                                open.tag, tag
+                            ),
+                        ))
+                    }
+                    None => {
+                        return Err(ParseError::new(
+                            i,
+                            format!("Unexpected end tag \"</{}>\"", tag),
+                        ))
+                    }
+                };
+
+                match (open.kind, stack.last_mut()) {
+                    // `<pre><code class="language-...">` specifies the language of
+                    // the code block
+                    (
+                        Some(kind @ (MessageEntityKind::Code | MessageEntityKind::Pre { .. })),
+                        Some(parent),
+                    ) if open.tag == "code"
+                        && parent.tag == "pre"
+                        && parent.start.offset == open.start.offset =>
+                    {
+                        if let MessageEntityKind::Pre { .. } = kind {
+                            parent.kind = Some(kind);
+                        }
+                    }
+                    (Some(MessageEntityKind::Pre { .. }), _) if open.tag == "code" => {
+                        builder.close(open.start, MessageEntityKind::Code)
+                    }
+                    (Some(kind), _) => builder.close(open.start, kind),
+                    (None, _) => {}
+                }
+
+                i += end + 1;
+            }
+            '<' => {
+                let (tag, kind, end) = parse_start_tag(s, i)?;
+                stack.push(Open {
+                    tag,
+                    kind,
+                    start: builder.open(),
+                    offset: i,
+                });
+                i = end;
+            }
+            c => {
+                builder.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+
+    match stack.pop() {
+        Some(open) => Err(ParseError::new(
+            open.offset,
+            format!(
+                "Can't find end tag corresponding to start tag \"{}\"",
+                open.tag
+            ),
+        )),
+        None => Ok(builder.finish()),
+    }
+}
+
+/// Renders text with [`MessageEntity`]s into an [HTML] string.
+///
+/// Entities which Telegram detects automatically (mentions, hashtags, urls,
+/// etc) are rendered as plain text.
+///
+/// [HTML]: https://core.telegram.org/bots/api#html-style
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     types::{MessageEntity, MessageEntityKind::*},
+///     utils::html,
+/// };
+///
+/// let entities = [
+///     MessageEntity::new(Bold, 0, 4),
+///     MessageEntity::new(Code, 5, 5),
+/// ];
+///
+/// assert_eq!(
+///     html::render("bold a < b", &entities),
+///     "<b>bold</b> <code>a &lt; b</code>"
+/// );
+/// ```
+pub fn render(text: &str, entities: &[MessageEntity]) -> String {
+    render::render::<Html>(text, entities)
+}
+
+/// Parses a start tag at the byte offset `i`, returns name of the tag, kind of
+/// the entity it produces and offset of the end of the tag.
+fn parse_start_tag(
+    s: &str,
+    i: usize,
+) -> Result<(String, Option<MessageEntityKind>, usize), ParseError> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    let skip_whitespace = |j: usize| j + s[j..].len() - s[j..].trim_start().len();
+
+    let name_len = s[i + 1..]
+        .find(|c| !is_name_char(c))
+        .unwrap_or(s.len() - i - 1);
+    let tag = s[i + 1..i + 1 + name_len].to_ascii_lowercase();
+
+    let mut attributes = Vec::new();
+    let mut j = i + 1 + name_len;
+    loop {
+        j = skip_whitespace(j);
+        match s[j..].chars().next() {
+            None => return Err(ParseError::new(i, "Unclosed start tag")),
+            Some('>') => break,
+            Some(_) => {}
+        }
+
+        let attr_len = s[j..].find(|c| !is_name_char(c)).unwrap_or(s.len() - j);
+        if attr_len == 0 {
+            return Err(ParseError::new(j, "Expected an attribute name"));
+        }
+        let attr = s[j..j + attr_len].to_ascii_lowercase();
+        j = skip_whitespace(j + attr_len);
+
+        let mut value = String::new();
+        if s[j..].starts_with('=') {
+            j = skip_whitespace(j + 1);
+            let raw = match s[j..].chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let len = s[j + 1..]
+                        .find(quote)
+                        .ok_or_else(|| ParseError::new(j, "Unclosed attribute value"))?;
+                    let raw = &s[j + 1..j + 1 + len];
+                    j += len + 2;
+                    raw
+                }
+                _ => {
+                    let len = s[j..]
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(s.len() - j);
+                    let raw = &s[j..j + len];
+                    j += len;
+                    raw
+                }
+            };
+            value = unescape(raw);
+        }
+
+        attributes.push((attr, value));
+    }
+
+    let attribute = |name: &str| {
+        attributes
+            .iter()
+            .find(|(attr, _)| attr == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    use MessageEntityKind::*;
+    let kind = match tag.as_str() {
+        "b" | "strong" => Some(Bold),
+        "i" | "em" => Some(Italic),
+        "u" | "ins" => Some(Underline),
+        "s" | "strike" | "del" => Some(Strikethrough),
+        "tg-spoiler" => Some(Spoiler),
+        "span" if attribute("class") == Some("tg-spoiler") => Some(Spoiler),
+        "span" => {
+            return Err(ParseError::new(
+                i,
+                "Tag \"span\" must have class \"tg-spoiler\"",
+            ))
+        }
+        // Like Telegram, ignore links with invalid urls
+        "a" => attribute("href")
+            .and_then(parse_url)
+            .map(|url| TextLink { url }),
+        // The language is only used for `<pre><code class="language-...">`
+        "code" => match attribute("class").and_then(|c| c.strip_prefix("language-")) {
+            Some(language) => Some(Pre {
+                language: Some(language.to_owned()),
+            }),
+            None => Some(Code),
+        },
+        "pre" => Some(Pre { language: None }),
+        _ => {
+            return Err(ParseError::new(
+                i,
+                format!("Unsupported start tag \"{}\"", tag),
+            ))
+        }
+    };
+
+    Ok((tag, kind, j + 1))
+}
+
+/// Replaces character references (e.g. `&lt;`) with the characters.
+fn unescape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut i = 0;
+
+    while let Some(c) = s[i..].chars().next() {
+        let (c, len) = match c {
+            '&' => char_reference(&s[i..]).unwrap_or(('&', 1)),
+            c => (c, c.len_utf8()),
+        };
+        res.push(c);
+        i += len;
+    }
+
+    res
+}
+
+/// Parses a character reference at the start of `s`, returns the character
+/// and length of the reference.
+fn char_reference(s: &str) -> Option<(char, usize)> {
+    let end = s.find(';')?;
+    let c = match &s[1..end] {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "quot" => '"',
+        name => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+
+    Some((c, end + 1))
+}
+
+struct Html;
+
+impl Markup for Html {
+    fn escape(text: &str, _in_code: bool) -> String {
+        escape(text)
+    }
+
+    fn open(kind: &MessageEntityKind) -> Option<String> {
+        use MessageEntityKind::*;
+
+        let tag = match kind {
+            Bold => "<b>".to_owned(),
+            Italic => "<i>".to_owned(),
+            Underline => "<u>".to_owned(),
+            Strikethrough => "<s>".to_owned(),
+            Spoiler => "<tg-spoiler>".to_owned(),
+            Code => "<code>".to_owned(),
+            Pre { language: None } => "<pre>".to_owned(),
+            Pre {
+                language: Some(language),
+            } => format!(
+                "<pre><code class=\"language-{}\">",
+                escape_attribute(language)
+            ),
+            TextLink { url } => format!("<a href=\"{}\">", escape_attribute(url.as_str())),
+            TextMention { user } => {
+                format!("<a href=\"{}\">", escape_attribute(user.url().as_str()))
+            }
+            Mention | Hashtag | Cashtag | BotCommand | Url | Email | PhoneNumber => return None,
+        };
+
+        Some(tag)
+    }
+
+    fn close(kind: &MessageEntityKind) -> String {
+        use MessageEntityKind::*;
+
+        let tag = match kind {
+            Bold => "</b>",
+            Italic => "</i>",
+            Underline => "</u>",
+            Strikethrough => "</s>",
+            Spoiler => "</tg-spoiler>",
+            Code => "</code>",
+            Pre { language: None } => "</pre>",
+            Pre { language: Some(_) } => "</code></pre>",
+            TextLink { .. } | TextMention { .. } => "</a>",
+            Mention | Hashtag | Cashtag | BotCommand | Url | Email | PhoneNumber => "",
+        };
+
+        tag.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FormattedText;
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            escape("  <title>Foo & Bar</title>   "),
+            "  &lt;title&gt;Foo &amp; Bar&lt;/title&gt;   "
+        );
+        assert_eq!(escape(r#"<p>"foo"</p>"#), r#"&lt;p&gt;"foo"&lt;/p&gt;"#);
+        assert_eq!(escape_attribute(r#"a"b&c"#), "a&quot;b&amp;c");
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(bold(" foobar "), "<b> foobar </b>");
+        assert_eq!(italic(&underline("foo")), "<i><u>foo</u></i>");
+        assert_eq!(strikethrough("foo"), "<s>foo</s>");
+        assert_eq!(spoiler("foo"), "<tg-spoiler>foo</tg-spoiler>");
+
+        assert_eq!(
+            link("https://www.google.com/?q=\"a\"&b", "google"),
+            "<a href=\"https://www.google.com/?q=&quot;a&quot;&amp;b\">google</a>"
+        );
+        assert_eq!(
+            user_mention(UserId(123456789), "pwner666"),
+            "<a href=\"tg://user/?id=123456789\">pwner666</a>"
+        );
+
+        assert_eq!(code_inline("a < b"), "<code>a &lt; b</code>");
+        assert_eq!(code_block("a & b"), "<pre>a &amp; b</pre>");
+        assert_eq!(
+            code_block_with_lang("Vec<u8>", "rust"),
+            "<pre><code class=\"language-rust\">Vec&lt;u8&gt;</code></pre>"
+        );
+    }
+
+    #[test]
+    fn parsing() {
+        use MessageEntityKind::*;
+
+        let (text, entities) = parse(
+            "<b>b <I>i <u>u</u> <s>s</s> <span class=\"tg-spoiler\">sp</span></i ></b> \
+             <code>c</code> <a href='https://e.com/?a=1&amp;b=2'>l</a> &lt;&#62;&#x26;&quot;&nbsp",
+        )
+        .unwrap();
+
+        assert_eq!(text, "b i u s sp c l <>&\"&nbsp");
+        assert_eq!(
+            entities,
+            [
+                MessageEntity::new(Bold, 0, 10),
+                MessageEntity::new(Italic, 2, 8),
+                MessageEntity::new(Underline, 4, 1),
+                MessageEntity::new(Strikethrough, 6, 1),
+                MessageEntity::new(Spoiler, 8, 2),
+                MessageEntity::new(Code, 11, 1),
+                MessageEntity::new(
+                    TextLink {
+                        url: "https://e.com/?a=1&b=2".parse().unwrap()
+                    },
+                    13,
+                    1
+                ),
+            ]
+        );
+
+        let pre = |language: Option<&str>| {
+            MessageEntity::new(
+                Pre {
+                    language: language.map(<_>::to_owned),
+                },
+                0,
+                1,
+            )
+        };
+        assert_eq!(
+            parse("<pre><code class=\"language-rust\">x</code></pre>").unwrap(),
+            ("x".to_owned(), vec![pre(Some("rust"))])
+        );
+        assert_eq!(
+            parse("<pre>x</pre>").unwrap(),
+            ("x".to_owned(), vec![pre(None)])
+        );
+    }
+
+    #[test]
+    fn parsing_errors() {
+        let err = parse("<b>a</i>").unwrap_err();
+        assert_eq!(err.offset(), 4);
+        assert_eq!(
+            err.message(),
+            "Unmatched end tag, expected \"</b>\", found \"</i>\""
+        );
+
+        assert_eq!(parse("a <b>b").unwrap_err().offset(), 2);
+        assert!(parse("a<br>b").is_err());
+        assert!(parse("<span>a</span>").is_err());
+        assert!(parse("a</b>").is_err());
+        assert!(parse("<a href=\"b>a</a>").is_err());
+    }
+
+    #[test]
+    fn rendering() {
+        use MessageEntityKind::*;
+
+        // Intersecting entities are split
+        assert_eq!(
+            render(
+                "abcd",
+                &[
+                    MessageEntity::new(Bold, 0, 3),
+                    MessageEntity::new(Italic, 1, 3)
+                ]
+            ),
+            "<b>a<i>bc</i></b><i>d</i>"
+        );
+
+        // Entities detected by Telegram are not rendered
+        assert_eq!(
+            render("@user <3", &[MessageEntity::new(Mention, 0, 5)]),
+            "@user &lt;3"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let (text, entities) = FormattedText::new()
+            .bold(FormattedText::new().italic(FormattedText::new().underline("🦀 crab")))
+            .plain(" <&> ")
+            .link("link", "https://example.com/?a=\"1\"&b".parse().unwrap())
+            .plain(" ")
+            .pre("fn main() { \"<>\" }", Some("rust".to_owned()))
+            .into_parts();
+
+        let rendered = render(&text, &entities);
+        assert_eq!(parse(&rendered).unwrap(), (text, entities));
+    }
+}
@@ -0,0 +1,685 @@
+//! Utilities for the [MarkdownV2] parse mode.
+//!
+//! Functions which format text (e.g. [`bold`]) accept text which is already
+//! escaped (with [`escape`]) or formatted, so they can be nested. Functions
+//! which produce code ([`code_inline`], [`code_block`] and
+//! [`code_block_with_lang`]) accept raw code and escape it themselves, since
+//! code can't contain other formatting.
+//!
+//! [`parse`] and [`render`] convert MarkdownV2 strings into text with
+//! [`MessageEntity`]s and back.
+//!
+//! [MarkdownV2]: https://core.telegram.org/bots/api#markdownv2-style
+//!
+//! ## Examples
+//!
+//! ```
+//! use teloxide_core::{
+//!     types::UserId,
+//!     utils::markdown::{bold, code_inline, escape, italic, user_mention},
+//! };
+//!
+//! let text = format!(
+//!     "{}, {} {}",
+//!     user_mention(UserId(42), &escape("Mr. Ferris")),
+//!     bold(&italic(&escape("please run"))),
+//!     code_inline("cargo fmt"),
+//! );
+//!
+//! assert_eq!(
+//!     text,
+//!     r"[Mr\. Ferris](tg://user/?id=42), *_please run_* `cargo fmt`"
+//! );
+//! ```
+
+use crate::{
+    types::{MessageEntity, MessageEntityKind, UserId},
+    utils::{
+        parse::{parse_url, EntitiesBuilder, Start},
+        render::{self, Markup},
+        ParseError,
+    },
+};
+
+/// Applies the bold font style to the string.
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn bold(s: &str) -> String {
+    format!("*{}*", s)
+}
+
+/// Applies the italic font style to the string.
+///
+/// Can be safely used with [`underline`].
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn italic(s: &str) -> String {
+    // See the comment in `underline`
+    if s.starts_with("__") && s.ends_with("__") {
+        format!("_{}\r__", &s[..s.len() - 1])
+    } else {
+        format!("_{}_", s)
+    }
+}
+
+/// Applies the underline font style to the string.
+///
+/// Can be safely used with [`italic`].
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn underline(s: &str) -> String {
+    // In case of ambiguity between italic and underline entities `__` is
+    // always greedily treated from left to right as beginning or end of
+    // underline entity, so instead of `___italic underline___` we should use
+    // `___italic underline_\r__`, where `\r` is a character with code 13,
+    // which will be ignored.
+    if s.starts_with('_') && s.ends_with('_') {
+        format!("__{}\r__", s)
+    } else {
+        format!("__{}__", s)
+    }
+}
+
+/// Applies the strikethrough font style to the string.
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn strikethrough(s: &str) -> String {
+    format!("~{}~", s)
+}
+
+/// Hides the string under a spoiler.
+///
+/// Passed string will not be automatically escaped because it can contain
+/// nested markup.
+pub fn spoiler(s: &str) -> String {
+    format!("||{}||", s)
+}
+
+/// Builds an inline link with an anchor.
+///
+/// Escapes `)` and `\` characters inside the link url.
+///
+/// `text` will not be automatically escaped because it can contain nested
+/// markup.
+pub fn link(url: &str, text: &str) -> String {
+    format!("[{}]({})", text, escape_link_url(url))
+}
+
+/// Builds an inline user mention link with an anchor.
+///
+/// Such mentions work even for users without usernames.
+///
+/// `text` will not be automatically escaped because it can contain nested
+/// markup.
+pub fn user_mention(user_id: UserId, text: &str) -> String {
+    link(user_id.url().as_str(), text)
+}
+
+/// Formats the code block.
+///
+/// Escapes `` ` `` and `\` characters inside the block.
+pub fn code_block(code: &str) -> String {
+    format!("```\n{}\n```", escape_code(code))
+}
+
+/// Formats the code block with a specific language syntax.
+///
+/// Escapes `` ` `` and `\` characters inside the block.
+pub fn code_block_with_lang(code: &str, lang: &str) -> String {
+    format!("```{}\n{}\n```", escape_code(lang), escape_code(code))
+}
+
+/// Formats the string as an inline code.
+///
+/// Escapes `` ` `` and `\` characters inside the string.
+pub fn code_inline(s: &str) -> String {
+    format!("`{}`", escape_code(s))
+}
+
+/// Escapes the string to be shown "as is" within the Telegram [MarkdownV2]
+/// message style.
+///
+/// [MarkdownV2]: https://core.telegram.org/bots/api#markdownv2-style
+pub fn escape(s: &str) -> String {
+    escape_chars(s, |c| {
+        matches!(
+            c,
+            '_' | '*'
+                | '['
+                | ']'
+                | '('
+                | ')'
+                | '~'
+                | '`'
+                | '>'
+                | '#'
+                | '+'
+                | '-'
+                | '='
+                | '|'
+                | '{'
+                | '}'
+                | '.'
+                | '!'
+                | '\\'
+        )
+    })
+}
+
+/// Escapes all markdown special characters specific for the inline link URL
+/// (`)` and `\`).
+pub fn escape_link_url(s: &str) -> String {
+    escape_chars(s, |c| matches!(c, ')' | '\\'))
+}
+
+/// Escapes all markdown special characters specific for the code block
+/// (`` ` `` and `\`).
+pub fn escape_code(s: &str) -> String {
+    escape_chars(s, |c| matches!(c, '`' | '\\'))
+}
+
+fn escape_chars(s: &str, special: impl Fn(char) -> bool) -> String {
+    let mut res = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        if special(c) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+
+    res
+}
+
+/// Parses a [MarkdownV2] string into text and [`MessageEntity`]s, the same
+/// way Telegram does it.
+///
+/// Returns an error if the string is not correctly formatted, e.g. if it
+/// contains unescaped reserved characters or unclosed entities.
+///
+/// [MarkdownV2]: https://core.telegram.org/bots/api#markdownv2-style
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     types::{MessageEntity, MessageEntityKind::*},
+///     utils::markdown,
+/// };
+///
+/// let (text, entities) = markdown::parse(r"*bold _italic bold_* \*").unwrap();
+///
+/// assert_eq!(text, "bold italic bold *");
+/// assert_eq!(
+///     entities,
+///     [
+///         MessageEntity::new(Bold, 0, 16),
+///         MessageEntity::new(Italic, 5, 11)
+///     ]
+/// );
+///
+/// assert!(markdown::parse("1. *unclosed").is_err());
+/// ```
+pub fn parse(s: &str) -> Result<(String, Vec<MessageEntity>), ParseError> {
+    #[derive(PartialEq)]
+    enum Marker {
+        Bold,
+        Italic,
+        Underline,
+        Strikethrough,
+        Spoiler,
+        Link,
+    }
+
+    let mut builder = EntitiesBuilder::default();
+    // Opened entities along with their offsets in `s`
+    let mut stack: Vec<(Marker, Start, usize)> = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = s[i..].chars().next() {
+        if let Some(c) = escaped(s, i) {
+            builder.push(c);
+            i += 2;
+            continue;
+        }
+
+        let (marker, len) = match c {
+            // `\r` is ignored, this allows to separate `_` and `__`
+            '\r' => {
+                i += 1;
+                continue;
+            }
+            '`' => {
+                i = parse_code(s, i, &mut builder)?;
+                continue;
+            }
+            ']' if matches!(stack.last(), Some((Marker::Link, ..))) => {
+                let (_, start, _) = stack.pop().unwrap();
+                i = parse_link_url(s, i + 1, start, &mut builder)?;
+                continue;
+            }
+            '*' => (Marker::Bold, 1),
+            '_' if s[i..].starts_with("__") => (Marker::Underline, 2),
+            '_' => (Marker::Italic, 1),
+            '~' => (Marker::Strikethrough, 1),
+            '|' if s[i..].starts_with("||") => (Marker::Spoiler, 2),
+            '[' => (Marker::Link, 1),
+            c if RESERVED.contains(c) => {
+                return Err(ParseError::new(
+                    i,
+                    format!(
+                        "Character '{}' is reserved and must be escaped with the preceding '\\'",
+                        c
+                    ),
+                ))
+            }
+            c => {
+                builder.push(c);
+                i += c.len_utf8();
+                continue;
+            }
+        };
+
+        match stack.last() {
+            // Only the innermost entity can be closed
+            Some((top, ..)) if *top == marker && marker != Marker::Link => {
+                let (_, start, _) = stack.pop().unwrap();
+                let kind = match marker {
+                    Marker::Bold => MessageEntityKind::Bold,
+                    Marker::Italic => MessageEntityKind::Italic,
+                    Marker::Underline => MessageEntityKind::Underline,
+                    Marker::Strikethrough => MessageEntityKind::Strikethrough,
+                    Marker::Spoiler => MessageEntityKind::Spoiler,
+                    Marker::Link => unreachable!(),
+                };
+                builder.close(start, kind);
+            }
+            _ => stack.push((marker, builder.open(), i)),
+        }
+        i += len;
+    }
+
+    match stack.pop() {
+        Some((marker, _, offset)) => {
+            let name = match marker {
+                Marker::Bold => "bold",
+                Marker::Italic => "italic",
+                Marker::Underline => "underline",
+                Marker::Strikethrough => "strikethrough",
+                Marker::Spoiler => "spoiler",
+                Marker::Link => "text URL",
+            };
+            Err(ParseError::new(
+                offset,
+                format!("Can't find end of {} entity", name),
+            ))
+        }
+        None => Ok(builder.finish()),
+    }
+}
+
+/// Renders text with [`MessageEntity`]s into a [MarkdownV2] string.
+///
+/// Entities which Telegram detects automatically (mentions, hashtags, urls,
+/// etc) are rendered as plain text.
+///
+/// [MarkdownV2]: https://core.telegram.org/bots/api#markdownv2-style
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     types::{MessageEntity, MessageEntityKind::*},
+///     utils::markdown,
+/// };
+///
+/// let entities = [
+///     MessageEntity::new(Bold, 0, 4),
+///     MessageEntity::new(Code, 5, 3),
+/// ];
+///
+/// assert_eq!(markdown::render("bold a.b", &entities), "*bold* `a.b`");
+/// ```
+pub fn render(text: &str, entities: &[MessageEntity]) -> String {
+    render::render::<MarkdownV2>(text, entities)
+}
+
+const RESERVED: &str = "_*[]()~`>#+-=|{}.!";
+
+/// Returns escaped char if there is `\` followed by an escapable char at the
+/// byte offset `i`.
+fn escaped(s: &str, i: usize) -> Option<char> {
+    let mut chars = s[i..].chars();
+
+    match (chars.next(), chars.next()) {
+        // Any character with code between 1 and 126 can be escaped
+        (Some('\\'), Some(c)) if ('\u{1}'..='\u{7e}').contains(&c) => Some(c),
+        _ => None,
+    }
+}
+
+/// Parses inline code or a code block starting at the byte offset `start`,
+/// returns offset of the end of the entity.
+fn parse_code(s: &str, start: usize, builder: &mut EntitiesBuilder) -> Result<usize, ParseError> {
+    let pre = s[start..].starts_with("```");
+    let closing = if pre { "```" } else { "`" };
+    let mut i = start + closing.len();
+
+    let mut language = None;
+    if pre {
+        // The language is specified directly after the opening
+        let rest = &s[i..];
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '`')
+            .unwrap_or(rest.len());
+        if end != 0 && rest[end..].starts_with(char::is_whitespace) {
+            language = Some(rest[..end].to_owned());
+            i += end;
+        }
+
+        // One new line after the opening is skipped
+        if s[i..].starts_with("\r\n") {
+            i += 2;
+        } else if s[i..].starts_with('\n') {
+            i += 1;
+        }
+    }
+
+    let entity = builder.open();
+    while let Some(c) = s[i..].chars().next() {
+        if let Some(c) = escaped(s, i) {
+            builder.push(c);
+            i += 2;
+        } else if s[i..].starts_with(closing) {
+            let kind = match pre {
+                true => MessageEntityKind::Pre { language },
+                false => MessageEntityKind::Code,
+            };
+            builder.close(entity, kind);
+
+            return Ok(i + closing.len());
+        } else {
+            builder.push(c);
+            i += c.len_utf8();
+        }
+    }
+
+    let name = if pre { "pre" } else { "code" };
+    Err(ParseError::new(
+        start,
+        format!("Can't find end of {} entity", name),
+    ))
+}
+
+/// Parses url of a link which text ends at the byte offset `i`, returns
+/// offset of the end of the link.
+fn parse_link_url(
+    s: &str,
+    mut i: usize,
+    start: Start,
+    builder: &mut EntitiesBuilder,
+) -> Result<usize, ParseError> {
+    let url = if s[i..].starts_with('(') {
+        let open = i;
+        let mut url = String::new();
+        i += 1;
+
+        loop {
+            match s[i..].chars().next() {
+                None => return Err(ParseError::new(open, "Can't find end of a URL")),
+                Some(')') => {
+                    i += 1;
+                    break url;
+                }
+                Some(c) => match escaped(s, i) {
+                    Some(c) => {
+                        url.push(c);
+                        i += 2;
+                    }
+                    None => {
+                        url.push(c);
+                        i += c.len_utf8();
+                    }
+                },
+            }
+        }
+    } else {
+        // Without an url, the text of the link is used as the url
+        builder.text_since(&start).to_owned()
+    };
+
+    // Like Telegram, ignore links with invalid urls
+    if let Some(url) = parse_url(&url) {
+        builder.close(start, MessageEntityKind::TextLink { url });
+    }
+
+    Ok(i)
+}
+
+struct MarkdownV2;
+
+impl Markup for MarkdownV2 {
+    fn escape(text: &str, in_code: bool) -> String {
+        match in_code {
+            true => escape_code(text),
+            false => escape(text),
+        }
+    }
+
+    fn open(kind: &MessageEntityKind) -> Option<String> {
+        use MessageEntityKind::*;
+
+        let tag = match kind {
+            Bold => "*",
+            Italic => "_",
+            Underline => "__",
+            Strikethrough => "~",
+            Spoiler => "||",
+            Code => "`",
+            Pre { language } => {
+                return Some(format!(
+                    "```{}\n",
+                    escape_code(language.as_deref().unwrap_or_default())
+                ))
+            }
+            TextLink { .. } | TextMention { .. } => "[",
+            Mention | Hashtag | Cashtag | BotCommand | Url | Email | PhoneNumber => return None,
+        };
+
+        Some(tag.to_owned())
+    }
+
+    fn close(kind: &MessageEntityKind) -> String {
+        use MessageEntityKind::*;
+
+        match kind {
+            Bold => "*".to_owned(),
+            Italic => "_".to_owned(),
+            Underline => "__".to_owned(),
+            Strikethrough => "~".to_owned(),
+            Spoiler => "||".to_owned(),
+            Code => "`".to_owned(),
+            Pre { .. } => "```".to_owned(),
+            TextLink { url } => format!("]({})", escape_link_url(url.as_str())),
+            TextMention { user } => format!("]({})", escape_link_url(user.url().as_str())),
+            Mention | Hashtag | Cashtag | BotCommand | Url | Email | PhoneNumber => String::new(),
+        }
+    }
+
+    fn push_tag(out: &mut String, tag: &str) {
+        // `_` and `__` next to each other are ambiguous, so they are separated
+        // by `\r` which is ignored by Telegram (see the comment in `underline`)
+        if out.ends_with('_') && tag.starts_with('_') {
+            out.push('\r');
+        }
+        out.push_str(tag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FormattedText;
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape("* foo*"), r"\* foo\*");
+        assert_eq!(
+            escape(r"_*[]()~`>#+-=|{}.!\"),
+            r"\_\*\[\]\(\)\~\`\>\#\+\-\=\|\{\}\.\!\\"
+        );
+        assert_eq!(escape("no special"), "no special");
+
+        assert_eq!(escape_code(r"print(`\n`)"), r"print(\`\\n\`)");
+        assert_eq!(
+            escape_link_url(r"https://a.b/(c)\d_e"),
+            r"https://a.b/(c\)\\d_e"
+        );
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(bold(" foobar "), "* foobar *");
+        assert_eq!(strikethrough("foo"), "~foo~");
+        assert_eq!(spoiler("foo"), "||foo||");
+        assert_eq!(italic(" foobar "), "_ foobar _");
+        assert_eq!(underline(" foobar "), "__ foobar __");
+
+        assert_eq!(italic(&underline("foo")), "___foo_\r__");
+        assert_eq!(underline(&italic("foo")), "___foo_\r__");
+    }
+
+    #[test]
+    fn links_and_code() {
+        assert_eq!(
+            link("https://www.google.com/(q=1)", &escape("google.com")),
+            r"[google\.com](https://www.google.com/(q=1\))"
+        );
+        assert_eq!(
+            user_mention(UserId(123456789), "pwner666"),
+            "[pwner666](tg://user/?id=123456789)"
+        );
+
+        assert_eq!(code_inline("foo`bar"), r"`foo\`bar`");
+        assert_eq!(
+            code_block("fn main() {\n    println!(\"\\`\");\n}"),
+            "```\nfn main() {\n    println!(\"\\\\\\`\");\n}\n```"
+        );
+        assert_eq!(code_block_with_lang("a_b", "rust"), "```rust\na_b\n```");
+    }
+
+    #[test]
+    fn parsing() {
+        use MessageEntityKind::*;
+
+        let (text, entities) =
+            parse(r"*b _i __u__ ~s~ ||sp||_* `c\`` [l](https://e.com/\)) \. \\").unwrap();
+
+        assert_eq!(text, r"b i u s sp c` l . \");
+        assert_eq!(
+            entities,
+            [
+                MessageEntity::new(Bold, 0, 10),
+                MessageEntity::new(Italic, 2, 8),
+                MessageEntity::new(Underline, 4, 1),
+                MessageEntity::new(Strikethrough, 6, 1),
+                MessageEntity::new(Spoiler, 8, 2),
+                MessageEntity::new(Code, 11, 2),
+                MessageEntity::new(
+                    TextLink {
+                        url: "https://e.com/)".parse().unwrap()
+                    },
+                    14,
+                    1
+                ),
+            ]
+        );
+
+        let pre = |language: Option<&str>, length| {
+            MessageEntity::new(
+                Pre {
+                    language: language.map(<_>::to_owned),
+                },
+                0,
+                length,
+            )
+        };
+        assert_eq!(
+            parse("```rust\nfn main() {}\n```").unwrap(),
+            ("fn main() {}\n".to_owned(), vec![pre(Some("rust"), 13)])
+        );
+        assert_eq!(
+            parse("```\n*x*```").unwrap(),
+            ("*x*".to_owned(), vec![pre(None, 3)])
+        );
+
+        assert_eq!(
+            parse("___a_\r__").unwrap(),
+            (
+                "a".to_owned(),
+                vec![
+                    MessageEntity::new(Underline, 0, 1),
+                    MessageEntity::new(Italic, 0, 1)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn parsing_errors() {
+        let err = parse("a.b").unwrap_err();
+        assert_eq!(err.offset(), 1);
+        assert_eq!(
+            err.message(),
+            r"Character '.' is reserved and must be escaped with the preceding '\'"
+        );
+
+        assert_eq!(parse("a *b").unwrap_err().offset(), 2);
+        assert_eq!(parse("*a _b*").unwrap_err().offset(), 5);
+        assert!(parse("`a").is_err());
+        assert!(parse("```a").is_err());
+        assert!(parse("[a](b").is_err());
+        assert!(parse("a]").is_err());
+    }
+
+    #[test]
+    fn rendering() {
+        use MessageEntityKind::*;
+
+        // Intersecting entities are split
+        assert_eq!(
+            render(
+                "abcd",
+                &[
+                    MessageEntity::new(Bold, 0, 3),
+                    MessageEntity::new(Italic, 1, 3)
+                ]
+            ),
+            "*a_bc_*_d_"
+        );
+
+        // Entities detected by Telegram are not rendered
+        assert_eq!(
+            render("@user #tag", &[MessageEntity::new(Mention, 0, 5)]),
+            r"@user \#tag"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let (text, entities) = FormattedText::new()
+            .bold(FormattedText::new().italic(FormattedText::new().underline("🦀 crab")))
+            .plain(" (1+1=2) ")
+            .link("link", "https://example.com/a_(b)".parse().unwrap())
+            .plain(" ")
+            .pre("fn main() { `\\` }", Some("rust".to_owned()))
+            .into_parts();
+
+        let rendered = render(&text, &entities);
+        assert_eq!(parse(&rendered).unwrap(), (text, entities));
+    }
+}
@@ -0,0 +1,88 @@
+use std::cmp::Reverse;
+
+use reqwest::Url;
+
+use crate::types::{MessageEntity, MessageEntityKind};
+
+/// Accumulates text and entities produced by a parser.
+#[derive(Default)]
+pub(crate) struct EntitiesBuilder {
+    text: String,
+    // Length of `text` in UTF-16 code units
+    utf16_len: usize,
+    // Entities along with the order in which they were opened
+    entities: Vec<(usize, MessageEntity)>,
+    opened: usize,
+}
+
+/// Start of an entity, returned by [`EntitiesBuilder::open`].
+pub(crate) struct Start {
+    pub(crate) offset: usize,
+    order: usize,
+}
+
+impl EntitiesBuilder {
+    pub(crate) fn push(&mut self, c: char) {
+        self.text.push(c);
+        self.utf16_len += c.len_utf16();
+    }
+
+    /// Returns text accumulated since the `start`.
+    pub(crate) fn text_since(&self, start: &Start) -> &str {
+        // `text` is sliced by UTF-16 offset, so count back from the end
+        let mut len = self.utf16_len - start.offset;
+        let mut idx = self.text.len();
+        for c in self.text.chars().rev() {
+            if len == 0 {
+                break;
+            }
+            len -= c.len_utf16();
+            idx -= c.len_utf8();
+        }
+
+        &self.text[idx..]
+    }
+
+    /// Marks the start of an entity at the current position.
+    pub(crate) fn open(&mut self) -> Start {
+        let order = self.opened;
+        self.opened += 1;
+
+        Start {
+            offset: self.utf16_len,
+            order,
+        }
+    }
+
+    /// Adds an entity which spans from the `start` to the current position.
+    ///
+    /// Empty entities are ignored, like Telegram does.
+    pub(crate) fn close(&mut self, start: Start, kind: MessageEntityKind) {
+        let length = self.utf16_len - start.offset;
+        if length != 0 {
+            self.entities
+                .push((start.order, MessageEntity::new(kind, start.offset, length)));
+        }
+    }
+
+    /// Returns the text and the entities sorted by offset, with outer entities
+    /// going first.
+    pub(crate) fn finish(mut self) -> (String, Vec<MessageEntity>) {
+        self.entities
+            .sort_by_key(|(order, e)| (e.offset, Reverse(e.length), *order));
+
+        (
+            self.text,
+            self.entities.into_iter().map(|(_, e)| e).collect(),
+        )
+    }
+}
+
+/// Parses url of a link, urls without a scheme are treated as `http` urls.
+pub(crate) fn parse_url(url: &str) -> Option<Url> {
+    let url = url.trim();
+
+    Url::parse(url)
+        .or_else(|_| Url::parse(&format!("http://{}", url)))
+        .ok()
+}
@@ -0,0 +1,128 @@
+use std::{cmp::Reverse, iter::Peekable, vec};
+
+use crate::types::{MessageEntity, MessageEntityKind};
+
+/// Markup language into which entities are rendered.
+pub(crate) trait Markup {
+    /// Escapes plain text, `in_code` is `true` inside of code entities.
+    fn escape(text: &str, in_code: bool) -> String;
+
+    /// Returns the opening tag of an entity, or `None` if the entity can't be
+    /// expressed in the markup (e.g. it's detected by Telegram automatically).
+    fn open(kind: &MessageEntityKind) -> Option<String>;
+
+    /// Returns the closing tag of an entity.
+    fn close(kind: &MessageEntityKind) -> String;
+
+    /// Appends a tag to the output.
+    fn push_tag(out: &mut String, tag: &str) {
+        out.push_str(tag);
+    }
+}
+
+/// Renders `text` with `entities` into the markup `M`.
+///
+/// Entities which intersect, but are not nested, are split into several
+/// entities.
+pub(crate) fn render<M: Markup>(text: &str, entities: &[MessageEntity]) -> String {
+    let mut entities: Vec<_> = entities
+        .iter()
+        .filter(|e| e.length != 0 && M::open(&e.kind).is_some())
+        .collect();
+    entities.sort_by_key(|e| (e.offset, Reverse(e.length)));
+
+    let mut renderer = Renderer::<M> {
+        text,
+        out: String::with_capacity(text.len()),
+        stack: Vec::new(),
+        flushed: 0,
+        entities: entities.into_iter().peekable(),
+        markup: std::marker::PhantomData,
+    };
+
+    let mut pos = 0;
+    for (idx, c) in text.char_indices() {
+        renderer.boundary(idx, pos);
+        pos += c.len_utf16();
+    }
+    renderer.finish()
+}
+
+struct Renderer<'a, M> {
+    text: &'a str,
+    out: String,
+    // Currently opened entities, outer first
+    stack: Vec<&'a MessageEntity>,
+    // Byte offset of text which is not yet written to `out`
+    flushed: usize,
+    // Entities which are not yet opened
+    entities: Peekable<vec::IntoIter<&'a MessageEntity>>,
+    markup: std::marker::PhantomData<M>,
+}
+
+impl<'a, M: Markup> Renderer<'a, M> {
+    /// Opens and closes entities at the char which starts at byte offset
+    /// `idx` and UTF-16 offset `pos`.
+    fn boundary(&mut self, idx: usize, pos: usize) {
+        let ends = self.stack.iter().position(|e| e.offset + e.length <= pos);
+        let starts = matches!(self.entities.peek(), Some(e) if e.offset <= pos);
+        if ends.is_none() && !starts {
+            return;
+        }
+
+        self.flush(idx);
+
+        if let Some(i) = ends {
+            // Entities opened after the ending one need to be closed first
+            let closed: Vec<_> = self.stack.drain(i..).collect();
+            for e in closed.iter().rev() {
+                M::push_tag(&mut self.out, &M::close(&e.kind));
+            }
+            for e in closed {
+                if e.offset + e.length > pos {
+                    self.open(e);
+                }
+            }
+        }
+
+        while let Some(e) = self.entities.next_if(|e| e.offset <= pos) {
+            // Code can't contain other entities
+            if !self.in_code() {
+                self.open(e);
+            }
+        }
+    }
+
+    fn open(&mut self, entity: &'a MessageEntity) {
+        if let Some(tag) = M::open(&entity.kind) {
+            M::push_tag(&mut self.out, &tag);
+            self.stack.push(entity);
+        }
+    }
+
+    fn flush(&mut self, idx: usize) {
+        let text = &self.text[self.flushed..idx];
+        if !text.is_empty() {
+            self.out.push_str(&M::escape(text, self.in_code()));
+        }
+        self.flushed = idx;
+    }
+
+    fn in_code(&self) -> bool {
+        self.stack.iter().any(|e| {
+            matches!(
+                e.kind,
+                MessageEntityKind::Code | MessageEntityKind::Pre { .. }
+            )
+        })
+    }
+
+    fn finish(mut self) -> String {
+        self.flush(self.text.len());
+        while let Some(e) = self.stack.pop() {
+            M::push_tag(&mut self.out, &M::close(&e.kind));
+        }
+
+        self.out
+    }
+}
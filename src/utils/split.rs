@@ -0,0 +1,430 @@
+//! Splitting of texts which are too long to be sent in one message.
+//!
+//! Telegram rejects messages longer than [`MAX_MESSAGE_LENGTH`] characters
+//! with [`ApiError::MessageIsTooLong`]. [`send_message`] splits such messages
+//! into several ones, preferably on paragraph, line or word boundaries and
+//! without breaking entities in half (unless an entity doesn't fit into one
+//! message).
+//!
+//! [`ApiError::MessageIsTooLong`]: crate::ApiError::MessageIsTooLong
+
+use crate::{
+    payloads::SendMessage,
+    requests::{HasPayload, Request, Requester},
+    types::{Message, MessageEntity, ParseMode},
+    utils::{html, markdown},
+};
+
+/// Maximum length of a message text, in UTF-16 code units (after parsing
+/// entities).
+pub const MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Sends a message, splitting it into several messages if it's longer than
+/// [`MAX_MESSAGE_LENGTH`].
+///
+/// See [`split_message`] for how the message is split. Messages are sent one
+/// by one, if sending fails, the error is returned and the rest of the
+/// messages are not sent.
+///
+/// Note that the payload is sent as is, so default parse mode set by the
+/// [`DefaultParseMode`] adaptor is not applied.
+///
+/// [`DefaultParseMode`]: crate::adaptors::DefaultParseMode
+///
+/// ## Examples
+///
+/// ```no_run
+/// use teloxide_core::{
+///     payloads::{SendMessage, SendMessageSetters},
+///     prelude::*,
+///     types::{ChatId, ParseMode},
+///     utils::split,
+/// };
+///
+/// # async {
+/// let bot = Bot::new("TOKEN");
+/// let report = "<b>Report</b>\n\n".to_owned() + &"Everything is fine.\n".repeat(1000);
+///
+/// let payload = SendMessage::new(ChatId(1), report).parse_mode(ParseMode::Html);
+/// let messages = split::send_message(&bot, payload).await?;
+/// assert_eq!(messages.len(), 5);
+/// # Ok::<_, teloxide_core::RequestError>(()) };
+/// ```
+pub async fn send_message<R>(bot: &R, payload: SendMessage) -> Result<Vec<Message>, R::Err>
+where
+    R: Requester,
+{
+    let mut messages = Vec::new();
+    for payload in split_message(payload) {
+        let mut request = bot.send_message(payload.chat_id.clone(), "");
+        *request.payload_mut() = payload;
+
+        messages.push(request.send().await?);
+    }
+
+    Ok(messages)
+}
+
+/// Splits a message into several messages, each at most
+/// [`MAX_MESSAGE_LENGTH`] characters long.
+///
+/// The message is returned as is if it's short enough. Otherwise:
+/// - if the message has [`ParseMode::Html`] or [`ParseMode::MarkdownV2`] parse
+///   mode, its text is parsed into entities (see [`utils`]),
+/// - the text is split with [`split_text`],
+/// - `reply_markup` is only kept in the last message and `reply_to_message_id`
+///   only in the first one.
+///
+/// Messages with the deprecated [`ParseMode::Markdown`] and messages which
+/// can't be parsed are returned as is.
+///
+/// [`utils`]: crate::utils
+pub fn split_message(mut payload: SendMessage) -> Vec<SendMessage> {
+    #[allow(deprecated)]
+    let parsed = match payload.parse_mode {
+        None => Ok((
+            payload.text.clone(),
+            payload.entities.clone().unwrap_or_default(),
+        )),
+        Some(ParseMode::Html) => html::parse(&payload.text),
+        Some(ParseMode::MarkdownV2) => markdown::parse(&payload.text),
+        Some(ParseMode::Markdown) => return vec![payload],
+    };
+    let (text, entities) = match parsed {
+        Ok(parsed) => parsed,
+        // Let Telegram report the error
+        Err(_) => return vec![payload],
+    };
+
+    if text.encode_utf16().count() <= MAX_MESSAGE_LENGTH {
+        return vec![payload];
+    }
+
+    payload.parse_mode = None;
+    let reply_markup = payload.reply_markup.take();
+
+    let mut messages: Vec<_> = split_text(&text, &entities, MAX_MESSAGE_LENGTH)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (text, entities))| SendMessage {
+            text,
+            entities: Some(entities).filter(|e| !e.is_empty()),
+            reply_to_message_id: payload.reply_to_message_id.filter(|_| i == 0),
+            ..payload.clone()
+        })
+        .collect();
+
+    if let Some(last) = messages.last_mut() {
+        last.reply_markup = reply_markup;
+    }
+
+    messages
+}
+
+/// Splits a text with entities into chunks which are at most `limit`
+/// characters (UTF-16 code units) long.
+///
+/// The text is split on the last paragraph boundary (empty line) which fits
+/// into the limit, or if there is none, on the last line boundary, or on the
+/// last whitespace, or in the last possible place. Boundaries in the second
+/// half of the limit are preferred, so that chunks are not too short. Places
+/// which are inside of entities are avoided, unless an entity doesn't fit into
+/// the limit itself.
+///
+/// Entities are re-based to their chunks. Splitting inside of an entity only
+/// happens as a last resort, when the entity is longer than the limit, in which
+/// case each chunk gets its part of the entity. Whitespace around split points
+/// is removed.
+///
+/// ## Panics
+///
+/// Panics if `limit` is less than 2 (a char might take 2 code units).
+///
+/// ## Examples
+///
+/// ```
+/// use teloxide_core::{
+///     types::{MessageEntity, MessageEntityKind::Bold},
+///     utils::split::split_text,
+/// };
+///
+/// let chunks = split_text(
+///     "first line\nsecond bold line",
+///     &[MessageEntity::new(Bold, 11, 16)],
+///     20,
+/// );
+///
+/// assert_eq!(
+///     chunks,
+///     [
+///         ("first line".to_owned(), vec![]),
+///         (
+///             "second bold line".to_owned(),
+///             vec![MessageEntity::new(Bold, 0, 16)]
+///         ),
+///     ]
+/// );
+/// ```
+pub fn split_text(
+    text: &str,
+    entities: &[MessageEntity],
+    limit: usize,
+) -> Vec<(String, Vec<MessageEntity>)> {
+    assert!(limit >= 2, "limit must be at least 2");
+
+    // UTF-16 and byte offsets of char boundaries
+    let mut boundaries: Vec<(usize, usize)> = Vec::with_capacity(text.len() + 1);
+    let mut utf16_len = 0;
+    for (idx, c) in text.char_indices() {
+        boundaries.push((utf16_len, idx));
+        utf16_len += c.len_utf16();
+    }
+    boundaries.push((utf16_len, text.len()));
+
+    let last = boundaries.len() - 1;
+    let char_before = |b: usize| text[..boundaries[b].1].chars().next_back();
+    let char_at = |b: usize| text[boundaries[b].1..].chars().next();
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let (start_pos, _) = boundaries[start];
+        let end = if utf16_len - start_pos <= limit {
+            last
+        } else {
+            let boundaries_after = |len: usize| {
+                start + boundaries[start..].partition_point(|&(pos, _)| pos - start_pos < len)
+            };
+            // The last boundary which fits into the limit
+            let max = boundaries_after(limit + 1) - 1;
+            // Splitting too early produces too many messages, so boundaries in
+            // the second half of the chunk are preferred
+            // (`limit >= 2`, so at least one char always fits, i.e. `start < max`)
+            let half = boundaries_after(limit / 2).max(start + 1).min(max);
+
+            find_split(start + 1, half, max, &boundaries, entities, char_before)
+        };
+
+        // Remove whitespace before the split point
+        let mut chunk_end = end;
+        if end != last {
+            while chunk_end > start + 1
+                && matches!(char_before(chunk_end), Some(c) if c.is_whitespace())
+            {
+                chunk_end -= 1;
+            }
+        }
+
+        chunks.push(chunk(
+            text,
+            entities,
+            boundaries[start],
+            boundaries[chunk_end],
+        ));
+
+        // Remove whitespace after the split point
+        start = end;
+        while start < last && matches!(char_at(start), Some(c) if c.is_whitespace()) {
+            start += 1;
+        }
+        if start == last {
+            break;
+        }
+    }
+
+    chunks
+}
+
+/// Finds the best place to split the text among boundaries `min..=max`,
+/// preferring boundaries `half..=max`.
+fn find_split(
+    min: usize,
+    half: usize,
+    max: usize,
+    boundaries: &[(usize, usize)],
+    entities: &[MessageEntity],
+    char_before: impl Fn(usize) -> Option<char>,
+) -> usize {
+    let inside_entity = |b: usize| {
+        let pos = boundaries[b].0;
+        entities
+            .iter()
+            .any(|e| e.offset < pos && pos < e.offset + e.length)
+    };
+    let paragraph = |b: usize| char_before(b) == Some('\n') && char_before(b - 1) == Some('\n');
+    let line = |b: usize| char_before(b) == Some('\n');
+    let word = |b: usize| matches!(char_before(b), Some(c) if c.is_whitespace());
+    let any = |_| true;
+
+    let kinds: [(&dyn Fn(usize) -> bool, usize); 7] = [
+        (&paragraph, half),
+        (&line, half),
+        (&word, half),
+        (&paragraph, min),
+        (&line, min),
+        (&word, min),
+        (&any, min),
+    ];
+    for respect_entities in [true, false] {
+        for (kind, min) in kinds {
+            let found = (min..=max)
+                .rev()
+                .find(|&b| kind(b) && !(respect_entities && inside_entity(b)));
+            if let Some(b) = found {
+                return b;
+            }
+        }
+    }
+
+    // `min..=max` is never empty and any boundary is accepted in the end
+    unreachable!()
+}
+
+/// Returns text between two boundaries, with entities re-based to it.
+fn chunk(
+    text: &str,
+    entities: &[MessageEntity],
+    (start_pos, start_idx): (usize, usize),
+    (end_pos, end_idx): (usize, usize),
+) -> (String, Vec<MessageEntity>) {
+    let entities = entities
+        .iter()
+        .filter_map(|e| {
+            let start = e.offset.max(start_pos);
+            let end = (e.offset + e.length).min(end_pos);
+
+            (start < end)
+                .then(|| MessageEntity::new(e.kind.clone(), start - start_pos, end - start))
+        })
+        .collect();
+
+    (text[start_idx..end_idx].to_owned(), entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        payloads::{SendMessage, SendMessageSetters},
+        types::{
+            ChatId, InlineKeyboardButton, InlineKeyboardMarkup, MessageEntity,
+            MessageEntityKind::*, ParseMode,
+        },
+        utils::split::{split_message, split_text, MAX_MESSAGE_LENGTH},
+    };
+
+    #[test]
+    fn boundaries() {
+        let text = "aaaa\n\nbbbb cccc\ndddd eeee";
+
+        // Paragraph, then line, then word boundaries
+        let chunks: Vec<_> = split_text(text, &[], 10)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert_eq!(chunks, ["aaaa", "bbbb cccc", "dddd eeee"]);
+
+        // Boundaries in the first half are used only if there are no others
+        let chunks: Vec<_> = split_text("a\n\nbbbb cccc", &[], 8)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert_eq!(chunks, ["a\n\nbbbb", "cccc"]);
+
+        // Words longer than the limit are split anywhere
+        let chunks: Vec<_> = split_text("🦀🦀🦀 a", &[], 4)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert_eq!(chunks, ["🦀🦀", "🦀 a"]);
+
+        // The smallest limit still fits any char
+        let chunks: Vec<_> = split_text("🦀🦀a", &[], 2)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect();
+        assert_eq!(chunks, ["🦀", "🦀", "a"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "limit must be at least 2")]
+    fn too_small_limit() {
+        split_text("abc", &[], 1);
+    }
+
+    #[test]
+    fn entities() {
+        // The entity is not broken, even though there is a boundary inside it
+        let chunks = split_text("aaa bbb ccc", &[MessageEntity::new(Italic, 4, 7)], 8);
+        assert_eq!(
+            chunks,
+            [
+                ("aaa".to_owned(), vec![]),
+                ("bbb ccc".to_owned(), vec![MessageEntity::new(Italic, 0, 7)]),
+            ]
+        );
+
+        // Entities longer than the limit are split
+        let chunks = split_text("aaaa bbbb", &[MessageEntity::new(Code, 0, 9)], 5);
+        assert_eq!(
+            chunks,
+            [
+                ("aaaa".to_owned(), vec![MessageEntity::new(Code, 0, 4)]),
+                ("bbbb".to_owned(), vec![MessageEntity::new(Code, 0, 4)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let markup = InlineKeyboardMarkup::new([[InlineKeyboardButton::callback("a", "b")]]);
+        let line = "<b>bold</b> line\n".repeat(MAX_MESSAGE_LENGTH / 4);
+
+        let payload = SendMessage::new(ChatId(1), &line)
+            .parse_mode(ParseMode::Html)
+            .reply_to_message_id(1)
+            .reply_markup(markup.clone());
+        let messages = split_message(payload);
+
+        assert_eq!(messages.len(), 3);
+        for (i, message) in messages.iter().enumerate() {
+            assert!(message.text.starts_with("bold line\n"));
+            assert!(message.text.encode_utf16().count() <= MAX_MESSAGE_LENGTH);
+            assert_eq!(message.parse_mode, None);
+            assert_eq!(
+                message.entities.as_ref().unwrap()[0],
+                MessageEntity::new(Bold, 0, 4)
+            );
+            assert_eq!(message.reply_to_message_id.is_some(), i == 0);
+            assert_eq!(message.reply_markup.is_some(), i == 2);
+        }
+
+        // Short messages are not changed
+        let payload = SendMessage::new(ChatId(1), "<b>a</b>").parse_mode(ParseMode::Html);
+        assert_eq!(split_message(payload.clone()), [payload]);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn send_message() {
+        use crate::{testing::MockBot, types::Message, utils::split};
+
+        let bot = MockBot::new();
+        bot.respond_with::<SendMessage, _>(|payload| {
+            let message = serde_json::json!({
+                "message_id": 1,
+                "date": 0,
+                "chat": { "id": 1, "first_name": "A", "type": "private" },
+                "text": payload.text,
+            });
+            Ok(serde_json::from_value::<Message>(message).unwrap())
+        });
+
+        let text = "a ".repeat(MAX_MESSAGE_LENGTH);
+        let messages = split::send_message(&bot, SendMessage::new(ChatId(1), text))
+            .await
+            .unwrap();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(bot.requests_of::<SendMessage>().len(), 2);
+    }
+}
@@ -0,0 +1,417 @@
+//! Receiving updates via webhooks.
+//!
+//! See [`Webhook`] for more.
+
+use std::{convert::Infallible, future::Future, net::SocketAddr, sync::Arc};
+
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, Stream};
+use hyper::{
+    body::HttpBody,
+    header::CONTENT_LENGTH,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use thiserror::Error;
+use tokio::sync::{mpsc, watch};
+use url::Url;
+
+use crate::{
+    requests::{Request as _, Requester},
+    types::Update,
+};
+
+/// Name of the header which contains secret token set by the `secret_token`
+/// parameter of `setWebhook` (Bot API 6.1).
+pub const SECRET_TOKEN_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+/// The maximum size of a request body, requests with bigger bodies are
+/// rejected with `413 Payload Too Large`.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Number of received updates which may wait in the stream, after that the
+/// server doesn't respond to Telegram until the updates are consumed.
+const UPDATES_CAPACITY: usize = 64;
+
+/// A minimal HTTP server that receives updates sent by Telegram to a webhook.
+///
+/// The server accepts `POST` requests to the configured path, checks the
+/// [`SECRET_TOKEN_HEADER`] header (if the secret token is set) and yields
+/// deserialized [`Update`]s from a stream.
+///
+/// Note that Telegram only sends updates via HTTPS, so this server must be
+/// behind a reverse proxy which terminates TLS.
+///
+/// ## Examples
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use teloxide_core::{webhook::Webhook, Bot};
+///
+/// # async {
+/// let bot = Bot::from_env();
+/// let url = "https://example.com/webhook".parse().unwrap();
+///
+/// let (updates, handle) = Webhook::new(([0, 0, 0, 0], 8443).into())
+///     .path("/webhook")
+///     .set_up(bot, url)
+///     .await?;
+///
+/// # let _ = handle;
+/// futures::pin_mut!(updates);
+/// while let Some(update) = updates.next().await {
+///     println!("{:?}", update);
+/// }
+/// # Ok::<_, Box<dyn std::error::Error>>(()) };
+/// ```
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    address: SocketAddr,
+    path: String,
+    secret_token: Option<String>,
+}
+
+/// A handle to a running [`Webhook`] server.
+#[derive(Clone, Debug)]
+pub struct WebhookHandle {
+    local_addr: SocketAddr,
+    shutdown: Arc<watch::Sender<bool>>,
+}
+
+/// An error returned from [`Webhook::set_up`].
+#[derive(Debug, Error)]
+pub enum SetUpError<E> {
+    /// Couldn't bind the server to the address.
+    #[error("Couldn't start the webhook server: {0}")]
+    Server(#[source] hyper::Error),
+
+    /// [`SetWebhook`] request failed.
+    ///
+    /// [`SetWebhook`]: crate::payloads::SetWebhook
+    #[error("Couldn't set webhook: {0}")]
+    SetWebhook(#[source] E),
+
+    /// The [secret token] is set, but it can't be passed to [`SetWebhook`]
+    /// yet, so Telegram wouldn't send it and all updates would be rejected.
+    ///
+    /// [secret token]: Webhook::secret_token
+    /// [`SetWebhook`]: crate::payloads::SetWebhook
+    #[error("Secret token is not supported by `Webhook::set_up`")]
+    SecretTokenUnsupported,
+}
+
+impl Webhook {
+    /// Creates new webhook server listening on `address`.
+    ///
+    /// By default the server accepts requests to `/` and doesn't check secret
+    /// token.
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            path: "/".to_owned(),
+            secret_token: None,
+        }
+    }
+
+    /// Sets the path at which updates are accepted.
+    pub fn path(mut self, val: impl Into<String>) -> Self {
+        self.path = val.into();
+        self
+    }
+
+    /// Sets the secret token which must be sent in the
+    /// [`SECRET_TOKEN_HEADER`] header of each request.
+    ///
+    /// Note that Telegram only sends this header if the token was passed to
+    /// `setWebhook` (Bot API 6.1), which is not supported by [`SetWebhook`]
+    /// yet, so [`Webhook::set_up`] returns an error if the token is set.
+    ///
+    /// [`SetWebhook`]: crate::payloads::SetWebhook
+    pub fn secret_token(mut self, val: impl Into<String>) -> Self {
+        self.secret_token = Some(val.into());
+        self
+    }
+
+    /// Starts the server (spawning it with `tokio::spawn`).
+    ///
+    /// Returns a stream of received updates alongside with a handle that allows
+    /// to stop the server. The stream ends after the server is stopped.
+    pub fn into_stream(
+        self,
+    ) -> Result<(impl Stream<Item = Update> + Send + 'static, WebhookHandle), hyper::Error> {
+        self.spawn(async {})
+    }
+
+    /// Starts the server, sets up the webhook using [`SetWebhook`] and
+    /// removes it using [`DeleteWebhook`] when the server is stopped.
+    ///
+    /// To use other options of [`SetWebhook`], call it manually and use
+    /// [`into_stream`](Self::into_stream).
+    ///
+    /// Returns [`SetUpError::SecretTokenUnsupported`] if the [secret token] is
+    /// set.
+    ///
+    /// [secret token]: Webhook::secret_token
+    /// [`SetWebhook`]: crate::payloads::SetWebhook
+    /// [`DeleteWebhook`]: crate::payloads::DeleteWebhook
+    pub async fn set_up<R>(
+        self,
+        bot: R,
+        url: Url,
+    ) -> Result<(impl Stream<Item = Update> + Send + 'static, WebhookHandle), SetUpError<R::Err>>
+    where
+        R: Requester + Send + Sync + 'static,
+        R::DeleteWebhook: Send,
+    {
+        if self.secret_token.is_some() {
+            return Err(SetUpError::SecretTokenUnsupported);
+        }
+
+        bot.set_webhook(url)
+            .send()
+            .await
+            .map_err(SetUpError::SetWebhook)?;
+
+        let delete_webhook = async move {
+            if let Err(err) = bot.delete_webhook().send().await {
+                log::error!("Couldn't delete webhook: {}", err);
+            }
+        };
+
+        self.spawn(delete_webhook).map_err(SetUpError::Server)
+    }
+
+    fn spawn<F>(
+        self,
+        on_stop: F,
+    ) -> Result<(impl Stream<Item = Update> + Send + 'static, WebhookHandle), hyper::Error>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let builder = Server::try_bind(&self.address)?;
+
+        let (tx, rx) = mpsc::channel(UPDATES_CAPACITY);
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+
+        let this = Arc::new(self);
+        let make_service = make_service_fn(move |_| {
+            let this = Arc::clone(&this);
+            let tx = tx.clone();
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    handle(Arc::clone(&this), tx.clone(), req)
+                }))
+            }
+        });
+
+        let server = builder.serve(make_service);
+        let local_addr = server.local_addr();
+        let server = server.with_graceful_shutdown(async move {
+            // Error means that all handles were dropped, so the server can't be stopped
+            while shutdown_rx.changed().await.is_ok() {
+                if *shutdown_rx.borrow() {
+                    return;
+                }
+            }
+            futures::future::pending().await
+        });
+
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                log::error!("Webhook server error: {}", err);
+            }
+
+            on_stop.await;
+        });
+
+        let updates = stream::unfold(rx, |mut rx| async {
+            rx.recv().await.map(|update| (update, rx))
+        });
+        let handle = WebhookHandle {
+            local_addr,
+            shutdown: Arc::new(shutdown_tx),
+        };
+
+        Ok((updates, handle))
+    }
+}
+
+impl WebhookHandle {
+    /// Returns the address the server is listening on.
+    ///
+    /// This is useful if the server was bound to port `0`.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Gracefully stops the server.
+    ///
+    /// If the server was started with [`Webhook::set_up`], the webhook is
+    /// deleted after the server is stopped.
+    pub fn shutdown(&self) {
+        // Error means that the server was already stopped
+        let _ = self.shutdown.send(true);
+    }
+}
+
+async fn handle(
+    webhook: Arc<Webhook>,
+    tx: mpsc::Sender<Update>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    if req.uri().path() != webhook.path {
+        return Ok(status(StatusCode::NOT_FOUND));
+    }
+
+    if req.method() != Method::POST {
+        return Ok(status(StatusCode::METHOD_NOT_ALLOWED));
+    }
+
+    if let Some(expected) = &webhook.secret_token {
+        let token = req.headers().get(SECRET_TOKEN_HEADER);
+        let valid = matches!(token, Some(t) if constant_time_eq(t.as_bytes(), expected.as_bytes()));
+        if !valid {
+            return Ok(status(StatusCode::UNAUTHORIZED));
+        }
+    }
+
+    let body = match read_body(req).await {
+        Ok(Some(body)) => body,
+        Ok(None) => return Ok(status(StatusCode::PAYLOAD_TOO_LARGE)),
+        Err(err) => {
+            log::error!("Couldn't read webhook request body: {}", err);
+            return Ok(status(StatusCode::BAD_REQUEST));
+        }
+    };
+
+    match serde_json::from_slice::<Update>(&body) {
+        Ok(update) => {
+            // Error means that the stream was dropped, we can't do anything about it
+            let _ = tx.send(update).await;
+        }
+        // Responding with an error would make Telegram resend the same update over and
+        // over again, so we only log the error
+        Err(err) => log::error!(
+            "Couldn't parse an update: {} (raw: {:?})",
+            err,
+            String::from_utf8_lossy(&body)
+        ),
+    }
+
+    Ok(status(StatusCode::OK))
+}
+
+/// Reads the body of `req`, returns `None` if it's bigger than
+/// [`MAX_BODY_SIZE`].
+async fn read_body(req: Request<Body>) -> Result<Option<Bytes>, hyper::Error> {
+    let content_length = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok()?.parse::<usize>().ok());
+    if matches!(content_length, Some(len) if len > MAX_BODY_SIZE) {
+        return Ok(None);
+    }
+
+    let mut body = req.into_body();
+    let mut buf = BytesMut::with_capacity(content_length.unwrap_or(0));
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if buf.len() + chunk.len() > MAX_BODY_SIZE {
+            return Ok(None);
+        }
+
+        buf.extend_from_slice(&chunk);
+    }
+
+    Ok(Some(buf.freeze()))
+}
+
+/// Compares `a` and `b` in time which doesn't depend on their contents (but
+/// depends on their lengths).
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use reqwest::StatusCode;
+
+    use crate::webhook::{Webhook, MAX_BODY_SIZE, SECRET_TOKEN_HEADER};
+
+    const UPDATE: &str = r#"{
+        "update_id": 892252934,
+        "message": {
+            "message_id": 6557,
+            "from": { "id": 218485655, "is_bot": false, "first_name": "Waffle" },
+            "chat": { "id": 218485655, "first_name": "Waffle", "type": "private" },
+            "date": 1569518342,
+            "text": "hello there"
+        }
+    }"#;
+
+    #[tokio::test]
+    async fn receives_updates() {
+        let (updates, handle) = Webhook::new(([127, 0, 0, 1], 0).into())
+            .path("/hook")
+            .secret_token("secret")
+            .into_stream()
+            .unwrap();
+        futures::pin_mut!(updates);
+
+        let url = format!("http://{}/hook", handle.local_addr());
+        let client = reqwest::Client::new();
+
+        let res = client.post(&url).body(UPDATE).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = client
+            .post(&url)
+            .header(SECRET_TOKEN_HEADER, "secret")
+            .body(UPDATE)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client.get(&url).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let res = client
+            .post(&url)
+            .header(SECRET_TOKEN_HEADER, "secret")
+            .body(vec![b' '; MAX_BODY_SIZE + 1])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let update = updates.next().await.unwrap();
+        assert_eq!(update.id, 892252934);
+
+        handle.shutdown();
+        assert!(updates.next().await.is_none());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn set_up_with_secret_token() {
+        use crate::{testing::MockBot, webhook::SetUpError};
+
+        let bot = MockBot::new();
+        let res = Webhook::new(([127, 0, 0, 1], 0).into())
+            .secret_token("secret")
+            .set_up(bot.clone(), "https://example.com".parse().unwrap())
+            .await;
+
+        assert!(matches!(res, Err(SetUpError::SecretTokenUnsupported)));
+        bot.assert_no_requests();
+    }
+}